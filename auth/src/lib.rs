@@ -1,14 +1,22 @@
 #![allow(clippy::or_fun_call)]
+use std::env;
 use std::ffi::OsString;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use anyhow::Context as _;
+use radicle_common::signer::external::ExternalSigner;
 use radicle_common::signer::ToSigner;
 
+use librad::crypto::keystore::pinentry::SecUtf8;
+use librad::crypto::BoxedSigner;
+use librad::git::storage::Storage;
 use librad::PeerId;
 
 use radicle_common::args::{Args, Error, Help};
-use radicle_common::{config, git, keys, person, profile};
+use radicle_common::keys::SshAuthSock;
+use radicle_common::profile::Profile;
+use radicle_common::{bundle, config, device, git, keys, person, profile, rotation};
 use radicle_terminal as term;
 
 pub const HELP: Help = Help {
@@ -19,6 +27,8 @@ pub const HELP: Help = Help {
 Usage
 
     rad auth [--init | --active] [<options>...] [<peer-id>]
+    rad auth --link-device [<file>]
+    rad auth --rotate
 
     A passphrase may be given via the environment variable `RAD_PASSPHRASE` or
     via the standard input stream if `--stdin` is used. Using one of these
@@ -27,9 +37,43 @@ Usage
     If `--init` is used, a name may be given via the `--name` option. Using
     this disables the input prompt.
 
+    To use your identity from a new device, link the device to it, in three steps:
+
+    1. On the new device, after `rad auth --init`, run `rad auth --link-device`.
+       This writes a link request signed by the device, to the working directory.
+    2. On a device already linked to your identity, run `rad auth --link-device`
+       with the request file. The device key is added to your identity, which is
+       written to a bundle next to the request.
+    3. On the new device, run `rad auth --link-device` with the bundle. The
+       identity is signed and becomes the device's identity. A bundle is written
+       back, to be given to the other device with `rad auth --link-device`.
+
+    Files can be exchanged in any way, eg. on a USB stick; they contain no secrets.
+
+    If your key is compromised, rotate it with `--rotate`. A new profile is created
+    with a new key, which replaces the old key in your identity and projects, and
+    becomes the active profile. The old key is recorded as revoked in both profiles:
+    the old profile can no longer sign, and commits signed by the old key no longer
+    verify locally. It is also revoked in the `.gitrevoked` file of the working copy,
+    if any; other working copies and other machines only reject the old key once
+    that file is committed and shared.
+
+    To sign with a key held outside of the profile, eg. in a hardware token, configure
+    an external signer in the profile's `config.toml`:
+
+        [signer]
+        program = "/path/to/signer"
+        args = []
+
+    The signer is run for each signature, and must hold the profile's key. Since
+    profiles are created with a key generated in software, a hardware token can only be
+    used if that key is imported into it after `rad auth --init`.
+
 Options
 
     --init                  Initialize a new identity
+    --link-device           Link a device to your identity (see above)
+    --rotate                Rotate the key of the active profile (see above)
     --active                Authenticate with the currently active profile
     --stdin                 Read passphrase from stdin (default: false)
     --name <name>           Use given name (default: none)
@@ -40,6 +84,9 @@ Options
 #[derive(Debug)]
 pub struct Options {
     pub init: bool,
+    pub link_device: bool,
+    pub link_file: Option<PathBuf>,
+    pub rotate: bool,
     pub active: bool,
     pub stdin: bool,
     pub name: Option<String>,
@@ -51,6 +98,9 @@ impl Args for Options {
         use lexopt::prelude::*;
 
         let mut init = false;
+        let mut link_device = false;
+        let mut link_file = None;
+        let mut rotate = false;
         let mut active = false;
         let mut stdin = false;
         let mut name = None;
@@ -62,6 +112,12 @@ impl Args for Options {
                 Long("init") => {
                     init = true;
                 }
+                Long("link-device") => {
+                    link_device = true;
+                }
+                Long("rotate") => {
+                    rotate = true;
+                }
                 Long("active") => {
                     active = true;
                 }
@@ -80,6 +136,9 @@ impl Args for Options {
                 Long("help") => {
                     return Err(Error::Help.into());
                 }
+                Value(val) if link_device => {
+                    link_file = Some(PathBuf::from(val));
+                }
                 Value(val) => {
                     let string = val.to_str().ok_or_else(|| {
                         anyhow::anyhow!("invalid UTF-8 string specified for peer id")
@@ -95,6 +154,9 @@ impl Args for Options {
         Ok((
             Options {
                 init,
+                link_device,
+                link_file,
+                rotate,
                 active,
                 stdin,
                 name,
@@ -111,7 +173,17 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
         _ => vec![],
     };
 
-    if options.init || profiles.is_empty() {
+    if options.link_device {
+        if options.init {
+            anyhow::bail!("`--link-device` can't be used with `--init`");
+        }
+        link_device(options.link_file, ctx)
+    } else if options.rotate {
+        if options.init {
+            anyhow::bail!("`--rotate` can't be used with `--init`");
+        }
+        rotate(options, ctx)
+    } else if options.init || profiles.is_empty() {
         if options.peer_id.is_some() {
             anyhow::bail!("you may not specify a peer id when initializing a new identity");
         }
@@ -135,11 +207,11 @@ pub fn init(options: Options) -> anyhow::Result<()> {
         term::blank();
     }
 
-    let name = sanitize_name(
-        options
-            .name
-            .unwrap_or_else(|| term::text_input("Name", None).unwrap()),
-    )?;
+    let name = match options.name {
+        Some(name) => name,
+        None => term::text_input_for("--name", "Name", None)?,
+    };
+    let name = sanitize_name(name)?;
 
     let passphrase = term::read_passphrase(options.stdin, true)?;
     let secret = keys::pwhash(passphrase.clone());
@@ -249,7 +321,12 @@ pub fn authenticate(
     }
 
     let profile = selection;
-    if let Ok(sock) = keys::ssh_auth_sock() {
+    if let Some(external) = ExternalSigner::load(profile)? {
+        term::success!(
+            "Signing with external signer for key {}",
+            term::format::tertiary(external.peer_id())
+        );
+    } else if let Ok(sock) = keys::ssh_auth_sock() {
         if !keys::is_ready(profile, sock.clone())? {
             term::warning("Adding your radicle key to ssh-agent...");
 
@@ -274,6 +351,263 @@ pub fn authenticate(
     Ok(())
 }
 
+/// Link a device to a person identity. Depending on the given file, this runs the
+/// step of the exchange for the new device, or the one for the linked device.
+pub fn link_device(file: Option<PathBuf>, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let signer = term::signer(&profile)?;
+    // Nb. Files of the exchange are written next to each other, so we work with
+    // absolute paths.
+    let file = file
+        .map(|path| {
+            path.canonicalize()
+                .with_context(|| format!("{} could not be read", path.display()))
+        })
+        .transpose()?;
+
+    match file {
+        // New device: create a link request.
+        None => {
+            let request = device::Request::new(&signer)?;
+            let path = env::current_dir()?.join(format!("rad-link-{}.json", request.peer()));
+
+            request.save(&path)?;
+
+            term::success!(
+                "Link request written to {}",
+                term::format::highlight(path.display())
+            );
+            term::tip!(
+                "Run `rad auth --link-device {}` on a device linked to your identity",
+                path.display()
+            );
+        }
+        // Either device: sign the identity bundled by the other device.
+        Some(path) if bundle::Manifest::path(&path).exists() => {
+            let storage = keys::storage(&profile, signer.clone())?;
+            let manifest = bundle::import(storage.read_only(), &path)?;
+            let accepted = device::accept(&storage, manifest.urn(), *manifest.peer())?;
+            let urn = accepted.person.urn();
+
+            term::success!(
+                "This device is linked to {} {}",
+                term::format::highlight(&accepted.person.subject().name),
+                term::format::dim(&urn)
+            );
+
+            if accepted.signed {
+                let reply = path.with_file_name(format!("rad-link-{}.bundle", storage.peer_id()));
+                bundle::export(storage.read_only(), &signer, &urn, &reply)?;
+
+                term::success!(
+                    "Signed identity written to {}",
+                    term::format::highlight(reply.display())
+                );
+                term::tip!(
+                    "Run `rad auth --link-device {}` on the other device to complete the link",
+                    reply.display()
+                );
+            } else {
+                term::tip!("To publish your identity, run `rad sync {}`", urn);
+            }
+        }
+        // Linked device: add the new device to the identity.
+        Some(path) => {
+            let request = device::Request::load(&path)?;
+            let storage = keys::storage(&profile, signer.clone())?;
+            let whoami = person::local(&storage)?;
+
+            term::info!(
+                "Linking device {} to {} {}",
+                term::format::tertiary(request.peer()),
+                term::format::highlight(&whoami.subject().name),
+                term::format::dim(whoami.urn())
+            );
+            if !term::confirm("Link device?")? {
+                anyhow::bail!("device link aborted");
+            }
+
+            let person = device::link(&storage, &request)?;
+            let out = path.with_file_name(format!("rad-link-{}.bundle", storage.peer_id()));
+            bundle::export(storage.read_only(), &signer, &person.urn(), &out)?;
+
+            term::success!(
+                "Device {} added to your identity",
+                term::format::tertiary(request.peer())
+            );
+            term::tip!(
+                "Run `rad auth --link-device {}` on the new device",
+                out.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Rotate the key of the active profile. A new profile is created with a new key, which
+/// replaces the old key, and is activated.
+pub fn rotate(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let signer = term::signer(&profile)?;
+    let storage = keys::storage(&profile, signer.clone())?;
+    let whoami = person::local(&storage)?;
+
+    term::info!(
+        "Rotating the key {} of {} {}",
+        term::format::tertiary(storage.peer_id()),
+        term::format::highlight(&whoami.subject().name),
+        term::format::dim(whoami.urn())
+    );
+    term::warning("The old key will be replaced in your identity and projects, and revoked.");
+
+    if !term::confirm("Rotate key?")? {
+        anyhow::bail!("key rotation aborted");
+    }
+
+    let sock = keys::ssh_auth_sock().ok();
+    let passphrase = term::read_passphrase(options.stdin, true)?;
+    let secret = keys::pwhash(passphrase.clone());
+
+    let spinner = term::spinner("Creating your new 🌱 Ed25519 keypair...");
+    let (rotated, _) = profile::create(profile::home(), secret.clone())?;
+    spinner.finish();
+
+    let rotation = match migrate(
+        &profile,
+        &storage,
+        &signer,
+        &rotated,
+        passphrase,
+        sock.clone(),
+    ) {
+        Ok(rotation) => rotation,
+        Err(err) => {
+            // The new profile is incomplete: remove it, along with its key, and go back
+            // to the old profile. Identity updates made in the old profile are rolled
+            // back by the rotation itself.
+            if let Some(sock) = sock {
+                keys::remove(&rotated, secret, sock).ok();
+            }
+            profile::set(profile.id())?;
+            profile::remove(&rotated)?;
+
+            return Err(err);
+        }
+    };
+
+    // Record the revocation in both profiles, so that the old profile can't sign
+    // anymore, and the old key is rejected even if the old profile is removed.
+    keys::revoke(&profile, &rotation.old)?;
+    keys::revoke(&rotated, &rotation.old)?;
+
+    term::success!(
+        "Key {} replaced with {}",
+        term::format::tertiary(rotation.old),
+        term::format::tertiary(rotation.new)
+    );
+    term::success!(
+        "Profile {} created and activated",
+        term::format::highlight(rotated.id())
+    );
+    for urn in &rotation.projects {
+        term::success!("Project {} migrated", term::format::dim(urn));
+    }
+    for urn in &rotation.skipped {
+        term::info!(
+            "Project {} not migrated, since you aren't one of its delegates; clone it again to use it from the new profile",
+            term::format::dim(urn)
+        );
+    }
+
+    // Revoke the old key in the working copy, if it uses signed commits.
+    if let Ok((_, repo)) = radicle_common::project::cwd() {
+        if let Some(path) = repo.workdir() {
+            if git::read_gitsigners(path).is_ok() {
+                git::add_gitsigners(path, [&rotation.new])?;
+                git::revoke_gitsigners(path, [&rotation.old])?;
+                git::configure_signing(path, &rotation.new)?;
+                git::configure_revocation(path)?;
+
+                term::success!(
+                    "Key {} revoked in {}",
+                    term::format::tertiary(rotation.old),
+                    term::format::tertiary(git::GITREVOKED)
+                );
+                term::tip!(
+                    "Commit the {} and {} files to share the revocation",
+                    term::format::tertiary(".gitsigners"),
+                    term::format::tertiary(git::GITREVOKED)
+                );
+            }
+        }
+    }
+
+    term::blank();
+    for urn in &rotation.pending {
+        term::warning(&format!(
+            "Project {} awaits the signatures of its other delegates",
+            urn
+        ));
+    }
+    term::warning(&format!(
+        "Other working copies still accept commits signed by {} until they have the committed {} file",
+        rotation.old,
+        git::GITREVOKED
+    ));
+    term::info!(
+        "The old profile {} is kept, but is no longer active, and can no longer sign.",
+        term::format::dim(profile.id())
+    );
+    term::tip!("To publish your identity and projects, run `rad sync` for each of them.");
+
+    Ok(())
+}
+
+/// Migrate the old profile to the newly created, rotated profile.
+fn migrate(
+    profile: &Profile,
+    storage: &Storage,
+    signer: &BoxedSigner,
+    rotated: &Profile,
+    passphrase: SecUtf8,
+    sock: Option<SshAuthSock>,
+) -> anyhow::Result<rotation::Rotation> {
+    let new_signer = if let Some(sock) = sock {
+        let spinner = term::spinner("Adding to ssh-agent...");
+
+        keys::add(rotated, keys::pwhash(passphrase), sock.clone())?;
+        let signer = sock.to_signer(rotated)?;
+
+        spinner.finish();
+        signer
+    } else {
+        keys::load_secret_key(rotated, passphrase)?.to_signer(rotated)?
+    };
+
+    // Carry over the seeds of the old profile.
+    match config::Config::profile(profile) {
+        Ok(cfg) => cfg.write(config::Config::path(rotated))?,
+        Err(_) => {
+            config::Config::init(rotated)?;
+        }
+    }
+
+    let spinner = term::spinner("Migrating your identity and projects...");
+    let new_storage = keys::storage(rotated, new_signer)?;
+
+    match rotation::rotate(storage, signer, &new_storage) {
+        Ok(rotation) => {
+            spinner.finish();
+            Ok(rotation)
+        }
+        Err(err) => {
+            spinner.failed();
+            Err(err.into())
+        }
+    }
+}
+
 fn sanitize_name(name: String) -> anyhow::Result<String> {
     if name.contains(char::is_whitespace) {
         anyhow::bail!("Name cannot contain whitespaces");
@@ -294,6 +628,9 @@ mod tests {
         Options {
             active: false,
             init: true,
+            link_device: false,
+            link_file: None,
+            rotate: false,
             stdin: false,
             name: Some(name.to_owned()),
             peer_id: None,
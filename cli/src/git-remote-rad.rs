@@ -6,7 +6,8 @@ use librad::profile::{LnkHome, LNK_HOME};
 use link_identities::git::Urn;
 use radicle_git_helpers::remote_helper;
 
-use radicle_common::{keys, profile, signer::ToSigner as _};
+use radicle_common::signer::{external::ExternalSigner, ToSigner as _};
+use radicle_common::{keys, profile};
 
 use anyhow::anyhow;
 #[cfg(feature = "ethereum")]
@@ -142,7 +143,11 @@ fn run(remote: Remote) -> anyhow::Result<()> {
         }
         Remote::Project { urn: _urn } => {
             let profile = profile::default()?;
-            let signer = if let Ok(sock) = keys::ssh_auth_sock() {
+            keys::ensure_not_revoked(&profile)?;
+
+            let signer = if let Some(external) = ExternalSigner::load(&profile)? {
+                external.to_signer(&profile)?
+            } else if let Ok(sock) = keys::ssh_auth_sock() {
                 sock.to_signer(&profile)?
             } else if let Ok(pass) = env::var(keys::RAD_PASSPHRASE) {
                 keys::load_secret_key(&profile, SecUtf8::from(pass))?.to_signer(&profile)?
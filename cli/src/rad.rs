@@ -3,7 +3,7 @@ use std::{io::ErrorKind, iter, process};
 
 use anyhow::anyhow;
 use rad_help::*;
-use radicle_common::profile;
+use radicle_common::{config, profile};
 use radicle_terminal as term;
 
 pub const NAME: &str = "rad";
@@ -19,7 +19,10 @@ enum Command {
 }
 
 fn main() {
-    match parse_args().map_err(Some).and_then(run) {
+    match parse_args(lexopt::Parser::from_env())
+        .map_err(Some)
+        .and_then(run)
+    {
         Ok(_) => process::exit(0),
         Err(err) => {
             if let Some(err) = err {
@@ -30,10 +33,9 @@ fn main() {
     }
 }
 
-fn parse_args() -> anyhow::Result<Command> {
+fn parse_args(mut parser: lexopt::Parser) -> anyhow::Result<Command> {
     use lexopt::prelude::*;
 
-    let mut parser = lexopt::Parser::from_env();
     let mut command = None;
 
     while let Some(arg) = parser.next()? {
@@ -44,6 +46,20 @@ fn parse_args() -> anyhow::Result<Command> {
             Long("version") => {
                 command = Some(Command::Version);
             }
+            Long("profile") if command.is_none() => {
+                let id = parser.value()?;
+                let id = id
+                    .to_str()
+                    .ok_or_else(|| anyhow!("invalid profile id specified with `--profile`"))?;
+
+                term::select_profile(id)?;
+            }
+            Long("no-prompt") if command.is_none() => {
+                term::disable_prompts();
+            }
+            Long("yes") | Short('y') if command.is_none() => {
+                term::accept_confirmations();
+            }
             Value(val) if command.is_none() => {
                 if val == *"." {
                     command = Some(Command::Other(vec![OsString::from("inspect")]));
@@ -90,7 +106,7 @@ fn run(command: Command) -> Result<(), Option<anyhow::Error>> {
             let exe = args.first();
 
             if let Some(Some(exe)) = exe.map(|s| s.to_str()) {
-                run_other(exe, &args[1..])?;
+                run_other(exe, &args[1..], &[])?;
             } else {
                 print_help()?;
             }
@@ -100,7 +116,10 @@ fn run(command: Command) -> Result<(), Option<anyhow::Error>> {
     Ok(())
 }
 
-fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>> {
+/// Run the given command. Commands that aren't built in are looked up in the aliases of the
+/// default profile, and else run as external commands. The aliases already expanded are
+/// given, to detect alias loops.
+fn run_other(exe: &str, args: &[OsString], expanded: &[&str]) -> Result<(), Option<anyhow::Error>> {
     match exe {
         #[cfg(feature = "ethereum")]
         "account" => {
@@ -135,6 +154,14 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "cob" => {
+            term::run_command_args::<rad_cob::Options, _>(
+                rad_cob::HELP,
+                "Command",
+                rad_cob::run,
+                args.to_vec(),
+            );
+        }
         "comment" => {
             term::run_command_args::<rad_comment::Options, _>(
                 rad_comment::HELP,
@@ -143,6 +170,30 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "completions" => {
+            term::run_command_args::<rad_completions::Options, _>(
+                rad_completions::HELP,
+                "Completion",
+                rad_completions::run,
+                args.to_vec(),
+            );
+        }
+        "__complete" => {
+            term::run_command_args::<rad_completions::complete::Options, _>(
+                rad_completions::complete::HELP,
+                "Completion",
+                rad_completions::complete::run,
+                args.to_vec(),
+            );
+        }
+        "delegate" => {
+            term::run_command_args::<rad_delegate::Options, _>(
+                rad_delegate::HELP,
+                "Delegation",
+                rad_delegate::run,
+                args.to_vec(),
+            );
+        }
         "edit" => {
             term::run_command_args::<rad_edit::Options, _>(
                 rad_edit::HELP,
@@ -177,6 +228,14 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "identity" => {
+            term::run_command_args::<rad_identity::Options, _>(
+                rad_identity::HELP,
+                "Identity",
+                rad_identity::run,
+                args.to_vec(),
+            );
+        }
         "init" => {
             term::run_command_args::<rad_init::Options, _>(
                 rad_init::HELP,
@@ -233,6 +292,14 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "profile" => {
+            term::run_command_args::<rad_profile::Options, _>(
+                rad_profile::HELP,
+                "Profile",
+                rad_profile::run,
+                args.to_vec(),
+            );
+        }
         "pull" => {
             term::run_command_args::<rad_pull::Options, _>(
                 rad_pull::HELP,
@@ -273,6 +340,14 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "seed" => {
+            term::run_command_args::<rad_seed::Options, _>(
+                rad_seed::HELP,
+                "Seed",
+                rad_seed::run,
+                args.to_vec(),
+            );
+        }
         "self" => {
             term::run_command_args::<rad_self::Options, _>(
                 rad_self::HELP,
@@ -305,9 +380,49 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
                 args.to_vec(),
             );
         }
+        "verify" => {
+            term::run_command_args::<rad_verify::Options, _>(
+                rad_verify::HELP,
+                "Verification",
+                rad_verify::run,
+                args.to_vec(),
+            );
+        }
         _ => {
+            if let Some(alias) = alias(exe)? {
+                let expanded = expanded
+                    .iter()
+                    .copied()
+                    .chain(iter::once(exe))
+                    .collect::<Vec<_>>();
+
+                if expanded[..expanded.len() - 1].contains(&exe) {
+                    return Err(Some(anyhow!(
+                        "alias `{}` expands to itself, via `{}`",
+                        exe,
+                        expanded.join("` -> `")
+                    )));
+                }
+                let (cmd, alias_args) = alias
+                    .split_first()
+                    .ok_or_else(|| anyhow!("alias `{}` is empty", exe))?;
+                let args = alias_args
+                    .iter()
+                    .map(OsString::from)
+                    .chain(args.iter().cloned())
+                    .collect::<Vec<_>>();
+
+                return run_other(cmd, &args, &expanded);
+            }
+
             let exe = format!("{}-{}", NAME, exe);
-            let status = process::Command::new(exe.clone()).args(args).status();
+            let mut cmd = process::Command::new(exe.clone());
+
+            // Let the external command run against the same profile as us.
+            if let Ok(profile) = profile::default() {
+                cmd.env(profile::RAD_PROFILE, profile.id().to_string());
+            }
+            let status = cmd.args(args).status();
 
             match status {
                 Ok(status) => {
@@ -327,3 +442,71 @@ fn run_other(exe: &str, args: &[OsString]) -> Result<(), Option<anyhow::Error>>
     }
     Ok(())
 }
+
+/// Get the command and arguments of the given alias, as configured in the default profile.
+fn alias(name: &str) -> anyhow::Result<Option<Vec<String>>> {
+    let profile = match profile::default() {
+        Ok(profile) => profile,
+        Err(_) => return Ok(None),
+    };
+    match config::Config::profile(&profile) {
+        Ok(config) => config
+            .alias(name)
+            .map_err(|err| anyhow!("invalid alias `{}`: {}", name, err)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(anyhow!(
+            "failed to read aliases from {}: {}",
+            config::Config::path(&profile).display(),
+            err
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Vec<OsString> {
+        match parse_args(lexopt::Parser::from_args(args.iter().copied())).unwrap() {
+            Command::Other(args) => args,
+            other => panic!("unexpected command {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_prompt_options() {
+        // Nb. Prompt options are passed to commands through the environment, so these
+        // cases are tested in sequence, in a single test.
+        std::env::remove_var(term::RAD_YES);
+        std::env::remove_var(term::RAD_NO_PROMPT);
+
+        // Options after the command belong to the command.
+        assert_eq!(parse(&["rm", "--yes"]), vec!["rm", "--yes"]);
+        assert!(std::env::var_os(term::RAD_YES).is_none());
+        assert!(std::env::var_os(term::RAD_NO_PROMPT).is_none());
+
+        assert_eq!(parse(&["--no-prompt", "issue"]), vec!["issue"]);
+        assert!(std::env::var_os(term::RAD_NO_PROMPT).is_some());
+        assert!(std::env::var_os(term::RAD_YES).is_none());
+        assert!(!term::interactive());
+
+        std::env::remove_var(term::RAD_NO_PROMPT);
+
+        for option in ["--yes", "-y"] {
+            assert_eq!(
+                parse(&[
+                    option,
+                    "rm",
+                    "rad:git:hnrkmg77m8tfzj4gi4pa4mbhgysfgzwntjpao"
+                ]),
+                vec!["rm", "rad:git:hnrkmg77m8tfzj4gi4pa4mbhgysfgzwntjpao"]
+            );
+            assert!(std::env::var_os(term::RAD_YES).is_some());
+            assert!(std::env::var_os(term::RAD_NO_PROMPT).is_some());
+            assert!(term::confirm("Remove?").unwrap());
+
+            std::env::remove_var(term::RAD_YES);
+            std::env::remove_var(term::RAD_NO_PROMPT);
+        }
+    }
+}
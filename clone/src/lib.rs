@@ -1,17 +1,19 @@
 #![allow(clippy::or_fun_call)]
 use std::convert::TryFrom;
 use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use anyhow::anyhow;
 use librad::git::tracking;
+use librad::git::Storage;
 use librad::git::Urn;
 use url::Url;
 
 use radicle_common::args::{Args, Error, Help};
 use radicle_common::seed;
 use radicle_common::Interactive;
-use radicle_common::{git, identity, keys, profile, project, sync};
+use radicle_common::{bundle, git, identity, keys, profile, project, sync};
 use radicle_terminal as term;
 
 pub const HELP: Help = Help {
@@ -22,12 +24,18 @@ pub const HELP: Help = Help {
 Usage
 
     rad clone <urn | url> [--seed <addr>] [<option>...]
+    rad clone --from-bundle <file> [<option>...]
+
+    With `--from-bundle`, the project is imported from a bundle created with
+    `rad sync --export-bundle`, without network access. The bundle is verified against
+    its signed manifest, which is expected next to it, as `<file>.manifest`.
 
 Options
 
-    --no-confirm    Don't ask for confirmation during clone
-    --seed <addr>   Seed to clone from
-    --help          Print help
+    --no-confirm            Don't ask for confirmation during clone
+    --seed <addr>           Seed to clone from
+    --from-bundle <file>    Bundle to clone from
+    --help                  Print help
 
 "#,
 };
@@ -36,6 +44,7 @@ Options
 enum Origin {
     Radicle(identity::Origin),
     Git(Url),
+    Bundle(PathBuf),
 }
 
 #[derive(Debug)]
@@ -58,6 +67,10 @@ impl Args for Options {
                 Long("seed") if seed.is_none() => {
                     seed = Some(seed::parse_value(&mut parser)?);
                 }
+                Long("from-bundle") if origin.is_none() => {
+                    let path = parser.value()?;
+                    origin = Some(Origin::Bundle(PathBuf::from(path)));
+                }
                 Long("no-confirm") => {
                     interactive = Interactive::No;
                 }
@@ -96,10 +109,14 @@ impl Args for Options {
             anyhow!("to clone, a URN or URL must be provided; see `rad clone --help`")
         })?;
 
-        let origin = if let Origin::Radicle(identity::Origin { urn, seed: None }) = origin {
-            Origin::Radicle(identity::Origin { urn, seed })
-        } else {
-            origin
+        let origin = match origin {
+            Origin::Radicle(identity::Origin { urn, seed: None }) => {
+                Origin::Radicle(identity::Origin { urn, seed })
+            }
+            Origin::Bundle(_) if seed.is_some() => {
+                anyhow::bail!("`--seed` cannot be specified when cloning from a bundle");
+            }
+            origin => origin,
         };
 
         Ok((
@@ -121,6 +138,9 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
             let profile = ctx.profile()?;
             clone_repository(url, &profile)?;
         }
+        Origin::Bundle(path) => {
+            clone_bundle(&path, options.interactive, ctx)?;
+        }
     }
     Ok(())
 }
@@ -144,24 +164,64 @@ pub fn clone_project(
         },
         profile.clone(),
     )?;
+
+    let signer = term::signer(&profile)?;
+    let storage = keys::storage(&profile, signer)?;
+
+    checkout(urn, interactive, &profile, &storage)
+}
+
+/// Clone a project from a bundle.
+pub fn clone_bundle(
+    path: &Path,
+    interactive: Interactive,
+    ctx: impl term::Context,
+) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let signer = term::signer(&profile)?;
+    let storage = keys::storage(&profile, signer)?;
+    let spinner = term::spinner(format!(
+        "Importing bundle {}...",
+        term::format::highlight(path.display())
+    ));
+    let manifest = match bundle::import(storage.read_only(), path) {
+        Ok(manifest) => manifest,
+        Err(err) => return Err(spinner.error(err.into())),
+    };
+    spinner.finish();
+
+    term::info!(
+        "Bundle of {} exported by {}",
+        term::format::highlight(manifest.urn()),
+        term::format::tertiary(manifest.peer())
+    );
+
+    checkout(manifest.urn().clone(), interactive, &profile, &storage)
+}
+
+/// Checkout a project that is in local storage, and track its delegates.
+fn checkout(
+    urn: Urn,
+    interactive: Interactive,
+    profile: &profile::Profile,
+    storage: &Storage,
+) -> anyhow::Result<()> {
     let path = rad_checkout::execute(
         rad_checkout::Options {
             urn: urn.clone(),
             interactive,
         },
-        &profile,
+        profile,
     )?;
 
-    let signer = term::signer(&profile)?;
-    let storage = keys::storage(&profile, signer)?;
     let cfg = tracking::config::Config::default();
-    let project = project::get(&storage, &urn)?
+    let project = project::get(storage, &urn)?
         .ok_or_else(|| anyhow!("couldn't load project {} from local state", urn))?;
 
     // Track all project delegates.
     for peer in project.remotes {
         tracking::track(
-            &storage,
+            storage,
             &urn,
             Some(peer),
             cfg.clone(),
@@ -197,7 +257,7 @@ pub fn clone_repository(url: Url, profile: &profile::Profile) -> anyhow::Result<
     if term::confirm(format!(
         "Initialize new 🌱 project in {}?",
         term::format::highlight(destination.display())
-    )) {
+    ))? {
         let options = rad_init::Options {
             path: Some(destination.as_path().into()),
             ..Default::default()
@@ -211,6 +271,8 @@ pub fn clone_repository(url: Url, profile: &profile::Profile) -> anyhow::Result<
 mod test {
     use super::*;
     use librad::PeerId;
+    use radicle_common::sync::queue::Queue;
+    use radicle_common::test;
 
     #[test]
     fn test_args_ok() {
@@ -271,4 +333,28 @@ mod test {
             Options::from_args(args).unwrap_err();
         }
     }
+
+    #[test]
+    fn test_clone_from_seed() {
+        let (_, _, _, project) = test::setup::profile();
+        let (_, profile, _, _) = test::setup::profile();
+        let urn = project.urn();
+        let seed = test::seed::Seed::spawn();
+        let addr = sync::Seed::<String>::try_from(seed.config()).unwrap();
+
+        // The test seed doesn't serve P2P, which cloning goes through: the project can't
+        // be replicated, and isn't checked out.
+        let err =
+            clone_project(urn.clone(), Some(addr), Interactive::No, profile.clone()).unwrap_err();
+        assert!(err.to_string().contains("could not be found"), "{}", err);
+        assert!(!Path::new(&project.subject().name.to_string()).exists());
+
+        // The sync is queued, to be retried with the given seed only.
+        let queue = Queue::load(&profile).unwrap();
+        let entry = queue.entries().find(|e| e.urn == urn).unwrap();
+        assert_eq!(
+            entry.seeds().iter().map(|s| s.peer).collect::<Vec<_>>(),
+            vec![seed.peer]
+        );
+    }
 }
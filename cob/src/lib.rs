@@ -0,0 +1,218 @@
+#![allow(clippy::or_fun_call)]
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context as _};
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::cobs::raw;
+use radicle_common::{cobs, fmt, json, keys, project};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "cob",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad cob list --type <typename>
+    rad cob show <id> --type <typename>
+    rad cob create --type <typename> [--file <path>]
+    rad cob update <id> --type <typename> [--file <path>]
+
+    Operates on collaborative objects of any type, in the current project.
+    Objects are represented as JSON maps. When creating or updating an object,
+    its contents are read from the given file, or edited interactively if no
+    file is given.
+
+    Type names under `xyz.radicle.` are reserved for built-in objects, and
+    can only be listed and shown.
+
+Options
+
+    --type <typename>   Object type name, eg. `org.example.checklist`
+    --file <path>       Read the object from a JSON file
+    --help              Print help
+"#,
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OperationName {
+    List,
+    Show,
+    Create,
+    Update,
+}
+
+#[derive(Debug)]
+pub enum Operation {
+    List,
+    Show {
+        id: cobs::Identifier,
+    },
+    Create {
+        file: Option<PathBuf>,
+    },
+    Update {
+        id: cobs::Identifier,
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub typename: raw::TypeName,
+    pub op: Operation,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut op: Option<OperationName> = None;
+        let mut id: Option<cobs::Identifier> = None;
+        let mut typename: Option<raw::TypeName> = None;
+        let mut file: Option<PathBuf> = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Long("type") => {
+                    let val = parser.value()?;
+                    let val = val
+                        .to_str()
+                        .ok_or_else(|| anyhow!("type name specified is not UTF-8"))?;
+
+                    typename = Some(raw::typename(val)?);
+                }
+                Long("file")
+                    if matches!(op, Some(OperationName::Create | OperationName::Update)) =>
+                {
+                    file = Some(parser.value()?.into());
+                }
+                Value(val) if op.is_none() => match val.to_string_lossy().as_ref() {
+                    "l" | "list" => op = Some(OperationName::List),
+                    "s" | "show" => op = Some(OperationName::Show),
+                    "c" | "create" => op = Some(OperationName::Create),
+                    "u" | "update" => op = Some(OperationName::Update),
+
+                    unknown => anyhow::bail!("unknown operation '{}'", unknown),
+                },
+                Value(val) if id.is_none() => {
+                    let val = val
+                        .to_str()
+                        .ok_or_else(|| anyhow!("object id specified is not UTF-8"))?;
+
+                    id = Some(
+                        cobs::Identifier::from_str(val)
+                            .map_err(|_| anyhow!("invalid object id '{}'", val))?,
+                    );
+                }
+                _ => {
+                    return Err(anyhow!(arg.unexpected()));
+                }
+            }
+        }
+
+        let op = match op.ok_or_else(|| anyhow!("an operation must be provided"))? {
+            OperationName::List => Operation::List,
+            OperationName::Show => Operation::Show {
+                id: id.ok_or_else(|| anyhow!("an object id must be provided"))?,
+            },
+            OperationName::Create => Operation::Create { file },
+            OperationName::Update => Operation::Update {
+                id: id.ok_or_else(|| anyhow!("an object id must be provided"))?,
+                file,
+            },
+        };
+        let typename = typename.ok_or_else(|| anyhow!("a type name must be provided"))?;
+
+        Ok((Options { typename, op }, vec![]))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let signer = term::signer(&profile)?;
+    let storage = keys::storage(&profile, signer)?;
+    let (project, _) = project::cwd()
+        .map_err(|_| anyhow!("this command must be run in the context of a project"))?;
+    let cobs = cobs::store(&profile, &storage)?;
+    let store = cobs.raw(options.typename);
+
+    match options.op {
+        Operation::List => {
+            for (id, value) in store.all(&project)? {
+                let summary = ["title", "name"]
+                    .iter()
+                    .find_map(|k| value.get(k).and_then(|v| v.as_str()))
+                    .unwrap_or_default();
+
+                term::info!("{} {}", term::format::highlight(fmt::cob(&id)), summary);
+            }
+        }
+        Operation::Show { id } => {
+            let id = store
+                .resolve_id(&project, &id)?
+                .ok_or_else(|| anyhow!("object '{}' not found", id))?;
+            let value = store
+                .get(&project, &id)?
+                .ok_or_else(|| anyhow!("object '{}' not found", id))?;
+
+            term::print(json::to_string_pretty(&value)?);
+        }
+        Operation::Create { file } => {
+            let value = read(file, &json::json!({}))?;
+            let id = store.create(&project, &value)?;
+
+            term::success!(
+                "Object {} of type {} created",
+                term::format::highlight(id),
+                term::format::dim(store.typename())
+            );
+        }
+        Operation::Update { id, file } => {
+            let id = store
+                .resolve_id(&project, &id)?
+                .ok_or_else(|| anyhow!("object '{}' not found", id))?;
+            let current = store
+                .get(&project, &id)?
+                .ok_or_else(|| anyhow!("object '{}' not found", id))?;
+            let value = read(file, &current)?;
+
+            if value == current {
+                term::info!("Nothing to do, object is unchanged.");
+                return Ok(());
+            }
+            store.update(&project, &id, &value)?;
+
+            term::success!("Object {} updated", term::format::highlight(id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Read an object from a file, or from the editor if no file is given.
+fn read(file: Option<PathBuf>, initial: &json::Value) -> anyhow::Result<json::Value> {
+    let contents = if let Some(path) = file {
+        fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?
+    } else {
+        term::Editor::new()
+            .extension(".json")
+            .edit(&json::to_string_pretty(initial)?)?
+            .ok_or_else(|| anyhow!("operation aborted"))?
+    };
+    let value: json::Value = json::from_str(&contents).context("failed to parse object")?;
+
+    if !value.is_object() {
+        anyhow::bail!("object must be a JSON map");
+    }
+    Ok(value)
+}
@@ -0,0 +1,419 @@
+//! Project bundles, for moving projects between devices without network access.
+//!
+//! A bundle is made of two files: a git bundle of the project's namespace in the
+//! monorepo, ie. its identity, the refs of each peer and collaborative objects, and a
+//! manifest listing these refs, signed by the exporting peer.
+//!
+//! Person identities can be bundled the same way.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use librad::crypto::{BoxedSigner, Signature};
+use librad::git::identities::{any, person, SomeIdentity};
+use librad::git::storage::ReadOnly;
+use librad::git::Urn;
+use librad::{PeerId, Signer};
+
+use crate::{git, identities, project, refs};
+
+/// File extension of bundle manifests, appended to the bundle file name.
+pub const MANIFEST_EXTENSION: &str = "manifest";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("git: {0}")]
+    Git(#[from] git2::Error),
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("failed to sign manifest: {0}")]
+    Sign(String),
+    #[error("manifest signature is invalid")]
+    InvalidSignature,
+    #[error("bundle doesn't match its manifest: {0}")]
+    Mismatch(String),
+    #[error("project {0} has no refs to export")]
+    Empty(Urn),
+    #[error("project identity could not be verified: {0}")]
+    Identity(String),
+    #[error(transparent)]
+    SignedRefs(#[from] refs::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Refs of a bundled project, and the peer that bundled them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Payload {
+    #[serde(deserialize_with = "project::deserialize_urn")]
+    urn: Urn,
+    peer: PeerId,
+    /// Refs, relative to the project namespace, eg. `refs/heads/master`.
+    refs: BTreeMap<String, String>,
+}
+
+/// Bundle manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(flatten)]
+    payload: Payload,
+    /// Signature of the payload, by the exporting peer.
+    signature: Signature,
+}
+
+impl Manifest {
+    /// Path of the manifest of the given bundle.
+    pub fn path(bundle: &Path) -> PathBuf {
+        let mut path = bundle.as_os_str().to_owned();
+        path.push(".");
+        path.push(MANIFEST_EXTENSION);
+
+        PathBuf::from(path)
+    }
+
+    /// Read the manifest of the given bundle, and verify its signature.
+    pub fn load(bundle: &Path) -> Result<Self, Error> {
+        let bytes = fs::read(Self::path(bundle))?;
+        let manifest: Self = serde_json::from_slice(&bytes)?;
+        let payload = serde_json::to_vec(&manifest.payload)?;
+
+        if !manifest
+            .payload
+            .peer
+            .as_public_key()
+            .verify(&manifest.signature, &payload)
+        {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(manifest)
+    }
+
+    pub fn urn(&self) -> &Urn {
+        &self.payload.urn
+    }
+
+    /// Peer that exported the bundle.
+    pub fn peer(&self) -> &PeerId {
+        &self.payload.peer
+    }
+
+    /// Bundled refs, relative to the project namespace.
+    pub fn refs(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.payload.refs.iter()
+    }
+}
+
+/// Export the given project from the monorepo into a bundle at `path`. The manifest is
+/// written next to it.
+pub fn export(
+    storage: &ReadOnly,
+    signer: &BoxedSigner,
+    urn: &Urn,
+    path: &Path,
+) -> Result<Manifest, Error> {
+    let path = absolute(path)?;
+    let monorepo = storage.path();
+    let repo = git::Repository::open_bare(monorepo)?;
+    let namespace = namespace(urn);
+    let mut refs = BTreeMap::new();
+
+    for r in repo.references_glob(&format!("{}*", namespace))? {
+        let r = r?;
+        if let (Some(name), Some(oid)) = (r.name(), r.target()) {
+            refs.insert(name[namespace.len()..].to_owned(), oid.to_string());
+        }
+    }
+    if refs.is_empty() {
+        return Err(Error::Empty(urn.clone()));
+    }
+
+    let mut args = vec![
+        "bundle".to_owned(),
+        "create".to_owned(),
+        path.to_string_lossy().into_owned(),
+    ];
+    args.extend(refs.keys().map(|name| format!("{}{}", namespace, name)));
+    git::git(monorepo, args)?;
+
+    let payload = Payload {
+        urn: urn.clone(),
+        peer: *storage.peer_id(),
+        refs,
+    };
+    let signature = signer
+        .sign_blocking(&serde_json::to_vec(&payload)?)
+        .map(Signature::from)
+        .map_err(|e| Error::Sign(e.to_string()))?;
+    let manifest = Manifest { payload, signature };
+
+    fs::write(Manifest::path(&path), serde_json::to_vec_pretty(&manifest)?)?;
+
+    Ok(manifest)
+}
+
+/// Import the bundle at `path` into the monorepo, after verifying it against its
+/// manifest. The exporter's own refs are stored as the refs of a remote. Once imported,
+/// the project or person identity is verified, and the refs of each remote are verified
+/// against the remote's signed refs.
+///
+/// If verification fails, the imported refs are restored to their previous targets.
+pub fn import(storage: &ReadOnly, path: &Path) -> Result<Manifest, Error> {
+    let path = absolute(path)?;
+    let manifest = Manifest::load(&path)?;
+    let monorepo = storage.path();
+    let local = storage.peer_id();
+    let namespace = namespace(manifest.urn());
+    let path = path.to_string_lossy();
+
+    git::git(monorepo, ["bundle", "verify", path.as_ref()])
+        .map_err(|e| Error::Mismatch(e.to_string()))?;
+
+    // The bundle's refs must be exactly those of the manifest.
+    let heads = git::git(monorepo, ["bundle", "list-heads", path.as_ref()])?;
+    let mut bundled = BTreeMap::new();
+    for line in heads.lines() {
+        if let Some((oid, name)) = line.split_once(' ') {
+            let name = name.strip_prefix(&namespace).ok_or_else(|| {
+                Error::Mismatch(format!("unexpected ref '{}' outside of project", name))
+            })?;
+            bundled.insert(name.to_owned(), oid.to_owned());
+        }
+    }
+    if bundled != manifest.payload.refs {
+        return Err(Error::Mismatch("refs differ".to_owned()));
+    }
+
+    let repo = git::Repository::open_bare(monorepo)?;
+    let mut refspecs = Vec::new();
+    let mut targets = Vec::new();
+    for name in bundled.keys() {
+        let target = match git::parse_remote(name) {
+            // Never overwrite our own refs.
+            Some((peer, _)) if &peer == local => continue,
+            Some(_) => name.clone(),
+            None => {
+                // Refs of the exporter become the refs of a remote.
+                let target = format!(
+                    "refs/remotes/{}/{}",
+                    manifest.peer(),
+                    name.trim_start_matches("refs/")
+                );
+                // The project identity is also needed at the top-level, if we don't
+                // have it yet.
+                let is_identity = name == "refs/rad/id" || name.starts_with("refs/rad/ids/");
+                if is_identity
+                    && repo
+                        .find_reference(&format!("{}{}", namespace, name))
+                        .is_err()
+                {
+                    refspecs.push(format!("{}{}:{}{}", namespace, name, namespace, name));
+                    targets.push(format!("{}{}", namespace, name));
+                }
+                target
+            }
+        };
+        refspecs.push(format!("+{}{}:{}{}", namespace, name, namespace, target));
+        targets.push(format!("{}{}", namespace, target));
+    }
+
+    let snapshot = refs::Snapshot::take(&repo, targets.iter().map(String::as_str));
+    let mut args = vec!["fetch".to_owned(), "--quiet".to_owned(), path.into_owned()];
+    args.extend(refspecs);
+
+    let result = git::git(monorepo, args)
+        .map_err(Error::from)
+        .and_then(|_| verify(storage, manifest.urn()).map_err(Error::Identity))
+        .and_then(|_| verify_refs(storage, manifest.urn(), &targets));
+    if let Err(err) = result {
+        snapshot.restore(&repo)?;
+        return Err(err);
+    }
+    Ok(manifest)
+}
+
+/// Verify the imported refs of each remote against the remote's signed refs.
+fn verify_refs(storage: &ReadOnly, urn: &Urn, targets: &[String]) -> Result<(), Error> {
+    let namespace = namespace(urn);
+    let mut remotes: HashMap<PeerId, Vec<&str>> = HashMap::new();
+
+    for target in targets {
+        if let Some((peer, name)) = target.strip_prefix(&namespace).and_then(git::parse_remote) {
+            remotes.entry(peer).or_default().push(name);
+        }
+    }
+    for (peer, names) in remotes {
+        match refs::verify(storage, urn, peer, names.iter().copied()) {
+            // Peers that only have identity refs, or collaborative objects, may not
+            // have signed refs.
+            Err(refs::Error::NotFound(_)) if !names.iter().any(|n| refs::is_signed(n)) => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Verify the identity of a bundle, which is either a project or a person.
+fn verify(storage: &ReadOnly, urn: &Urn) -> Result<(), String> {
+    let verified = match any::get(storage, urn) {
+        Ok(Some(SomeIdentity::Person(_))) => person::verify(storage, urn)
+            .map(|p| p.is_some())
+            .map_err(|e| e.to_string()),
+        Ok(Some(_)) => identities::project::verify(storage, urn)
+            .map(|p| p.is_some())
+            .map_err(|e| e.to_string()),
+        Ok(None) => Ok(false),
+        Err(err) => Err(err.to_string()),
+    }?;
+
+    if verified {
+        Ok(())
+    } else {
+        Err("identity not found".to_owned())
+    }
+}
+
+/// Make the given bundle path absolute, since git commands are run from the monorepo.
+/// The bundle file itself may not exist yet.
+fn absolute(path: &Path) -> Result<PathBuf, io::Error> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "bundle path must name a file")
+    })?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    Ok(dir.canonicalize()?.join(name))
+}
+
+/// Project namespace prefix in the monorepo.
+fn namespace(urn: &Urn) -> String {
+    format!("refs/namespaces/{}/", urn.encode_id())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+
+    #[test]
+    fn test_export_manifest() {
+        let (storage, profile, _whoami, project) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass).unwrap();
+        let path = profile.paths().git_dir().join("project.bundle");
+        let urn = project.urn();
+
+        let manifest = export(storage.read_only(), &signer, &urn, &path).unwrap();
+        assert!(manifest.refs().any(|(name, _)| name == "refs/rad/id"));
+
+        let loaded = Manifest::load(&path).unwrap();
+        assert_eq!(loaded.urn(), &urn);
+        assert_eq!(loaded.peer(), storage.peer_id());
+
+        // Tampering with the manifest invalidates it.
+        let mut tampered = loaded;
+        tampered
+            .payload
+            .refs
+            .insert("refs/heads/evil".to_owned(), git::Oid::zero().to_string());
+        fs::write(
+            Manifest::path(&path),
+            serde_json::to_vec(&tampered).unwrap(),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            Manifest::load(&path),
+            Err(Error::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_absolute() {
+        let cwd = std::env::current_dir().unwrap().canonicalize().unwrap();
+
+        assert_eq!(
+            absolute(Path::new("project.bundle")).unwrap(),
+            cwd.join("project.bundle")
+        );
+        assert_eq!(
+            absolute(&cwd.join("project.bundle")).unwrap(),
+            cwd.join("project.bundle")
+        );
+        assert!(absolute(Path::new("..")).is_err());
+    }
+
+    #[test]
+    fn test_import_roundtrip() {
+        let (exporter, profile, whoami, _) = test::setup::profile();
+        let (importer, _, _, _) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass).unwrap();
+        let urn = whoami.urn();
+        let peer = *exporter.peer_id();
+        let namespace = namespace(&urn);
+        let remote = format!("{}refs/remotes/{}/rad/id", namespace, peer);
+
+        let dir = profile.paths().git_dir().parent().unwrap().to_path_buf();
+        let exported = export(
+            exporter.read_only(),
+            &signer,
+            &urn,
+            &dir.join("person.bundle"),
+        )
+        .unwrap();
+        let imported = import(importer.read_only(), &dir.join("person.bundle")).unwrap();
+        assert_eq!(imported.urn(), exported.urn());
+        assert_eq!(imported.peer(), &peer);
+
+        // The exporter's refs are imported as the refs of a remote, and the identity
+        // is also available at the top-level.
+        let repo = git::Repository::open_bare(importer.path()).unwrap();
+        let id = repo.refname_to_id(&remote).unwrap();
+        assert_eq!(
+            repo.refname_to_id(&format!("{}refs/rad/id", namespace))
+                .unwrap(),
+            id
+        );
+
+        // A branch that isn't in the exporter's signed refs is rejected, and the
+        // previously imported refs are left as they were.
+        let monorepo = git::Repository::open_bare(exporter.path()).unwrap();
+        let commit = monorepo.find_commit(id).unwrap();
+        monorepo
+            .reference(
+                &format!("{}refs/heads/unsigned", namespace),
+                commit.id(),
+                true,
+                "",
+            )
+            .unwrap();
+        export(
+            exporter.read_only(),
+            &signer,
+            &urn,
+            &dir.join("tampered.bundle"),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            import(importer.read_only(), &dir.join("tampered.bundle")),
+            Err(Error::SignedRefs(_))
+        ));
+        assert_eq!(repo.refname_to_id(&remote).unwrap(), id);
+        assert!(repo
+            .find_reference(&format!(
+                "{}refs/remotes/{}/heads/unsigned",
+                namespace, peer
+            ))
+            .is_err());
+    }
+}
@@ -1,6 +1,8 @@
 pub mod issue;
 pub mod label;
 pub mod patch;
+pub mod raw;
+pub mod schema;
 pub mod shared;
 pub mod user;
 
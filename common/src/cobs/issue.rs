@@ -16,6 +16,7 @@ use librad::git::identities::local::LocalIdentity;
 use librad::git::storage::ReadOnly;
 use librad::git::Urn;
 
+use crate::cobs::schema::{self, Migration, Schema, Version};
 use crate::cobs::shared;
 use crate::cobs::shared::*;
 
@@ -23,6 +24,15 @@ lazy_static! {
     pub static ref TYPENAME: TypeName = FromStr::from_str("xyz.radicle.issue").unwrap();
 }
 
+/// Schema of issue documents.
+pub const SCHEMA: Schema = Schema {
+    root: "issue",
+    migrations: &[Migration {
+        version: Version::new(1),
+        apply: schema::migrations::labels,
+    }],
+};
+
 /// Identifier for an issue.
 pub type IssueId = ObjectId;
 
@@ -172,9 +182,11 @@ impl TryFrom<&History> for Issue {
 }
 
 impl TryFrom<Automerge> for Issue {
-    type Error = DocumentError;
+    type Error = schema::SchemaError;
+
+    fn try_from(mut doc: Automerge) -> Result<Self, Self::Error> {
+        SCHEMA.migrate(&mut doc)?;
 
-    fn try_from(doc: Automerge) -> Result<Self, Self::Error> {
         let doc = Document::new(&doc);
         let (_obj, obj_id) = doc.get(automerge::ObjId::Root, "issue")?;
         let title = doc.val(&obj_id, "title")?;
@@ -362,7 +374,11 @@ impl<'a> IssueStore<'a> {
         let mut issues = Vec::new();
         for cob in cobs {
             let issue: Result<Issue, _> = cob.history().try_into();
-            issues.push((*cob.id(), issue.unwrap()));
+
+            match issue {
+                Ok(issue) => issues.push((*cob.id(), issue)),
+                Err(err) => log::warn!("Skipping malformed issue {}: {}", cob.id(), err),
+            }
         }
         issues.sort_by_key(|(_, i)| i.timestamp);
 
@@ -438,11 +454,10 @@ mod events {
         description: &str,
         timestamp: Timestamp,
         labels: &[Label],
-    ) -> Result<EntryContents, AutomergeError> {
+    ) -> Result<EntryContents, Error> {
         let title = title.trim();
-        // TODO: Return error.
         if title.is_empty() {
-            panic!("Empty issue title");
+            return Err(ValidationError::EmptyTitle.into());
         }
 
         // TODO: Set actor id of document?
@@ -453,6 +468,7 @@ mod events {
                 |tx| {
                     let issue = tx.put_object(ObjId::Root, "issue", ObjType::Map)?;
 
+                    tx.put(&issue, schema::VERSION_PROPERTY, SCHEMA.current())?;
                     tx.put(&issue, "title", title)?;
                     tx.put(&issue, "author", author.urn().to_string())?;
                     tx.put(&issue, "peer", author.peer.default_encoding())?;
@@ -551,7 +567,11 @@ mod events {
                 |_| CommitOptions::default().with_message("Label issue".to_owned()),
                 |tx| {
                     let (_, obj_id) = tx.get(ObjId::Root, "issue")?.unwrap();
-                    let (_, labels_id) = tx.get(&obj_id, "labels")?.unwrap();
+                    // Nb. Older issues may not have a `labels` map.
+                    let labels_id = match tx.get(&obj_id, "labels")? {
+                        Some((_, labels_id)) => labels_id,
+                        None => tx.put_object(&obj_id, "labels", ObjType::Map)?,
+                    };
 
                     for label in labels {
                         tx.put(&labels_id, label.name().trim(), true)?;
@@ -649,6 +669,8 @@ mod events {
 mod test {
     use super::*;
     use crate::test;
+    use automerge::transaction::{CommitOptions, Transactable};
+    use automerge::ObjId;
 
     #[test]
     fn test_issue_create_and_get() {
@@ -670,6 +692,22 @@ mod test {
         assert!(issue.timestamp() >= timestamp);
     }
 
+    #[test]
+    fn test_issue_schema_version() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let issues = cobs.issues();
+        let issue_id = issues
+            .create(&project.urn(), "My first issue", "Blah blah blah.", &[])
+            .unwrap();
+        let doc = issues.get_raw(&project.urn(), &issue_id).unwrap().unwrap();
+
+        assert_eq!(
+            SCHEMA.version(Document::new(&doc)).unwrap(),
+            SCHEMA.current()
+        );
+    }
+
     #[test]
     fn test_issue_create_and_change_state() {
         let (storage, profile, whoami, project) = test::setup::profile();
@@ -914,4 +952,56 @@ mod test {
         assert_eq!(issues[1].1.title(), "My second issue");
         assert_eq!(issues[2].1.title(), "My third issue");
     }
+
+    #[test]
+    fn test_issue_empty_title() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let issues = cobs.issues();
+        let err = issues
+            .create(&project.urn(), "  ", "Blah blah blah.", &[])
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Validation(ValidationError::EmptyTitle)
+        ));
+    }
+
+    #[test]
+    fn test_issue_all_malformed() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let issues = cobs.issues();
+
+        issues
+            .create(&project.urn(), "My first issue", "Blah blah blah.", &[])
+            .unwrap();
+
+        // An issue without a title, eg. received from a misbehaving peer.
+        let mut doc = Automerge::new();
+        doc.transact_with::<_, _, AutomergeError, _, ()>(
+            |_| CommitOptions::default().with_message("Create issue".to_owned()),
+            |tx| {
+                let issue = tx.put_object(ObjId::Root, "issue", ObjType::Map)?;
+                tx.put(&issue, "state", "open")?;
+
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        cobs::create(
+            EntryContents::Automerge(doc.save_incremental()),
+            &project.urn(),
+            &cobs.whoami,
+            issues.store,
+        )
+        .unwrap();
+
+        let issues = issues.all(&project.urn()).unwrap();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].1.title(), "My first issue");
+    }
 }
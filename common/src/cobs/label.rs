@@ -59,18 +59,14 @@ impl TryFrom<&History> for Label {
 }
 
 impl TryFrom<Automerge> for Label {
-    type Error = AutomergeError;
+    type Error = DocumentError;
 
     fn try_from(doc: Automerge) -> Result<Self, Self::Error> {
-        let (_, obj_id) = doc.get(automerge::ObjId::Root, "label")?.unwrap();
-        let (name, _) = doc.get(&obj_id, "name")?.unwrap();
-        let (description, _) = doc.get(&obj_id, "description")?.unwrap();
-        let (color, _) = doc.get(&obj_id, "color")?.unwrap();
-
-        let name = name.into_string().unwrap();
-        let description = description.into_string().unwrap();
-        let color = color.into_string().unwrap();
-        let color = Color::from_str(&color).unwrap();
+        let doc = Document::new(&doc);
+        let (_, obj_id) = doc.get(automerge::ObjId::Root, "label")?;
+        let name = doc.val(&obj_id, "name")?;
+        let description = doc.val(&obj_id, "description")?;
+        let color = doc.val(&obj_id, "color")?;
 
         Ok(Self {
             name,
@@ -93,6 +89,9 @@ pub enum Error {
 
     #[error(transparent)]
     Automerge(#[from] AutomergeError),
+
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
 }
 
 pub struct Labels<'a> {
@@ -128,7 +127,8 @@ impl<'a> Labels<'a> {
             .map_err(|e| Error::Retrieve(e.to_string()))?;
 
         if let Some(cob) = cob {
-            let label = Label::try_from(cob.history()).unwrap();
+            let label =
+                Label::try_from(cob.history()).map_err(|e| Error::Retrieve(e.to_string()))?;
             Ok(Some(label))
         } else {
             Ok(None)
@@ -173,11 +173,10 @@ mod events {
         name: &str,
         description: &str,
         color: &Color,
-    ) -> Result<EntryContents, AutomergeError> {
+    ) -> Result<EntryContents, Error> {
         let name = name.trim();
-        // TODO: Return error.
         if name.is_empty() {
-            panic!("Empty label name");
+            return Err(ValidationError::EmptyLabelName.into());
         }
         let mut doc = Automerge::new();
 
@@ -203,6 +202,7 @@ mod events {
 mod test {
     use super::*;
     use crate::test;
+    use automerge::transaction::{CommitOptions, Transactable};
 
     #[test]
     fn test_label_create_and_get() {
@@ -222,4 +222,42 @@ mod test {
         assert_eq!(label.description, "Something that doesn't work");
         assert_eq!(label.color.to_string(), "#ff0000");
     }
+
+    #[test]
+    fn test_label_empty_name() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let labels = Labels::new(whoami, profile.paths(), &storage).unwrap();
+        let err = labels
+            .create(
+                &project.urn(),
+                "",
+                "Nothing",
+                &Color::from_str("#ff0000").unwrap(),
+            )
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Validation(ValidationError::EmptyLabelName)
+        ));
+    }
+
+    #[test]
+    fn test_label_malformed() {
+        let mut doc = Automerge::new();
+        doc.transact_with::<_, _, AutomergeError, _, ()>(
+            |_| CommitOptions::default().with_message("Create label".to_owned()),
+            |tx| {
+                let label = tx.put_object(automerge::ObjId::Root, "label", ObjType::Map)?;
+                tx.put(&label, "name", "bug")?;
+                tx.put(&label, "description", "Something that doesn't work")?;
+                tx.put(&label, "color", "red")?;
+
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert!(Label::try_from(doc).is_err());
+    }
 }
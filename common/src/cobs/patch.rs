@@ -23,6 +23,7 @@ use librad::PeerId;
 
 use radicle_git_ext as git;
 
+use crate::cobs::schema::{self, Migration, Schema, Version};
 use crate::cobs::shared;
 use crate::cobs::shared::*;
 
@@ -30,6 +31,21 @@ lazy_static! {
     pub static ref TYPENAME: TypeName = FromStr::from_str("xyz.radicle.patch").unwrap();
 }
 
+/// Schema of patch documents.
+pub const SCHEMA: Schema = Schema {
+    root: "patch",
+    migrations: &[
+        Migration {
+            version: Version::new(1),
+            apply: schema::migrations::labels,
+        },
+        Migration {
+            version: Version::new(2),
+            apply: migrations::revisions,
+        },
+    ],
+};
+
 /// Identifier for a patch.
 pub type PatchId = ObjectId;
 
@@ -99,18 +115,46 @@ where
 
 impl Patch {
     pub fn head(&self) -> &git::Oid {
-        &self.revisions.last().oid
+        &self.latest().1.oid
     }
 
     pub fn version(&self) -> RevisionIx {
-        self.revisions.len() - 1
+        self.latest().0
     }
 
+    /// Get the latest revision. If there are conflicting revisions, this is the
+    /// last one in the revisions list. Since the list order is determined by the
+    /// underlying document, all peers agree on which revision this is.
     pub fn latest(&self) -> (RevisionIx, &Revision) {
-        let version = self.version();
-        let revision = &self.revisions[version];
+        if let Some(head) = self.heads().pop() {
+            return head;
+        }
+        let version = self.revisions.len() - 1;
+
+        (version, &self.revisions[version])
+    }
 
-        (version, revision)
+    /// Revisions that were neither superseded by another revision, nor discarded.
+    ///
+    /// There is usually a single head. More than one head means that revisions were
+    /// published concurrently, eg. by the author from two different devices.
+    pub fn heads(&self) -> Vec<(RevisionIx, &Revision)> {
+        let superseded = self
+            .revisions
+            .iter()
+            .flat_map(|r| r.supersedes.iter())
+            .collect::<HashSet<_>>();
+
+        self.revisions
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| !r.discarded && !superseded.contains(&r.id))
+            .collect()
+    }
+
+    /// Whether this patch has conflicting revisions.
+    pub fn is_conflicted(&self) -> bool {
+        self.heads().len() > 1
     }
 
     pub fn is_proposed(&self) -> bool {
@@ -175,6 +219,18 @@ impl TryFrom<Document<'_>> for Patch {
     }
 }
 
+impl TryFrom<Automerge> for Patch {
+    type Error = schema::SchemaError;
+
+    fn try_from(mut doc: Automerge) -> Result<Self, Self::Error> {
+        SCHEMA.migrate(&mut doc)?;
+
+        let patch = Patch::try_from(Document::new(&doc))?;
+
+        Ok(patch)
+    }
+}
+
 impl TryFrom<&History> for Patch {
     type Error = anyhow::Error;
 
@@ -194,7 +250,7 @@ impl TryFrom<&History> for Patch {
             }
             ControlFlow::Continue(doc)
         });
-        let patch = Patch::try_from(Document::new(&doc))?;
+        let patch = Patch::try_from(doc)?;
 
         Ok(patch)
     }
@@ -288,7 +344,7 @@ impl<'a> PatchStore<'a> {
     ) -> Result<RevisionIx, Error> {
         let author = self.author();
         let timestamp = Timestamp::now();
-        let revision = Revision::new(
+        let mut revision = Revision::new(
             author,
             self.peer_id,
             base.into(),
@@ -298,6 +354,11 @@ impl<'a> PatchStore<'a> {
         );
 
         let mut patch = self.get_raw(project, patch_id)?.unwrap();
+        let current = Patch::try_from(patch.clone())?;
+
+        // The new revision supersedes all current heads, resolving any conflicts.
+        revision.supersedes = current.heads().into_iter().map(|(_, r)| r.id).collect();
+
         let (revision_ix, changes) = events::update(&mut patch, revision)?;
 
         cobs::update(
@@ -312,6 +373,29 @@ impl<'a> PatchStore<'a> {
         Ok(revision_ix)
     }
 
+    /// Discard revisions of a patch, eg. to resolve a conflict between revisions
+    /// by picking one of them.
+    pub fn discard(
+        &self,
+        project: &Urn,
+        patch_id: &PatchId,
+        revisions: &[RevisionId],
+    ) -> Result<(), Error> {
+        let mut patch = self.get_raw(project, patch_id)?.unwrap();
+        let changes = events::discard(&mut patch, revisions)?;
+
+        cobs::update(
+            *patch_id,
+            project,
+            "Discard revisions",
+            changes,
+            &self.whoami,
+            self.store,
+        )?;
+
+        Ok(())
+    }
+
     pub fn reply(
         &self,
         project: &Urn,
@@ -441,7 +525,11 @@ impl<'a> PatchStore<'a> {
         let cobs = self.store.list(project, &TYPENAME)?;
         for cob in cobs {
             let patch: Result<Patch, _> = cob.history().try_into();
-            patches.push((*cob.id(), patch.unwrap()));
+
+            match patch {
+                Ok(patch) => patches.push((*cob.id(), patch)),
+                Err(err) => log::warn!("Skipping malformed patch {}: {}", cob.id(), err),
+            }
         }
         patches.sort_by_key(|(_, p)| p.timestamp);
 
@@ -502,6 +590,11 @@ pub struct Revision<T = (), P = PeerId> {
     /// Unique revision ID. This is useful in case of conflicts, eg.
     /// a user published a revision from two devices by mistake.
     pub id: RevisionId,
+    /// Revisions superseded by this one. This is usually the previous revision,
+    /// unless this revision resolves a conflict.
+    pub supersedes: HashSet<RevisionId>,
+    /// Whether this revision was discarded in favor of a conflicting revision.
+    pub discarded: bool,
     /// Peer who published this revision.
     pub peer: PeerId,
     /// Base branch commit (merge base).
@@ -533,6 +626,8 @@ impl Revision {
     ) -> Self {
         Self {
             id: uuid::Uuid::new_v4(),
+            supersedes: HashSet::default(),
+            discarded: false,
             peer,
             base,
             oid,
@@ -569,10 +664,16 @@ impl Revision {
         );
 
         tx.put(&id, "id", self.id.to_string())?;
+        tx.put(&id, "discarded", self.discarded)?;
         tx.put(&id, "peer", self.peer.to_string())?;
         tx.put(&id, "oid", self.oid.to_string())?;
         tx.put(&id, "base", self.base.to_string())?;
 
+        let supersedes_id = tx.put_object(&id, "supersedes", ObjType::Map)?;
+        for revision in &self.supersedes {
+            tx.put(&supersedes_id, revision.to_string(), true)?;
+        }
+
         self.comment.put(tx, id)?;
 
         tx.put_object(&id, "discussion", ObjType::List)?;
@@ -743,6 +844,17 @@ mod lookup {
         let (_, comment_id) = doc.get(&revision_id, "comment")?;
         let (_, reviews_id) = doc.get(&revision_id, "reviews")?;
         let id = doc.val(&revision_id, "id")?;
+        // Revisions written before conflicts were tracked don't have these properties.
+        let supersedes = match doc.keys(&revision_id, "supersedes") {
+            Ok(supersedes) => supersedes,
+            Err(DocumentError::PropertyNotFound(_)) => HashSet::default(),
+            Err(err) => return Err(err),
+        };
+        let discarded = match doc.val(&revision_id, "discarded") {
+            Ok(discarded) => discarded,
+            Err(DocumentError::PropertyNotFound(_)) => false,
+            Err(err) => return Err(err),
+        };
         let peer = doc.val(&revision_id, "peer")?;
         let base = doc.val(&revision_id, "base")?;
         let oid = doc.val(&revision_id, "oid")?;
@@ -764,6 +876,8 @@ mod lookup {
 
         Ok(Revision {
             id,
+            supersedes,
+            discarded,
             peer,
             base,
             oid,
@@ -851,6 +965,44 @@ mod cobs {
     }
 }
 
+mod migrations {
+    use super::*;
+    use automerge::transaction::Transaction;
+    use automerge::ObjId;
+
+    /// Revisions used to be strictly sequential: each revision implicitly superseded the
+    /// previous one. Make this explicit for revisions written before conflicts were tracked.
+    pub fn revisions(tx: &mut Transaction, obj_id: &ObjId) -> Result<(), AutomergeError> {
+        let revisions_id = match tx.get(obj_id, "revisions")? {
+            Some((_, id)) => id,
+            None => return Ok(()),
+        };
+        let mut previous: Option<String> = None;
+
+        for ix in 0..tx.length(&revisions_id) {
+            let (_, revision_id) = match tx.get(&revisions_id, ix)? {
+                Some(revision) => revision,
+                None => continue,
+            };
+
+            if tx.get(&revision_id, "supersedes")?.is_none() {
+                let supersedes_id = tx.put_object(&revision_id, "supersedes", ObjType::Map)?;
+
+                if let Some(previous) = &previous {
+                    tx.put(&supersedes_id, previous.as_str(), true)?;
+                }
+            }
+            if tx.get(&revision_id, "discarded")?.is_none() {
+                tx.put(&revision_id, "discarded", false)?;
+            }
+            previous = tx
+                .get(&revision_id, "id")?
+                .and_then(|(id, _)| id.into_string().ok());
+        }
+        Ok(())
+    }
+}
+
 mod events {
     use super::*;
     use automerge::{
@@ -865,11 +1017,10 @@ mod events {
         target: MergeTarget,
         timestamp: Timestamp,
         labels: &[Label],
-    ) -> Result<EntryContents, AutomergeError> {
+    ) -> Result<EntryContents, Error> {
         let title = title.trim();
-        // TODO: Return error.
         if title.is_empty() {
-            panic!("Empty patch title");
+            return Err(ValidationError::EmptyTitle.into());
         }
 
         let mut doc = Automerge::new();
@@ -879,6 +1030,7 @@ mod events {
                 |tx| {
                     let patch_id = tx.put_object(ObjId::Root, "patch", ObjType::Map)?;
 
+                    tx.put(&patch_id, schema::VERSION_PROPERTY, SCHEMA.current())?;
                     tx.put(&patch_id, "title", title)?;
                     tx.put(&patch_id, "author", author.urn().to_string())?;
                     tx.put(&patch_id, "peer", author.peer.default_encoding())?;
@@ -1067,6 +1219,38 @@ mod events {
 
         Ok(EntryContents::Automerge(change))
     }
+
+    pub fn discard(
+        patch: &mut Automerge,
+        revisions: &[RevisionId],
+    ) -> Result<EntryContents, AutomergeError> {
+        patch
+            .transact_with::<_, _, AutomergeError, _, ()>(
+                |_| CommitOptions::default().with_message("Discard revisions".to_owned()),
+                |tx| {
+                    let (_, obj_id) = tx.get(ObjId::Root, "patch")?.unwrap();
+                    let (_, revisions_id) = tx.get(&obj_id, "revisions")?.unwrap();
+
+                    for ix in 0..tx.length(&revisions_id) {
+                        let (_, revision_id) = tx.get(&revisions_id, ix)?.unwrap();
+                        let id = tx
+                            .get(&revision_id, "id")?
+                            .and_then(|(id, _)| id.into_string().ok())
+                            .and_then(|id| RevisionId::from_str(&id).ok());
+
+                        if id.map_or(false, |id| revisions.contains(&id)) {
+                            tx.put(&revision_id, "discarded", true)?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(|failure| failure.error)?;
+
+        let change = patch.get_last_local_change().unwrap().raw_bytes().to_vec();
+
+        Ok(EntryContents::Automerge(change))
+    }
 }
 
 #[cfg(test)]
@@ -1220,5 +1404,147 @@ mod test {
         assert_eq!(id, 1);
         assert_eq!(revision.oid, rev1_oid);
         assert_eq!(revision.description(), "I've made changes.");
+        assert!(!patch.is_conflicted());
+    }
+
+    #[test]
+    fn test_patch_empty_title() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let patches = cobs.patches();
+        let oid = git::Oid::from(git2::Oid::zero());
+        let err = patches
+            .create(
+                &project.urn(),
+                "",
+                "Blah blah blah.",
+                MergeTarget::Upstream,
+                oid,
+                oid,
+                &[],
+            )
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::Validation(ValidationError::EmptyTitle)
+        ));
+    }
+
+    #[test]
+    fn test_patch_concurrent_update() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let store = Store::new(whoami, profile.paths(), &storage);
+        let patches = store.patches();
+        let base = git::Oid::from_str("af08e95ada2bb38aadd8e6cef0963ce37a87add3").unwrap();
+        let rev0_oid = git::Oid::from_str("518d5069f94c03427f694bb494ac1cd7d1339380").unwrap();
+        let rev1_oid = git::Oid::from_str("cb18e95ada2bb38aadd8e6cef0963ce37a87add3").unwrap();
+        let rev2_oid = git::Oid::from_str("fa18e95ada2bb38aadd8e6cef0963ce37a87add3").unwrap();
+        let project = &project.urn();
+        let patch_id = patches
+            .create(
+                project,
+                "My first patch",
+                "Blah blah blah.",
+                MergeTarget::Upstream,
+                base,
+                rev0_oid,
+                &[],
+            )
+            .unwrap();
+        let patch = patches.get(project, &patch_id).unwrap().unwrap();
+        let rev0 = patch.latest().1.id;
+
+        // Publish two revisions concurrently, as if from two devices: both are
+        // based on the same state of the patch.
+        let docs = vec![
+            patches.get_raw(project, &patch_id).unwrap().unwrap(),
+            patches.get_raw(project, &patch_id).unwrap().unwrap(),
+        ];
+        for (mut doc, oid) in docs.into_iter().zip(vec![rev1_oid, rev2_oid]) {
+            let mut revision = Revision::new(
+                patches.author(),
+                patches.peer_id,
+                base,
+                oid,
+                String::from("Concurrent changes."),
+                Timestamp::now(),
+            );
+            revision.supersedes = std::iter::once(rev0).collect();
+
+            let (ix, changes) = events::update(&mut doc, revision).unwrap();
+            assert_eq!(ix, 1);
+
+            cobs::update(
+                patch_id,
+                project,
+                "Update patch",
+                changes,
+                &store.whoami,
+                &store,
+            )
+            .unwrap();
+        }
+
+        let patch = patches.get(project, &patch_id).unwrap().unwrap();
+        let heads = patch.heads();
+
+        assert_eq!(patch.revisions.len(), 3);
+        assert_eq!(heads.len(), 2);
+        assert!(patch.is_conflicted());
+        assert_eq!(patch.latest().0, heads[1].0);
+
+        // Keep one of the conflicting revisions.
+        let (keep, discard) = (heads[0].1.id, heads[1].1.id);
+        patches.discard(project, &patch_id, &[discard]).unwrap();
+
+        let patch = patches.get(project, &patch_id).unwrap().unwrap();
+        assert!(!patch.is_conflicted());
+        assert_eq!(patch.latest().1.id, keep);
+
+        // A new revision supersedes the current head.
+        let ix = patches
+            .update(project, &patch_id, "More changes.", base, rev2_oid)
+            .unwrap();
+        let patch = patches.get(project, &patch_id).unwrap().unwrap();
+
+        assert_eq!(patch.version(), ix);
+        assert!(!patch.is_conflicted());
+        assert!(patch.latest().1.supersedes.contains(&keep));
+    }
+
+    #[test]
+    fn test_revision_lookup_legacy() {
+        use automerge::ObjId;
+
+        let (storage, profile, whoami, _project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let patches = cobs.patches();
+        let oid = git::Oid::from(git2::Oid::zero());
+        let revision = Revision::new(
+            patches.author(),
+            patches.peer_id,
+            oid,
+            oid,
+            String::from("Legacy revision."),
+            Timestamp::now(),
+        );
+
+        // A revision written before conflicts were tracked.
+        let mut doc = Automerge::new();
+        let mut tx = doc.transaction();
+        let revision_id = tx
+            .put_object(ObjId::Root, "revision", ObjType::Map)
+            .unwrap();
+        revision.put(&mut tx, &revision_id).unwrap();
+        tx.delete(&revision_id, "supersedes").unwrap();
+        tx.delete(&revision_id, "discarded").unwrap();
+        tx.commit();
+
+        let legacy = lookup::revision(Document::new(&doc), &revision_id).unwrap();
+
+        assert_eq!(legacy.id, revision.id);
+        assert!(legacy.supersedes.is_empty());
+        assert!(!legacy.discarded);
     }
 }
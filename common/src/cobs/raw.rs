@@ -0,0 +1,457 @@
+//! COBs of arbitrary type, represented as JSON.
+//!
+//! This allows objects of types unknown to this crate to be created and inspected,
+//! eg. for prototyping new object types.
+use std::ops::{ControlFlow, Deref};
+use std::str::FromStr;
+
+use automerge::transaction::{CommitOptions, Transactable, Transaction};
+use automerge::{Automerge, AutomergeError, ObjId, ObjType, ScalarValue, Value};
+use serde_json as json;
+
+use librad::collaborative_objects::{
+    self, EntryContents, History, NewObjectSpec, ObjectId, UpdateObjectSpec,
+};
+use librad::git::Urn;
+
+pub use librad::collaborative_objects::TypeName;
+
+use crate::cobs::shared::{Identifier, Store};
+
+/// Type name prefix reserved for built-in object types.
+pub const RESERVED_PREFIX: &str = "xyz.radicle.";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("type name `{0}` is reserved for built-in objects")]
+    Reserved(TypeName),
+    #[error("object must be a JSON map")]
+    NotAnObject,
+    #[error("object {0} was not found")]
+    NotFound(ObjectId),
+    #[error("create error: {0}")]
+    Create(#[from] collaborative_objects::error::Create),
+    #[error("update error: {0}")]
+    Update(#[from] collaborative_objects::error::Update),
+    #[error("retrieve error: {0}")]
+    Retrieve(#[from] collaborative_objects::error::Retrieve),
+    #[error(transparent)]
+    Automerge(#[from] AutomergeError),
+}
+
+/// Whether objects of the given type may be written as raw JSON.
+pub fn is_reserved(typename: &TypeName) -> bool {
+    typename.to_string().starts_with(RESERVED_PREFIX)
+}
+
+/// Store for objects of a given type.
+pub struct RawStore<'a> {
+    store: &'a Store<'a>,
+    typename: TypeName,
+}
+
+impl<'a> Deref for RawStore<'a> {
+    type Target = Store<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        self.store
+    }
+}
+
+impl<'a> RawStore<'a> {
+    pub fn new(store: &'a Store<'a>, typename: TypeName) -> Self {
+        Self { store, typename }
+    }
+
+    pub fn typename(&self) -> &TypeName {
+        &self.typename
+    }
+
+    pub fn resolve_id(
+        &self,
+        project: &Urn,
+        identifier: &Identifier,
+    ) -> anyhow::Result<Option<ObjectId>> {
+        self.store
+            .resolve_typed_id(project, &self.typename, identifier)
+    }
+
+    pub fn create(&self, project: &Urn, value: &json::Value) -> Result<ObjectId, Error> {
+        if is_reserved(&self.typename) {
+            return Err(Error::Reserved(self.typename.clone()));
+        }
+        let map = value.as_object().ok_or(Error::NotAnObject)?;
+        let history = events::create(map)?;
+        let cob = self.store.create(
+            &self.whoami,
+            project,
+            NewObjectSpec {
+                typename: self.typename.clone(),
+                message: Some(format!("Create {}", self.typename)),
+                history,
+            },
+        )?;
+
+        Ok(*cob.id())
+    }
+
+    pub fn update(&self, project: &Urn, id: &ObjectId, value: &json::Value) -> Result<(), Error> {
+        if is_reserved(&self.typename) {
+            return Err(Error::Reserved(self.typename.clone()));
+        }
+        let map = value.as_object().ok_or(Error::NotAnObject)?;
+        let mut doc = self.get_raw(project, id)?.ok_or(Error::NotFound(*id))?;
+        let changes = match events::update(&mut doc, map)? {
+            Some(changes) => changes,
+            // Nothing changed, so there is nothing to record.
+            None => return Ok(()),
+        };
+
+        self.store.update(
+            &self.whoami,
+            project,
+            UpdateObjectSpec {
+                object_id: *id,
+                typename: self.typename.clone(),
+                message: Some(format!("Update {}", self.typename)),
+                changes,
+            },
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, project: &Urn, id: &ObjectId) -> Result<Option<json::Value>, Error> {
+        let cob = self.store.retrieve(project, &self.typename, id)?;
+
+        Ok(cob.map(|cob| self::to_json(&self::document(cob.history()))))
+    }
+
+    pub fn all(&self, project: &Urn) -> Result<Vec<(ObjectId, json::Value)>, Error> {
+        let cobs = self.store.list(project, &self.typename)?;
+
+        Ok(cobs
+            .iter()
+            .map(|cob| (*cob.id(), self::to_json(&self::document(cob.history()))))
+            .collect())
+    }
+
+    pub fn get_raw(&self, project: &Urn, id: &ObjectId) -> Result<Option<Automerge>, Error> {
+        let cob = self.store.retrieve(project, &self.typename, id)?;
+        let cob = if let Some(cob) = cob {
+            cob
+        } else {
+            return Ok(None);
+        };
+
+        let doc = cob.history().traverse(Vec::new(), |mut doc, entry| {
+            match entry.contents() {
+                EntryContents::Automerge(bytes) => {
+                    doc.extend(bytes);
+                }
+            }
+            ControlFlow::Continue(doc)
+        });
+        let doc = Automerge::load(&doc)?;
+
+        Ok(Some(doc))
+    }
+}
+
+/// Parse a type name given by the user.
+pub fn typename(s: &str) -> anyhow::Result<TypeName> {
+    TypeName::from_str(s).map_err(|_| anyhow::anyhow!("invalid type name '{}'", s))
+}
+
+/// Build a document from an object history.
+pub fn document(history: &History) -> Automerge {
+    history.traverse(Automerge::new(), |mut doc, entry| {
+        match entry.contents() {
+            EntryContents::Automerge(bytes) => {
+                match automerge::Change::from_bytes(bytes.clone()) {
+                    Ok(change) => {
+                        doc.apply_changes([change]).ok();
+                    }
+                    Err(_err) => {
+                        // Ignore
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(doc)
+    })
+}
+
+/// Convert a document to JSON.
+pub fn to_json(doc: &Automerge) -> json::Value {
+    self::object(doc, &ObjId::Root, ObjType::Map)
+}
+
+fn object(doc: &Automerge, obj_id: &ObjId, typ: ObjType) -> json::Value {
+    match typ {
+        ObjType::Map | ObjType::Table => {
+            let mut map = json::Map::new();
+
+            for key in doc.keys(obj_id) {
+                if let Ok(Some((val, id))) = doc.get(obj_id, key.as_str()) {
+                    map.insert(key, self::value(doc, &id, val));
+                }
+            }
+            json::Value::Object(map)
+        }
+        ObjType::List => {
+            let mut list = Vec::new();
+
+            for i in 0..doc.length(obj_id) {
+                if let Ok(Some((val, id))) = doc.get(obj_id, i) {
+                    list.push(self::value(doc, &id, val));
+                }
+            }
+            json::Value::Array(list)
+        }
+        ObjType::Text => json::Value::String(doc.text(obj_id).unwrap_or_default()),
+    }
+}
+
+fn value(doc: &Automerge, obj_id: &ObjId, val: Value) -> json::Value {
+    match val {
+        Value::Object(typ) => self::object(doc, obj_id, typ),
+        Value::Scalar(scalar) => match scalar.as_ref() {
+            ScalarValue::Str(s) => json::Value::from(s.as_str()),
+            ScalarValue::Int(n) => json::Value::from(*n),
+            ScalarValue::Uint(n) => json::Value::from(*n),
+            ScalarValue::F64(n) => json::Value::from(*n),
+            ScalarValue::Timestamp(n) => json::Value::from(*n),
+            ScalarValue::Boolean(b) => json::Value::from(*b),
+            ScalarValue::Bytes(bytes) => json::Value::from(base64::encode(bytes)),
+            ScalarValue::Null => json::Value::Null,
+            other => json::Value::from(other.to_string()),
+        },
+    }
+}
+
+mod events {
+    use super::*;
+
+    pub fn create(map: &json::Map<String, json::Value>) -> Result<EntryContents, AutomergeError> {
+        let mut doc = Automerge::new();
+
+        doc.transact_with::<_, _, AutomergeError, _, ()>(
+            |_| CommitOptions::default().with_message("Create object".to_owned()),
+            |tx| {
+                for (key, val) in map {
+                    put(tx, &ObjId::Root, key, val)?;
+                }
+                Ok(())
+            },
+        )
+        .map_err(|failure| failure.error)?;
+
+        Ok(EntryContents::Automerge(doc.save_incremental()))
+    }
+
+    /// Update a document to the given map. Returns `None` if the document is unchanged.
+    pub fn update(
+        doc: &mut Automerge,
+        map: &json::Map<String, json::Value>,
+    ) -> Result<Option<EntryContents>, AutomergeError> {
+        let current = to_json(doc);
+        let current = current.as_object().cloned().unwrap_or_default();
+        let heads = doc.get_heads();
+
+        doc.transact_with::<_, _, AutomergeError, _, ()>(
+            |_| CommitOptions::default().with_message("Update object".to_owned()),
+            |tx| merge(tx, &ObjId::Root, &current, map),
+        )
+        .map_err(|failure| failure.error)?;
+
+        if doc.get_heads() == heads {
+            return Ok(None);
+        }
+        let change = doc
+            .get_last_local_change()
+            .map(|change| change.raw_bytes().to_vec());
+
+        Ok(change.map(EntryContents::Automerge))
+    }
+
+    /// Apply the difference between two JSON maps to a document object.
+    /// Nested maps are merged, while other values, including lists, are replaced.
+    fn merge(
+        tx: &mut Transaction,
+        obj_id: &ObjId,
+        old: &json::Map<String, json::Value>,
+        new: &json::Map<String, json::Value>,
+    ) -> Result<(), AutomergeError> {
+        for key in old.keys() {
+            if !new.contains_key(key) {
+                tx.delete(obj_id, key.as_str())?;
+            }
+        }
+        for (key, val) in new {
+            match (old.get(key), val) {
+                (Some(old), new) if old == new => {}
+                (Some(json::Value::Object(old)), json::Value::Object(new)) => {
+                    if let Some((_, id)) = tx.get(obj_id, key.as_str())? {
+                        merge(tx, &id, old, new)?;
+                    } else {
+                        put(tx, obj_id, key, val)?;
+                    }
+                }
+                _ => {
+                    put(tx, obj_id, key, val)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn put(
+        tx: &mut Transaction,
+        obj_id: &ObjId,
+        key: &str,
+        val: &json::Value,
+    ) -> Result<(), AutomergeError> {
+        match val {
+            json::Value::Object(map) => {
+                let id = tx.put_object(obj_id, key, ObjType::Map)?;
+                for (k, v) in map {
+                    put(tx, &id, k, v)?;
+                }
+            }
+            json::Value::Array(list) => {
+                let id = tx.put_object(obj_id, key, ObjType::List)?;
+                for (i, v) in list.iter().enumerate() {
+                    insert(tx, &id, i, v)?;
+                }
+            }
+            _ => {
+                tx.put(obj_id, key, scalar(val))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(
+        tx: &mut Transaction,
+        list_id: &ObjId,
+        ix: usize,
+        val: &json::Value,
+    ) -> Result<(), AutomergeError> {
+        match val {
+            json::Value::Object(map) => {
+                let id = tx.insert_object(list_id, ix, ObjType::Map)?;
+                for (k, v) in map {
+                    put(tx, &id, k, v)?;
+                }
+            }
+            json::Value::Array(list) => {
+                let id = tx.insert_object(list_id, ix, ObjType::List)?;
+                for (i, v) in list.iter().enumerate() {
+                    insert(tx, &id, i, v)?;
+                }
+            }
+            _ => {
+                tx.insert(list_id, ix, scalar(val))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scalar(val: &json::Value) -> ScalarValue {
+        match val {
+            json::Value::Bool(b) => ScalarValue::Boolean(*b),
+            json::Value::Number(n) => {
+                if let Some(n) = n.as_i64() {
+                    ScalarValue::Int(n)
+                } else if let Some(n) = n.as_u64() {
+                    ScalarValue::Uint(n)
+                } else {
+                    ScalarValue::F64(n.as_f64().unwrap_or_default())
+                }
+            }
+            json::Value::String(s) => ScalarValue::from(s.as_str()),
+            _ => ScalarValue::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let value = json::json!({
+            "title": "Release v1.0",
+            "done": false,
+            "items": [{ "name": "Changelog", "done": true }, "Tag"],
+            "meta": { "version": 1, "ratio": 0.5 },
+            "owner": null,
+        });
+        let history = events::create(value.as_object().unwrap()).unwrap();
+        let EntryContents::Automerge(bytes) = history;
+        let doc = Automerge::load(&bytes).unwrap();
+
+        assert_eq!(to_json(&doc), value);
+    }
+
+    #[test]
+    fn test_create_and_update() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let checklists = cobs.raw(typename("org.acme.checklist").unwrap());
+        let project = project.urn();
+
+        let id = checklists
+            .create(
+                &project,
+                &json::json!({ "title": "Release", "items": ["Tag"] }),
+            )
+            .unwrap();
+        checklists
+            .update(
+                &project,
+                &id,
+                &json::json!({ "title": "Release v1", "items": ["Tag", "Publish"] }),
+            )
+            .unwrap();
+
+        let value = checklists.get(&project, &id).unwrap().unwrap();
+        assert_eq!(
+            value,
+            json::json!({ "title": "Release v1", "items": ["Tag", "Publish"] })
+        );
+        assert_eq!(checklists.all(&project).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_update_unchanged() {
+        let value = json::json!({ "title": "Release", "items": ["Tag"] });
+        let EntryContents::Automerge(bytes) = events::create(value.as_object().unwrap()).unwrap();
+        let mut doc = Automerge::load(&bytes).unwrap();
+
+        assert!(events::update(&mut doc, value.as_object().unwrap())
+            .unwrap()
+            .is_none());
+        assert!(events::update(
+            &mut doc,
+            json::json!({ "title": "Release v1" }).as_object().unwrap()
+        )
+        .unwrap()
+        .is_some());
+    }
+
+    #[test]
+    fn test_reserved() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let cobs = Store::new(whoami, profile.paths(), &storage);
+        let issues = cobs.raw(typename("xyz.radicle.issue").unwrap());
+
+        assert!(matches!(
+            issues.create(&project.urn(), &json::json!({})),
+            Err(Error::Reserved(_))
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! COB document schema versioning.
+//!
+//! Every COB document records the schema version it was written with, under
+//! the `version` property of its root object. Documents written before
+//! versioning was introduced don't have this property, and are considered
+//! to be at version `0`.
+//!
+//! When a document is decoded, the migrations of its [`Schema`] are applied
+//! to it in order, bringing it up to the current version. Migrations are applied
+//! in-memory only: they are never published, since other peers may still be
+//! running an older version of the software.
+use std::borrow::Borrow;
+use std::fmt;
+
+use automerge::transaction::{CommitOptions, Transactable, Transaction};
+use automerge::{Automerge, AutomergeError, ObjId, ObjType, ScalarValue, Value};
+
+use crate::cobs::shared::{Document, DocumentError, FromValue, ValueError};
+
+/// Root object property holding the schema version.
+pub const VERSION_PROPERTY: &str = "version";
+
+/// Schema version of a COB document.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version(u64);
+
+impl Version {
+    /// Version of documents written before schema versioning.
+    pub const UNVERSIONED: Self = Self(0);
+
+    pub const fn new(version: u64) -> Self {
+        Self(version)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+impl From<Version> for ScalarValue {
+    fn from(version: Version) -> Self {
+        ScalarValue::Uint(version.0)
+    }
+}
+
+impl<'a> FromValue<'a> for Version {
+    fn from_value(val: Value<'a>) -> Result<Self, ValueError> {
+        if let Value::Scalar(scalar) = &val {
+            return match scalar.borrow() {
+                ScalarValue::Uint(v) => Ok(Self(*v)),
+                ScalarValue::Int(v) if *v >= 0 => Ok(Self(*v as u64)),
+                _ => Err(ValueError::InvalidValue(val.to_string())),
+            };
+        }
+        Err(ValueError::InvalidType)
+    }
+}
+
+/// Error migrating a document.
+#[derive(thiserror::Error, Debug)]
+pub enum SchemaError {
+    #[error(transparent)]
+    Document(#[from] DocumentError),
+    #[error(transparent)]
+    Automerge(#[from] AutomergeError),
+}
+
+/// A migration of a document from the previous schema version to the next.
+pub struct Migration {
+    /// Version of the document once the migration is applied.
+    pub version: Version,
+    /// Apply the migration, given the document's root object.
+    pub apply: fn(&mut Transaction, &ObjId) -> Result<(), AutomergeError>,
+}
+
+/// Schema of a COB type.
+pub struct Schema {
+    /// Name of the root object of the document, eg. `"patch"`.
+    pub root: &'static str,
+    /// Migrations, ordered by version.
+    pub migrations: &'static [Migration],
+}
+
+impl Schema {
+    /// The current schema version, ie. the version new documents are written with.
+    pub fn current(&self) -> Version {
+        self.migrations
+            .last()
+            .map(|m| m.version)
+            .unwrap_or(Version::UNVERSIONED)
+    }
+
+    /// Get the schema version of a document.
+    pub fn version(&self, doc: Document) -> Result<Version, DocumentError> {
+        let (_, obj_id) = doc.get(ObjId::Root, self.root)?;
+
+        match doc.val(&obj_id, VERSION_PROPERTY) {
+            Ok(version) => Ok(version),
+            Err(DocumentError::PropertyNotFound(_)) => Ok(Version::UNVERSIONED),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Bring a document up to the current schema version, and return the version the
+    /// document was originally at.
+    ///
+    /// Documents written with a newer version than the current one are left untouched:
+    /// schema changes are expected to be additive, so these can still be decoded.
+    pub fn migrate(&self, doc: &mut Automerge) -> Result<Version, SchemaError> {
+        let version = self.version(Document::new(doc))?;
+        let pending = self
+            .migrations
+            .iter()
+            .filter(|m| m.version > version)
+            .collect::<Vec<_>>();
+
+        if pending.is_empty() {
+            return Ok(version);
+        }
+
+        doc.transact_with::<_, _, SchemaError, _, ()>(
+            |_| CommitOptions::default().with_message(format!("Migrate to {}", self.current())),
+            |tx| {
+                let (_, obj_id) = tx
+                    .get(ObjId::Root, self.root)?
+                    .ok_or_else(|| DocumentError::PropertyNotFound(self.root.to_owned()))?;
+
+                for migration in pending {
+                    (migration.apply)(tx, &obj_id)?;
+                }
+                tx.put(&obj_id, VERSION_PROPERTY, self.current())?;
+
+                Ok(())
+            },
+        )
+        .map_err(|failure| failure.error)?;
+
+        Ok(version)
+    }
+}
+
+/// Migrations shared between COB types.
+pub mod migrations {
+    use super::*;
+
+    /// Versions of the software before labels were introduced didn't create a `labels` map.
+    pub fn labels(tx: &mut Transaction, obj_id: &ObjId) -> Result<(), AutomergeError> {
+        if tx.get(obj_id, "labels")?.is_none() {
+            tx.put_object(obj_id, "labels", ObjType::Map)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: Schema = Schema {
+        root: "thing",
+        migrations: &[Migration {
+            version: Version::new(1),
+            apply: migrations::labels,
+        }],
+    };
+
+    fn document(version: Option<Version>) -> Automerge {
+        let mut doc = Automerge::new();
+
+        doc.transact_with::<_, _, AutomergeError, _, ()>(
+            |_| CommitOptions::default().with_message("Create thing".to_owned()),
+            |tx| {
+                let thing = tx.put_object(ObjId::Root, "thing", ObjType::Map)?;
+
+                tx.put(&thing, "title", "Thing")?;
+                if let Some(version) = version {
+                    tx.put(&thing, VERSION_PROPERTY, version)?;
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        doc
+    }
+
+    #[test]
+    fn test_unversioned() {
+        let mut doc = document(None);
+
+        assert_eq!(
+            SCHEMA.version(Document::new(&doc)).unwrap(),
+            Version::UNVERSIONED
+        );
+        assert_eq!(SCHEMA.migrate(&mut doc).unwrap(), Version::UNVERSIONED);
+
+        let doc = Document::new(&doc);
+        let (_, obj_id) = doc.get(ObjId::Root, "thing").unwrap();
+
+        assert_eq!(SCHEMA.version(doc).unwrap(), SCHEMA.current());
+        assert!(doc.get(&obj_id, "labels").is_ok());
+    }
+
+    #[test]
+    fn test_current() {
+        let mut doc = document(Some(SCHEMA.current()));
+        let heads = doc.get_heads();
+
+        assert_eq!(SCHEMA.migrate(&mut doc).unwrap(), SCHEMA.current());
+        assert_eq!(doc.get_heads(), heads, "No migration should be applied");
+    }
+
+    #[test]
+    fn test_newer() {
+        let mut doc = document(Some(Version::new(99)));
+
+        assert_eq!(SCHEMA.migrate(&mut doc).unwrap(), Version::new(99));
+        assert!(SCHEMA.current() < Version::new(99));
+    }
+}
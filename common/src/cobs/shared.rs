@@ -26,7 +26,7 @@ use librad::profile::Profile;
 use librad::PeerId;
 use radicle_git_ext as git;
 
-use crate::cobs::{issue, patch, user};
+use crate::cobs::{issue, patch, raw, schema, user};
 use crate::{person, project};
 
 #[derive(Debug, thiserror::Error)]
@@ -39,6 +39,19 @@ pub enum Error {
     Retrieve(#[from] collaborative_objects::error::Retrieve),
     #[error(transparent)]
     Automerge(#[from] AutomergeError),
+    #[error(transparent)]
+    Schema(#[from] schema::SchemaError),
+    #[error(transparent)]
+    Validation(#[from] ValidationError),
+}
+
+/// Error validating an object before it is created.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("title cannot be empty")]
+    EmptyTitle,
+    #[error("label name cannot be empty")]
+    EmptyLabelName,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -141,6 +154,10 @@ impl<'a> Store<'a> {
         user::UserStore::new(self)
     }
 
+    pub fn raw(&self, typename: TypeName) -> raw::RawStore<'_> {
+        raw::RawStore::new(self, typename)
+    }
+
     pub fn get<T: Cob>(&self, namespace: &Urn, id: &ObjectId) -> anyhow::Result<Option<T>> {
         let cob = self.store.retrieve(namespace, T::type_name(), id)?;
 
@@ -172,11 +189,20 @@ impl<'a> Store<'a> {
         &self,
         project: &Urn,
         identifier: &Identifier,
+    ) -> anyhow::Result<Option<ObjectId>> {
+        self.resolve_typed_id(project, T::type_name(), identifier)
+    }
+
+    pub fn resolve_typed_id(
+        &self,
+        project: &Urn,
+        typename: &TypeName,
+        identifier: &Identifier,
     ) -> anyhow::Result<Option<ObjectId>> {
         match identifier {
             Identifier::Full(id) => Ok(Some(*id)),
             Identifier::Prefix(prefix) => {
-                let cobs = self.store.list(project, T::type_name())?;
+                let cobs = self.store.list(project, typename)?;
 
                 let matches = cobs
                     .into_iter()
@@ -602,6 +628,15 @@ impl<'a> FromValue<'a> for Urn {
     }
 }
 
+impl<'a> FromValue<'a> for Color {
+    fn from_value(val: Value<'a>) -> Result<Color, ValueError> {
+        let color = String::from_value(val)?;
+        let color = Color::from_str(&color).map_err(|e| ValueError::Other(Arc::new(e)))?;
+
+        Ok(color)
+    }
+}
+
 impl<'a> FromValue<'a> for git::Oid {
     fn from_value(val: Value<'a>) -> Result<git::Oid, ValueError> {
         let oid = String::from_value(val)?;
@@ -621,6 +656,17 @@ impl<'a> FromValue<'a> for git::OneLevel {
     }
 }
 
+impl<'a> FromValue<'a> for bool {
+    fn from_value(val: Value<'a>) -> Result<bool, ValueError> {
+        if let Value::Scalar(scalar) = &val {
+            if let ScalarValue::Boolean(b) = scalar.borrow() {
+                return Ok(*b);
+            }
+        }
+        Err(ValueError::InvalidType)
+    }
+}
+
 impl<'a> FromValue<'a> for String {
     fn from_value(val: Value) -> Result<String, ValueError> {
         val.into_string().map_err(|_| ValueError::InvalidType)
@@ -686,7 +732,9 @@ impl<'a> Document<'a> {
             .get(id, prop.clone())?
             .ok_or_else(|| DocumentError::PropertyNotFound(prop.to_string()))?;
 
-        assert_eq!(list.to_objtype(), Some(ObjType::List));
+        if list.to_objtype() != Some(ObjType::List) {
+            return Err(DocumentError::UnexpectedType(prop.to_string()));
+        }
 
         let mut objs: Vec<V> = Vec::new();
         for i in 0..self.length(&list_id) {
@@ -720,7 +768,9 @@ impl<'a> Document<'a> {
             .get(id, prop.clone())?
             .ok_or_else(|| DocumentError::PropertyNotFound(prop.to_string()))?;
 
-        assert_eq!(obj.to_objtype(), Some(ObjType::Map));
+        if obj.to_objtype() != Some(ObjType::Map) {
+            return Err(DocumentError::UnexpectedType(prop.to_string()));
+        }
 
         let mut map = HashMap::new();
         for key in self.doc.keys(&obj_id) {
@@ -751,7 +801,9 @@ impl<'a> Document<'a> {
             .get(id, prop.clone())?
             .ok_or_else(|| DocumentError::PropertyNotFound(prop.to_string()))?;
 
-        assert_eq!(obj.to_objtype(), Some(ObjType::List));
+        if obj.to_objtype() != Some(ObjType::List) {
+            return Err(DocumentError::UnexpectedType(prop.to_string()));
+        }
 
         let mut acc = T::default();
         for i in 0..self.doc.length(&obj_id) {
@@ -779,7 +831,9 @@ impl<'a> Document<'a> {
             .get(id, prop.clone())?
             .ok_or_else(|| DocumentError::PropertyNotFound(prop.to_string()))?;
 
-        assert_eq!(obj.to_objtype(), Some(ObjType::Map));
+        if obj.to_objtype() != Some(ObjType::Map) {
+            return Err(DocumentError::UnexpectedType(prop.to_string()));
+        }
 
         let mut keys = HashSet::new();
         for key in self.doc.keys(&obj_id) {
@@ -812,6 +866,8 @@ pub enum DocumentError {
     Value(#[from] ValueError),
     #[error("list cannot be empty")]
     EmptyList,
+    #[error("property '{0}' has an unexpected type")]
+    UnexpectedType(String),
 }
 
 pub mod lookup {
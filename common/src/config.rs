@@ -1,12 +1,15 @@
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::fs;
 use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use librad::profile::Profile;
 use librad::PeerId;
+use lnk_clib::seed::store::FileStore;
 use serde::{Deserialize, Serialize};
 use url::{Host, Url};
 
@@ -35,6 +38,15 @@ pub const FILE_NAME_LOCAL: &str = "Radicle.toml";
 /// Configuration file name for the profile scope.
 pub const FILE_NAME_PROFILE: &str = "config.toml";
 
+/// Configuration scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Configuration of the active profile.
+    Profile,
+    /// Configuration of the current working copy.
+    Local,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SeedConfig {
     /// "Pet" name of the seed.
@@ -47,20 +59,105 @@ pub struct SeedConfig {
     pub api: Url,
 }
 
+impl SeedConfig {
+    /// Create a seed configuration for the given host, using the default ports.
+    pub fn new(host: Host, peer: Option<PeerId>, name: Option<String>) -> Self {
+        let mut p2p: Url = Address::new(host.clone(), Protocol::Link { peer }).into();
+        let mut git: Url = Address::new(host.clone(), Protocol::Git { local: false }).into();
+        let mut api: Url = Address::new(host, Protocol::Api { local: false }).into();
+
+        p2p.set_port(Some(DEFAULT_SEED_P2P_PORT)).ok();
+        git.set_port(Some(DEFAULT_SEED_GIT_PORT)).ok();
+        api.set_port(Some(DEFAULT_SEED_API_PORT)).ok();
+
+        Self {
+            name,
+            p2p,
+            git,
+            api,
+        }
+    }
+
+    /// The seed's peer id, if known.
+    pub fn peer(&self) -> Option<PeerId> {
+        Address::try_from(self.p2p.clone())
+            .ok()
+            .and_then(|addr| addr.peer())
+    }
+
+    /// Whether this seed is designated by the given name, host or URL.
+    pub fn matches(&self, seed: &str) -> bool {
+        self.name.as_deref() == Some(seed)
+            || self.p2p.host_str() == Some(seed)
+            || [&self.p2p, &self.git, &self.api]
+                .iter()
+                .any(|url| url.as_str().trim_end_matches('/') == seed.trim_end_matches('/'))
+    }
+}
+
 impl TryFrom<SeedConfig> for Seed<String> {
     type Error = anyhow::Error;
 
     fn try_from(cfg: SeedConfig) -> Result<Self, Self::Error> {
         let addr: Address = cfg.p2p.try_into()?;
-        let seed: Seed<String> = addr.try_into()?;
+        let mut seed: Seed<String> = addr.try_into()?;
+
+        seed.label = cfg.name;
 
         Ok(seed)
     }
 }
 
+impl TryFrom<Seed<String>> for SeedConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(seed: Seed<String>) -> Result<Self, Self::Error> {
+        let ip = |ip: IpAddr| match ip {
+            IpAddr::V4(ip) => Host::Ipv4(ip),
+            IpAddr::V6(ip) => Host::Ipv6(ip),
+        };
+        // Addresses are either socket addresses, eg. `[::1]:8776`, bare IP addresses or
+        // domain names with an optional port.
+        let (host, port) = if let Ok(addr) = seed.addrs.parse::<SocketAddr>() {
+            (ip(addr.ip()), Some(addr.port()))
+        } else if let Ok(addr) = seed.addrs.parse::<IpAddr>() {
+            (ip(addr), None)
+        } else {
+            match seed.addrs.rsplit_once(':') {
+                Some((host, port)) if !seed.addrs.ends_with(']') => {
+                    (Host::parse(host)?, Some(port.parse::<u16>()?))
+                }
+                _ => (Host::parse(&seed.addrs)?, None),
+            }
+        };
+        let mut cfg = SeedConfig::new(host, Some(seed.peer), seed.label);
+
+        if port.is_some() {
+            cfg.p2p.set_port(port).ok();
+        }
+        Ok(cfg)
+    }
+}
+
+/// External signer, used instead of the profile's keystore to sign with the profile key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerConfig {
+    /// Signer executable.
+    pub program: PathBuf,
+    /// Arguments passed to the signer.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub seed: Vec<SeedConfig>,
+    /// External signer of the profile, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<SignerConfig>,
+    /// Command aliases, eg. `pl = "patch --list"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub alias: BTreeMap<String, String>,
 }
 
 impl Default for Config {
@@ -72,27 +169,11 @@ impl Default for Config {
                     let host = String::from(*host);
                     let peer = PeerId::from_str(peer).ok();
 
-                    let mut p2p: Url =
-                        Address::new(Host::Domain(host.clone()), Protocol::Link { peer }).into();
-                    let mut git: Url =
-                        Address::new(Host::Domain(host.clone()), Protocol::Git { local: false })
-                            .into();
-                    let mut api: Url =
-                        Address::new(Host::Domain(host.clone()), Protocol::Api { local: false })
-                            .into();
-
-                    p2p.set_port(Some(DEFAULT_SEED_P2P_PORT)).ok();
-                    git.set_port(Some(DEFAULT_SEED_GIT_PORT)).ok();
-                    api.set_port(Some(DEFAULT_SEED_API_PORT)).ok();
-
-                    SeedConfig {
-                        name: Some(host),
-                        p2p,
-                        git,
-                        api,
-                    }
+                    SeedConfig::new(Host::Domain(host.clone()), peer, Some(host))
                 })
                 .collect(),
+            signer: None,
+            alias: BTreeMap::new(),
         }
     }
 }
@@ -142,7 +223,339 @@ impl Config {
             .with_file_name(FILE_NAME_PROFILE)
     }
 
+    /// Path of the configuration file for the given scope.
+    pub fn scope_path(scope: Scope, profile: &Profile) -> PathBuf {
+        match scope {
+            Scope::Profile => Self::path(profile),
+            Scope::Local => PathBuf::from(FILE_NAME_LOCAL),
+        }
+    }
+
+    /// Read the configuration of the given scope. If there is no configuration file
+    /// for that scope, the default configuration is returned for the profile scope, and
+    /// an empty configuration for the local scope.
+    pub fn scope(scope: Scope, profile: &Profile) -> Result<Self, io::Error> {
+        match Self::read(Self::scope_path(scope, profile)) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => match scope {
+                Scope::Profile => Ok(Self::default()),
+                Scope::Local => Ok(Self {
+                    seed: Vec::new(),
+                    signer: None,
+                    alias: BTreeMap::new(),
+                }),
+            },
+            result => result,
+        }
+    }
+
     pub fn seeds(&self) -> impl Iterator<Item = &SeedConfig> {
         self.seed.iter()
     }
+
+    /// The default seed, ie. the first one configured.
+    pub fn default_seed(&self) -> Option<&SeedConfig> {
+        self.seed.first()
+    }
+
+    /// The command and arguments of the given alias, if defined. Arguments are split as
+    /// by a shell: they are separated by whitespace, and may be quoted with single or
+    /// double quotes, eg. `c = "comment -m 'looks good'"`.
+    pub fn alias(&self, name: &str) -> Result<Option<Vec<String>>, io::Error> {
+        self.alias
+            .get(name)
+            .map(|alias| split_words(alias))
+            .transpose()
+    }
+
+    /// Find a seed by name, host or URL.
+    pub fn find(&self, seed: &str) -> Option<&SeedConfig> {
+        self.seed.iter().find(|s| s.matches(seed))
+    }
+
+    /// Add a seed. If a seed with the same P2P URL is already configured, it is replaced.
+    /// Returns `true` if the seed was added, or `false` if it replaced an existing one.
+    pub fn add(&mut self, seed: SeedConfig) -> bool {
+        if let Some(existing) = self.seed.iter_mut().find(|s| s.p2p == seed.p2p) {
+            *existing = seed;
+            return false;
+        }
+        self.seed.push(seed);
+
+        true
+    }
+
+    /// Remove a seed by name, host or URL.
+    pub fn remove(&mut self, seed: &str) -> Option<SeedConfig> {
+        let ix = self.seed.iter().position(|s| s.matches(seed))?;
+
+        Some(self.seed.remove(ix))
+    }
+
+    /// Make a seed the default, by moving it to the front of the list.
+    /// Returns `false` if the seed wasn't found.
+    pub fn set_default(&mut self, seed: &str) -> bool {
+        if let Some(ix) = self.seed.iter().position(|s| s.matches(seed)) {
+            let seed = self.seed.remove(ix);
+            self.seed.insert(0, seed);
+
+            return true;
+        }
+        false
+    }
+
+    /// Migrate the legacy profile seeds file into this configuration, and write it as the
+    /// profile configuration. Seeds that are already configured are skipped. Once the
+    /// configuration is written, the legacy file is renamed, so that it isn't migrated again.
+    ///
+    /// Returns the migrated seeds.
+    pub fn migrate(&mut self, profile: &Profile) -> Result<Vec<SeedConfig>, anyhow::Error> {
+        let seeds_file = profile.paths().seeds_file();
+        if !seeds_file.exists() {
+            return Ok(Vec::new());
+        }
+        let store = FileStore::<String>::new(seeds_file.to_path_buf())?;
+        let mut migrated = Vec::new();
+
+        for seed in store.iter()? {
+            let seed = seed?;
+            let addr = format!("{}@{}", seed.peer, seed.addrs);
+            let seed = SeedConfig::try_from(seed)
+                .and_then(|cfg| Seed::<String>::try_from(cfg.clone()).map(|_| cfg))
+                .map_err(|err| {
+                    anyhow::anyhow!(
+                        "invalid seed '{}' in {}: {}",
+                        addr,
+                        seeds_file.display(),
+                        err
+                    )
+                })?;
+
+            if !self.seed.iter().any(|s| s.p2p == seed.p2p) {
+                self.seed.push(seed.clone());
+                migrated.push(seed);
+            }
+        }
+        // Only retire the legacy file once its seeds are safely in the configuration.
+        if !migrated.is_empty() {
+            self.write(Self::path(profile))?;
+        }
+        fs::rename(seeds_file, seeds_file.with_extension("migrated"))?;
+
+        Ok(migrated)
+    }
+}
+
+/// Split a string into words, following shell quoting rules: words are separated by
+/// whitespace, single quotes preserve their contents, and in double quotes or unquoted,
+/// a backslash escapes the next character.
+fn split_words(input: &str) -> Result<Vec<String>, io::Error> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated(input)),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated(input)),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated(input)),
+                    }
+                }
+            }
+            '\\' => {
+                let c = chars.next().ok_or_else(|| unterminated(input))?;
+                word.get_or_insert_with(String::new).push(c);
+            }
+            c => {
+                word.get_or_insert_with(String::new).push(c);
+            }
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+fn unterminated(input: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unterminated quote or escape in `{}`", input),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv6Addr;
+
+    #[test]
+    fn test_seeds_add_remove() {
+        let mut config = Config::default();
+        let seed = SeedConfig::new(Host::Domain("seed.acme.org".to_owned()), None, None);
+
+        assert!(config.add(seed.clone()));
+        assert!(!config.add(seed));
+        assert_eq!(config.seeds().count(), DEFAULT_SEEDS.len() + 1);
+
+        assert!(config.set_default("seed.acme.org"));
+        assert_eq!(
+            config.default_seed().unwrap().p2p.host_str(),
+            Some("seed.acme.org")
+        );
+        assert!(!config.set_default("seed.example.org"));
+
+        assert!(config.remove("https://seed.acme.org:8777").is_some());
+        assert!(config.find("seed.acme.org").is_none());
+        assert!(config.remove("pine.radicle.garden").is_some());
+        assert_eq!(config.seeds().count(), DEFAULT_SEEDS.len() - 1);
+    }
+
+    #[test]
+    fn test_signer() {
+        let config: Config = toml::from_str(
+            r#"
+            seed = []
+
+            [signer]
+            program = "/usr/local/bin/rad-signer"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.signer,
+            Some(SignerConfig {
+                program: PathBuf::from("/usr/local/bin/rad-signer"),
+                args: vec![],
+            })
+        );
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("signer"));
+    }
+
+    #[test]
+    fn test_alias() {
+        let config: Config = toml::from_str(
+            r#"
+            seed = []
+
+            [alias]
+            pl = "patch  --list"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.alias("pl").unwrap(),
+            Some(vec!["patch".to_owned(), "--list".to_owned()])
+        );
+        assert_eq!(config.alias("patch").unwrap(), None);
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("alias"));
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words(r#"comment -m "looks good""#).unwrap(),
+            vec!["comment", "-m", "looks good"]
+        );
+        assert_eq!(
+            split_words(r#"comment -m 'say "hi"' a\ b"#).unwrap(),
+            vec!["comment", "-m", "say \"hi\"", "a b"]
+        );
+        assert_eq!(
+            split_words(r#"x "a \"q\" \n" '' "#).unwrap(),
+            vec!["x", "a \"q\" \\n", ""]
+        );
+        assert!(split_words("comment -m 'looks good").is_err());
+        assert!(split_words("comment \\").is_err());
+    }
+
+    #[test]
+    fn test_seed_from_legacy() {
+        let seed: Seed<String> =
+            "hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa@pine.radicle.garden:9999"
+                .parse()
+                .unwrap();
+        let cfg = SeedConfig::try_from(seed.clone()).unwrap();
+
+        assert_eq!(cfg.peer(), Some(seed.peer));
+        assert_eq!(cfg.p2p.port(), Some(9999));
+        assert_eq!(cfg.api.host_str(), Some("pine.radicle.garden"));
+
+        let peer = "hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa";
+        let seed: Seed<String> = format!("{}@[::1]:9999", peer).parse().unwrap();
+        let cfg = SeedConfig::try_from(seed).unwrap();
+
+        assert_eq!(cfg.p2p.host(), Some(Host::Ipv6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(cfg.p2p.port(), Some(9999));
+
+        let seed: Seed<String> = format!("{}@[::1]", peer).parse().unwrap();
+        let cfg = SeedConfig::try_from(seed).unwrap();
+
+        assert_eq!(cfg.p2p.host(), Some(Host::Ipv6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(cfg.p2p.port(), Some(DEFAULT_SEED_P2P_PORT));
+    }
+
+    #[test]
+    fn test_scope_migrate() {
+        let (_, profile, _, _) = crate::test::setup::profile();
+        let peer = "hyd1to75dyfpizchxp43rdwhisp8nbr76g5pxa5f4y7jh4pa6jjzns";
+        let seeds_file = profile.paths().seeds_file().to_path_buf();
+
+        fs::remove_file(Config::path(&profile)).ok();
+        let config = Config::scope(Scope::Profile, &profile).unwrap();
+        assert_eq!(config.seeds().count(), DEFAULT_SEEDS.len());
+
+        fs::write(&seeds_file, format!("{}@seed.example.com:9999\n", peer)).unwrap();
+        let migrated = config_migrate(&profile).unwrap();
+
+        assert_eq!(migrated.len(), 1);
+        assert!(!seeds_file.exists());
+        assert_eq!(
+            Config::profile(&profile).unwrap().seeds().count(),
+            DEFAULT_SEEDS.len() + 1
+        );
+
+        // Invalid seeds aren't migrated, and the legacy file is kept.
+        fs::write(&seeds_file, format!("{}@seed example:port\n", peer)).unwrap();
+
+        assert!(config_migrate(&profile).is_err());
+        assert!(seeds_file.exists());
+        assert_eq!(
+            Config::profile(&profile).unwrap().seeds().count(),
+            DEFAULT_SEEDS.len() + 1
+        );
+    }
+
+    fn config_migrate(profile: &Profile) -> Result<Vec<SeedConfig>, anyhow::Error> {
+        Config::scope(Scope::Profile, profile)?.migrate(profile)
+    }
 }
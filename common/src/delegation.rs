@@ -0,0 +1,339 @@
+//! Project delegations, and identity updates awaiting the signatures of delegates.
+//!
+//! An update to a project identity, eg. adding a delegate, only takes effect once it is
+//! signed by a quorum of the current delegates, ie. more than half of them. Until then,
+//! it is *pending*: the delegate who proposed it has signed it, and the other delegates
+//! sign it by merging it into their own identity history.
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+
+use either::Either;
+use thiserror::Error;
+
+use librad::crypto::PublicKey;
+use librad::git::identities::{self, project, Person, Project};
+use librad::git::storage::{ReadOnly, Storage};
+use librad::git::types::{Namespace, Reference};
+use librad::git::Urn;
+use librad::PeerId;
+
+use crate::person;
+use crate::project::Delegate;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("project {0} not found")]
+    NotFound(Urn),
+    #[error("{0} is already a delegate")]
+    AlreadyDelegate(String),
+    #[error("{0} is not a delegate")]
+    NotDelegate(String),
+    #[error("a project must have at least one delegate")]
+    LastDelegate,
+    #[error("no pending update from {0}")]
+    NoPendingUpdate(PeerId),
+    #[error(transparent)]
+    Identities(#[from] identities::Error),
+    #[error(transparent)]
+    Update(#[from] lnk_identities::project::Error),
+    #[error(transparent)]
+    Local(#[from] lnk_identities::local::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// A delegation to add or remove: either a key or a person identity.
+pub type Delegation = Either<PublicKey, Urn>;
+
+/// Signature status of a project identity revision.
+#[derive(Debug, Clone)]
+pub struct Status {
+    /// Number of delegations that signed the revision.
+    pub signed: usize,
+    /// Number of signatures needed for the revision to take effect.
+    pub quorum: usize,
+    /// Delegates that didn't sign the revision.
+    pub missing: Vec<Delegate>,
+}
+
+impl Status {
+    /// Get the signature status of a revision, against its own delegations.
+    pub fn of(project: &Project) -> Self {
+        let mut signed = 0;
+        let mut missing = Vec::new();
+
+        for delegation in project.delegations().iter() {
+            match delegation {
+                Either::Left(pk) => {
+                    if project.signatures.contains_key(pk) {
+                        signed += 1;
+                    } else {
+                        missing.push(Delegate::Direct {
+                            id: PeerId::from(*pk),
+                        });
+                    }
+                }
+                Either::Right(person) => {
+                    // A person signs with any of its keys.
+                    if person
+                        .delegations()
+                        .iter()
+                        .any(|pk| project.signatures.contains_key(pk))
+                    {
+                        signed += 1;
+                    } else {
+                        missing.push(Delegate::Indirect {
+                            urn: person.urn(),
+                            ids: person
+                                .delegations()
+                                .iter()
+                                .map(|pk| PeerId::from(*pk))
+                                .collect(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Self {
+            signed,
+            quorum: quorum(project),
+            missing,
+        }
+    }
+
+    /// Get the signature status of a revision of a person identity.
+    pub fn of_person(person: &Person) -> Self {
+        let delegations = person.delegations();
+        let missing = delegations
+            .iter()
+            .filter(|pk| !person.signatures.contains_key(pk))
+            .map(|pk| Delegate::Direct {
+                id: PeerId::from(*pk),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            signed: delegations.len() - missing.len(),
+            quorum: delegations.len() / 2 + 1,
+            missing,
+        }
+    }
+
+    /// Whether the revision is signed by a quorum of delegates.
+    pub fn is_quorum(&self) -> bool {
+        self.signed >= self.quorum
+    }
+}
+
+/// An update to a project identity, that isn't signed by a quorum of delegates yet.
+#[derive(Debug, Clone)]
+pub struct Pending {
+    /// Delegate who proposed the update, or `None` if it was proposed locally.
+    pub peer: Option<PeerId>,
+    /// Proposed revision of the identity.
+    pub project: Project,
+    /// Signature status of the proposed revision.
+    pub status: Status,
+}
+
+/// Number of delegate signatures needed for an update to take effect.
+pub fn quorum(project: &Project) -> usize {
+    project.delegations().len() / 2 + 1
+}
+
+/// Get the delegations of a project, in a form suitable for updates.
+pub fn delegations(project: &Project) -> BTreeSet<Delegation> {
+    project
+        .delegations()
+        .iter()
+        .map(|d| match d {
+            Either::Left(pk) => Either::Left(*pk),
+            Either::Right(person) => Either::Right(person.urn()),
+        })
+        .collect()
+}
+
+/// Add a delegate to a project. Returns the updated project, which is pending until
+/// signed by a quorum of delegates.
+pub fn add(storage: &Storage, urn: &Urn, delegate: Delegation) -> Result<Project, Error> {
+    let project = latest(storage.read_only(), urn)?;
+    let mut delegations = delegations(&project);
+
+    if !delegations.insert(delegate.clone()) {
+        return Err(Error::AlreadyDelegate(display(&delegate)));
+    }
+    update(storage, urn, delegations)
+}
+
+/// Remove a delegate from a project. The delegate may be specified by one of its keys,
+/// if it is a person. Returns the updated project, which is pending until signed by a
+/// quorum of delegates.
+pub fn remove(storage: &Storage, urn: &Urn, delegate: Delegation) -> Result<Project, Error> {
+    let project = latest(storage.read_only(), urn)?;
+    let removed = project
+        .delegations()
+        .iter()
+        .find_map(|d| match (d, &delegate) {
+            (Either::Left(pk), Either::Left(key)) if pk == key => Some(Either::Left(*pk)),
+            (Either::Right(person), Either::Left(key))
+                if person.delegations().iter().any(|pk| pk == key) =>
+            {
+                Some(Either::Right(person.urn()))
+            }
+            (Either::Right(person), Either::Right(urn)) if &person.urn() == urn => {
+                Some(Either::Right(person.urn()))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| Error::NotDelegate(display(&delegate)))?;
+
+    let mut delegations = delegations(&project);
+    delegations.remove(&removed);
+
+    if delegations.is_empty() {
+        return Err(Error::LastDelegate);
+    }
+    update(storage, urn, delegations)
+}
+
+/// Sign the pending update proposed by the given delegate, by merging it into our
+/// identity history.
+pub fn accept(storage: &Storage, urn: &Urn, peer: PeerId) -> Result<Project, Error> {
+    let pending = pending(storage.read_only(), urn)?;
+
+    if !pending.iter().any(|p| p.peer == Some(peer)) {
+        return Err(Error::NoPendingUpdate(peer));
+    }
+    let project = project::merge(storage, urn, peer)?;
+
+    Ok(project)
+}
+
+/// Get the pending updates of a project identity: our own, if we proposed one, and
+/// those proposed by other delegates.
+pub fn pending(storage: &ReadOnly, urn: &Urn) -> Result<Vec<Pending>, Error> {
+    let verified = project::verify(storage, urn)?.ok_or_else(|| Error::NotFound(urn.clone()))?;
+    let verified = verified.into_inner();
+    let local = project::get(storage, urn)?;
+    let mut pending = Vec::new();
+
+    if let Some(local) = &local {
+        if local.revision != verified.revision {
+            pending.push(Pending {
+                peer: None,
+                status: Status::of(local),
+                project: local.clone(),
+            });
+        }
+    }
+
+    for delegate in verified.delegations().iter() {
+        let keys = match delegate {
+            Either::Left(pk) => vec![*pk],
+            Either::Right(person) => person.delegations().iter().copied().collect(),
+        };
+        for key in keys {
+            let peer = PeerId::from(key);
+            if &peer == storage.peer_id() {
+                continue;
+            }
+            let remote =
+                Urn::try_from(Reference::rad_id(Namespace::from(urn.clone())).with_remote(peer))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+
+            if let Some(proposed) = project::get(storage, &remote)? {
+                let known = proposed.revision == verified.revision
+                    || local
+                        .as_ref()
+                        .map_or(false, |l| l.revision == proposed.revision);
+
+                if !known {
+                    pending.push(Pending {
+                        peer: Some(peer),
+                        status: Status::of(&proposed),
+                        project: proposed,
+                    });
+                }
+            }
+        }
+    }
+    Ok(pending)
+}
+
+/// Get the latest revision of a project identity that we know of, whether or not it is
+/// signed by a quorum.
+fn latest(storage: &ReadOnly, urn: &Urn) -> Result<Project, Error> {
+    project::get(storage, urn)?.ok_or_else(|| Error::NotFound(urn.clone()))
+}
+
+/// Update the delegations of a project, signing the update with the local identity.
+fn update(
+    storage: &Storage,
+    urn: &Urn,
+    delegations: BTreeSet<Delegation>,
+) -> Result<Project, Error> {
+    let whoami = person::local(storage)?;
+    let project = lnk_identities::project::update(
+        storage,
+        urn,
+        Some(whoami.urn()),
+        None,
+        vec![],
+        Some(delegations),
+    )?;
+
+    Ok(project)
+}
+
+fn display(delegate: &Delegation) -> String {
+    match delegate {
+        Either::Left(pk) => PeerId::from(*pk).to_string(),
+        Either::Right(urn) => urn.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+    use librad::crypto::SecretKey;
+
+    #[test]
+    fn test_add_remove() {
+        let (storage, _profile, _whoami, project) = test::setup::profile();
+        let urn = project.urn();
+
+        assert!(Status::of(&project).is_quorum());
+        assert!(pending(storage.read_only(), &urn).unwrap().is_empty());
+
+        let peer = PeerId::from(SecretKey::new());
+        let key = *peer.as_public_key();
+        let updated = add(&storage, &urn, Either::Left(key)).unwrap();
+        let status = Status::of(&updated);
+
+        assert_eq!(status.quorum, 2);
+        assert_eq!(status.signed, 1);
+        assert!(matches!(
+            status.missing.as_slice(),
+            [Delegate::Direct { id }] if id == &peer
+        ));
+
+        let pending = pending(storage.read_only(), &urn).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].peer, None);
+
+        assert!(matches!(
+            add(&storage, &urn, Either::Left(key)),
+            Err(Error::AlreadyDelegate(_))
+        ));
+        assert!(matches!(
+            remove(
+                &storage,
+                &urn,
+                Either::Left(*PeerId::from(SecretKey::new()).as_public_key())
+            ),
+            Err(Error::NotDelegate(_))
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+//! Linking devices to a person identity.
+//!
+//! Each device has its own key. To use the same person identity from a new device, the
+//! new device creates a link request, signed with its key, which is taken out-of-band
+//! to a device already linked to the person. That device adds the new key to the
+//! person's delegations.
+//!
+//! Since the update needs to be signed by a quorum of the person's keys, the updated
+//! identity is then taken back to the new device, eg. as a bundle, where it is signed
+//! and set as the local identity.
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use librad::crypto::{BoxedSigner, PublicKey, Signature};
+use librad::git::identities::{self, person as identity, Person};
+use librad::git::storage::Storage;
+use librad::git::types::{Namespace, Reference};
+use librad::git::Urn;
+use librad::{PeerId, Signer};
+
+use crate::person;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid link request: {0}")]
+    Request(#[from] serde_json::Error),
+    #[error("failed to sign link request: {0}")]
+    Sign(String),
+    #[error("link request signature is invalid")]
+    InvalidSignature,
+    #[error("device {0} is already linked")]
+    AlreadyLinked(PeerId),
+    #[error("device {0} is not linked to {1}")]
+    NotLinked(PeerId, Urn),
+    #[error("person {0} not found")]
+    NotFound(Urn),
+    #[error(transparent)]
+    Identities(#[from] identities::Error),
+    #[error(transparent)]
+    Update(#[from] lnk_identities::person::Error),
+    #[error(transparent)]
+    Local(#[from] lnk_identities::local::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Payload {
+    /// Device to link.
+    peer: PeerId,
+}
+
+/// Request to link a device to a person identity, signed by the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    #[serde(flatten)]
+    payload: Payload,
+    signature: Signature,
+}
+
+impl Request {
+    /// Create a link request for the device of the given signer.
+    pub fn new(signer: &BoxedSigner) -> Result<Self, Error> {
+        let payload = Payload {
+            peer: PeerId::from_signer(signer),
+        };
+        let signature = signer
+            .sign_blocking(&serde_json::to_vec(&payload)?)
+            .map(Signature::from)
+            .map_err(|e| Error::Sign(e.to_string()))?;
+
+        Ok(Self { payload, signature })
+    }
+
+    /// Read a link request, and verify its signature.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let request: Self = serde_json::from_slice(&fs::read(path)?)?;
+        let payload = serde_json::to_vec(&request.payload)?;
+
+        if !request
+            .payload
+            .peer
+            .as_public_key()
+            .verify(&request.signature, &payload)
+        {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(request)
+    }
+
+    /// Write the link request to the given path.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Device to link.
+    pub fn peer(&self) -> &PeerId {
+        &self.payload.peer
+    }
+}
+
+/// Outcome of accepting a person identity linked to this device.
+#[derive(Debug)]
+pub struct Accepted {
+    pub person: Person,
+    /// Whether we signed a revision that the other device didn't have our signature
+    /// on. If so, the other device needs our view of the identity.
+    pub signed: bool,
+}
+
+/// Link the requesting device to the local person identity, by adding its key to the
+/// person's delegations. Returns the updated person, which needs to be signed by the
+/// linked device to take effect.
+pub fn link(storage: &Storage, request: &Request) -> Result<Person, Error> {
+    let whoami = person::local(storage)?;
+    let key = *request.peer().as_public_key();
+    let mut keys = whoami
+        .delegations()
+        .iter()
+        .copied()
+        .collect::<Vec<PublicKey>>();
+
+    if keys.contains(&key) {
+        return Err(Error::AlreadyLinked(*request.peer()));
+    }
+    keys.push(key);
+
+    let person = lnk_identities::person::update(
+        storage,
+        &whoami.urn(),
+        Some(whoami.urn()),
+        None,
+        vec![],
+        Some(keys.into_iter()),
+    )?;
+
+    Ok(person)
+}
+
+/// Accept the person identity as seen by the given device, eg. after importing it from
+/// a bundle, signing it if this device is one of its keys. The person is set as the
+/// local identity.
+pub fn accept(storage: &Storage, urn: &Urn, from: PeerId) -> Result<Accepted, Error> {
+    let local = storage.peer_id();
+    let remote = Urn::try_from(Reference::rad_id(Namespace::from(urn.clone())).with_remote(from))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let theirs = identity::get(storage, &remote)?.ok_or_else(|| Error::NotFound(urn.clone()))?;
+
+    if !theirs
+        .delegations()
+        .iter()
+        .any(|pk| pk == local.as_public_key())
+    {
+        return Err(Error::NotLinked(*local, urn.clone()));
+    }
+    let signed = !theirs.signatures.contains_key(local.as_public_key());
+    let person = identity::merge(storage, urn, from)?;
+
+    person::set_local(storage, &person)?;
+
+    Ok(Accepted { person, signed })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bundle;
+    use crate::signer::ZeroizingSecretKey;
+    use crate::test;
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+    use librad::crypto::SecretKey;
+
+    #[test]
+    fn test_request() {
+        let (storage, profile, _whoami, _project) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass).unwrap();
+        let path = profile.paths().git_dir().join("link-request.json");
+
+        Request::new(&signer).unwrap().save(&path).unwrap();
+
+        let request = Request::load(&path).unwrap();
+        assert_eq!(request.peer(), storage.peer_id());
+
+        // A request can't be made on behalf of another device.
+        let mut forged = request;
+        forged.payload.peer = PeerId::from(SecretKey::new());
+        forged.save(&path).unwrap();
+
+        assert!(matches!(Request::load(&path), Err(Error::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_link() {
+        let (storage, _profile, whoami, _project) = test::setup::profile();
+        // Sign the request with the key of another device.
+        let signer = BoxedSigner::new(ZeroizingSecretKey::new(SecretKey::new()));
+        let request = Request::new(&signer).unwrap();
+        let person = link(&storage, &request).unwrap();
+
+        assert_eq!(person.urn(), whoami.urn());
+        assert!(person
+            .delegations()
+            .iter()
+            .any(|pk| pk == request.peer().as_public_key()));
+        assert!(matches!(
+            link(&storage, &request),
+            Err(Error::AlreadyLinked(_))
+        ));
+    }
+
+    #[test]
+    fn test_link_exchange() {
+        let (linked, profile, whoami, _) = test::setup::profile();
+        let (new, new_profile, _, _) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass.clone()).unwrap();
+        let new_signer = test::signer(&new_profile, pass).unwrap();
+        let dir = profile.paths().git_dir().parent().unwrap().to_path_buf();
+        let urn = whoami.urn();
+
+        // The new device can't accept a person it isn't a delegate of.
+        bundle::export(
+            linked.read_only(),
+            &signer,
+            &urn,
+            &dir.join("person.bundle"),
+        )
+        .unwrap();
+        bundle::import(new.read_only(), &dir.join("person.bundle")).unwrap();
+        assert!(matches!(
+            accept(&new, &urn, *linked.peer_id()),
+            Err(Error::NotLinked(..))
+        ));
+
+        // 1. The new device creates a link request.
+        let request_path = dir.join("link-request.json");
+        Request::new(&new_signer)
+            .unwrap()
+            .save(&request_path)
+            .unwrap();
+
+        // 2. The linked device adds the new device, and bundles the updated person.
+        let request = Request::load(&request_path).unwrap();
+        link(&linked, &request).unwrap();
+        bundle::export(linked.read_only(), &signer, &urn, &dir.join("link.bundle")).unwrap();
+
+        // 3. The new device signs the person, switches to it, and bundles it back.
+        let manifest = bundle::import(new.read_only(), &dir.join("link.bundle")).unwrap();
+        let accepted = accept(&new, manifest.urn(), *manifest.peer()).unwrap();
+
+        assert!(accepted.signed);
+        assert_eq!(accepted.person.urn(), urn);
+        assert_eq!(person::local(&new).unwrap().urn(), urn);
+        assert!(accepted
+            .person
+            .signatures
+            .contains_key(new.peer_id().as_public_key()));
+
+        bundle::export(
+            new.read_only(),
+            &new_signer,
+            &urn,
+            &dir.join("reply.bundle"),
+        )
+        .unwrap();
+
+        // 4. The linked device accepts the new device's signature. It had already signed,
+        // so there is nothing to send back.
+        let manifest = bundle::import(linked.read_only(), &dir.join("reply.bundle")).unwrap();
+        let accepted = accept(&linked, manifest.urn(), *manifest.peer()).unwrap();
+
+        assert!(!accepted.signed);
+        for peer in [linked.peer_id(), new.peer_id()] {
+            assert!(accepted
+                .person
+                .delegations()
+                .iter()
+                .any(|pk| pk == peer.as_public_key()));
+            assert!(accepted
+                .person
+                .signatures
+                .contains_key(peer.as_public_key()));
+        }
+        assert!(matches!(
+            link(&linked, &request),
+            Err(Error::AlreadyLinked(_))
+        ));
+    }
+}
@@ -17,7 +17,7 @@ use librad::{crypto::BoxedSigner, PeerId};
 
 pub use git2::{
     build::CheckoutBuilder, AnnotatedCommit, Commit, Direction, ErrorCode, MergeAnalysis,
-    MergeOptions, Oid, Reference, Repository, Signature,
+    MergeOptions, Oid, Reference, Repository, Signature, Time,
 };
 pub use librad::git::local::transport;
 pub use librad::git::types::remote::LocalFetchspec;
@@ -32,6 +32,10 @@ pub const CONFIG_SIGNING_KEY: &str = "user.signingkey";
 pub const CONFIG_GPG_FORMAT: &str = "gpg.format";
 pub const CONFIG_GPG_SSH_PROGRAM: &str = "gpg.ssh.program";
 pub const CONFIG_GPG_SSH_ALLOWED_SIGNERS: &str = "gpg.ssh.allowedSignersFile";
+pub const CONFIG_GPG_SSH_REVOCATION_FILE: &str = "gpg.ssh.revocationFile";
+
+/// File listing the revoked signing keys of a repository, next to `.gitsigners`.
+pub const GITREVOKED: &str = ".gitrevoked";
 
 /// Minimum required git version.
 pub const VERSION_REQUIRED: Version = Version {
@@ -192,10 +196,79 @@ pub fn add_gitsigners<'a>(
     Ok(())
 }
 
-/// Read a `.gitsigners` file. Returns SSH keys.
+/// Revoke signers, by adding them to the repository's `.gitrevoked` file. The file is
+/// created if needed.
+///
+/// Revoked keys are left out by [`read_gitsigners`], and once the file is configured as
+/// the revocation file with [`configure_revocation`], git rejects signatures made with
+/// them, even if they are listed in `.gitsigners`.
+pub fn revoke_gitsigners<'a>(
+    path: &Path,
+    signers: impl IntoIterator<Item = &'a PeerId>,
+) -> Result<(), io::Error> {
+    let revoked = read_revoked(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path.join(GITREVOKED))?;
+
+    for peer_id in signers.into_iter() {
+        let key = keys::to_ssh_key(peer_id)?;
+        if !revoked.contains(&key) {
+            // Nb. The peer id is the key comment, as understood by `ssh-keygen`.
+            writeln!(file, "{} {}", key, peer_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a `.gitrevoked` file. Returns SSH keys. If there is no such file, no keys
+/// are revoked.
+pub fn read_revoked(path: &Path) -> Result<HashSet<String>, io::Error> {
+    use std::io::BufRead;
+
+    let mut keys = HashSet::new();
+    let file = match File::open(path.join(GITREVOKED)) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(keys),
+        Err(err) => return Err(err),
+    };
+
+    for line in io::BufReader::new(file).lines() {
+        let line = line?;
+        let mut fields = line.split_whitespace();
+
+        if let (Some(kind), Some(key)) = (fields.next(), fields.next()) {
+            keys.insert(format!("{} {}", kind, key));
+        }
+    }
+    Ok(keys)
+}
+
+/// Configure git to reject signatures made with the keys revoked in `.gitrevoked`.
+pub fn configure_revocation(repo: &Path) -> Result<(), anyhow::Error> {
+    git(
+        repo,
+        [
+            "config",
+            "--local",
+            CONFIG_GPG_SSH_REVOCATION_FILE,
+            GITREVOKED,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Read a `.gitsigners` file. Returns SSH keys. Keys revoked in `.gitrevoked`, or
+/// revoked in any of the local profiles, are left out.
 pub fn read_gitsigners(path: &Path) -> Result<HashSet<String>, io::Error> {
     use std::io::BufRead;
 
+    let mut revoked = read_revoked(path)?;
+    for peer in keys::revoked_locally() {
+        revoked.insert(keys::to_ssh_key(&peer)?);
+    }
     let mut keys = HashSet::new();
     let file = File::open(path.join(".gitsigners"))?;
 
@@ -211,7 +284,9 @@ pub fn read_gitsigners(path: &Path) -> Result<HashSet<String>, io::Error> {
                     ));
                 }
             }
-            keys.insert(key.to_owned());
+            if !revoked.contains(key) {
+                keys.insert(key.to_owned());
+            }
         }
     }
     Ok(keys)
@@ -487,4 +562,30 @@ mod test {
 
         assert!(Version::from_str("2.34").is_err());
     }
+
+    #[test]
+    fn test_revoke_gitsigners() {
+        use librad::crypto::SecretKey;
+
+        let old = PeerId::from(SecretKey::new());
+        let new = PeerId::from(SecretKey::new());
+        let path = std::env::temp_dir().join(format!("rad-gitsigners-{}", new));
+
+        std::fs::create_dir_all(&path).unwrap();
+        write_gitsigners(&path, [&old]).unwrap();
+        assert!(read_revoked(&path).unwrap().is_empty());
+
+        add_gitsigners(&path, [&new]).unwrap();
+        revoke_gitsigners(&path, [&old]).unwrap();
+        revoke_gitsigners(&path, [&old]).unwrap();
+
+        let signers = read_gitsigners(&path).unwrap();
+        let revoked = read_revoked(&path).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(revoked.len(), 1);
+        assert!(revoked.contains(&keys::to_ssh_key(&old).unwrap()));
+        assert!(!signers.contains(&keys::to_ssh_key(&old).unwrap()));
+        assert!(signers.contains(&keys::to_ssh_key(&new).unwrap()));
+    }
 }
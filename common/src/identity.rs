@@ -1,3 +1,5 @@
+pub mod history;
+
 use std::convert::TryFrom;
 use std::str::FromStr;
 
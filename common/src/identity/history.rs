@@ -0,0 +1,297 @@
+//! Identity document history.
+//!
+//! Each revision of an identity is a commit in its `rad/id` history, signed by some of
+//! its delegates. The history is walked from the latest revision we know of, following
+//! first parents.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use either::Either;
+use serde_json::Value;
+
+use librad::git::identities::{any, Person, Project, SomeIdentity};
+use librad::git::storage::ReadOnly;
+use librad::git::Urn;
+use librad::identities::git::Identities;
+use librad::identities::payload::{self, HasNamespace};
+use librad::PeerId;
+
+use crate::delegation::Status;
+use crate::git;
+use crate::person::Ens;
+use crate::project::Delegate;
+
+/// A revision of an identity document.
+#[derive(Debug, Clone)]
+pub struct Revision {
+    /// Commit of the revision.
+    pub commit: git::Oid,
+    /// Revision id, ie. the hash of the document.
+    pub revision: String,
+    /// Commit time.
+    pub time: git::Time,
+    /// Document payload.
+    pub payload: Value,
+    /// Delegations of the document.
+    pub delegates: Vec<Delegate>,
+    /// Keys that signed the revision.
+    pub signers: Vec<PeerId>,
+    /// Signature status of the revision.
+    pub status: Status,
+}
+
+impl Revision {
+    fn project(commit: &git::Commit, project: Project) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            commit: commit.id(),
+            revision: project.revision.to_string(),
+            time: commit.time(),
+            payload: serde_json::to_value(project.payload())?,
+            delegates: project
+                .delegations()
+                .iter()
+                .map(|d| match d {
+                    Either::Left(pk) => Delegate::Direct {
+                        id: PeerId::from(*pk),
+                    },
+                    Either::Right(person) => Delegate::Indirect {
+                        urn: person.urn(),
+                        ids: person
+                            .delegations()
+                            .iter()
+                            .map(|pk| PeerId::from(*pk))
+                            .collect(),
+                    },
+                })
+                .collect(),
+            signers: project
+                .signatures
+                .keys()
+                .map(|pk| PeerId::from(*pk))
+                .collect(),
+            status: Status::of(&project),
+        })
+    }
+
+    fn person(commit: &git::Commit, person: Person) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            commit: commit.id(),
+            revision: person.revision.to_string(),
+            time: commit.time(),
+            payload: serde_json::to_value(person.payload())?,
+            delegates: person
+                .delegations()
+                .iter()
+                .map(|pk| Delegate::Direct {
+                    id: PeerId::from(*pk),
+                })
+                .collect(),
+            signers: person
+                .signatures
+                .keys()
+                .map(|pk| PeerId::from(*pk))
+                .collect(),
+            status: Status::of_person(&person),
+        })
+    }
+
+    /// Payload fields that are set, by name. Fields of the identity's own namespace are named as is,
+    /// eg. `name`, and fields of extensions are prefixed with the extension, eg.
+    /// `ens.name`.
+    pub fn fields(&self) -> BTreeMap<String, Value> {
+        let mut fields = BTreeMap::new();
+
+        if let Value::Object(namespaces) = &self.payload {
+            for (namespace, value) in namespaces {
+                let prefix = if namespace.as_str() == payload::Project::namespace().as_str()
+                    || namespace.as_str() == payload::Person::namespace().as_str()
+                {
+                    String::new()
+                } else if namespace.as_str() == Ens::namespace().as_str() {
+                    String::from("ens.")
+                } else {
+                    format!("{}#", namespace)
+                };
+
+                match value {
+                    Value::Object(values) => {
+                        for (key, value) in values.iter().filter(|(_, v)| !v.is_null()) {
+                            fields.insert(format!("{}{}", prefix, key), value.clone());
+                        }
+                    }
+                    other => {
+                        fields.insert(prefix.trim_end_matches('.').to_owned(), other.clone());
+                    }
+                }
+            }
+        }
+        fields
+    }
+}
+
+/// A change between two revisions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A payload field was set, changed or unset.
+    Field {
+        name: String,
+        old: Option<Value>,
+        new: Option<Value>,
+    },
+    /// A delegate was added.
+    DelegateAdded(Delegate),
+    /// A delegate was removed.
+    DelegateRemoved(Delegate),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Field {
+                name,
+                old: None,
+                new: Some(new),
+            } => write!(f, "{}: set to {}", name, new),
+            Self::Field {
+                name,
+                old: Some(old),
+                new: Some(new),
+            } => write!(f, "{}: {} → {}", name, old, new),
+            Self::Field { name, .. } => write!(f, "{}: unset", name),
+            Self::DelegateAdded(delegate) => write!(f, "delegate added: {}", delegate),
+            Self::DelegateRemoved(delegate) => write!(f, "delegate removed: {}", delegate),
+        }
+    }
+}
+
+/// Get the revisions of an identity, latest first.
+pub fn history(storage: &ReadOnly, urn: &Urn) -> anyhow::Result<Vec<Revision>> {
+    let identity =
+        any::get(storage, urn)?.ok_or_else(|| anyhow::anyhow!("identity {} not found", urn))?;
+    let repo = git::Repository::open_bare(storage.path())?;
+    let tip = repo
+        .find_reference(&format!("refs/namespaces/{}/refs/rad/id", urn.encode_id()))?
+        .peel_to_commit()?;
+    let mut revisions = Vec::new();
+    let mut commit = Some(tip);
+
+    while let Some(c) = commit {
+        let revision = match &identity {
+            SomeIdentity::Project(_) => {
+                let project = Identities::<Project>::from(&repo).get(c.id())?;
+                Revision::project(&c, project)?
+            }
+            SomeIdentity::Person(_) => {
+                let person = Identities::<Person>::from(&repo).get(c.id())?;
+                Revision::person(&c, person)?
+            }
+            _ => anyhow::bail!("identity type of {} is not supported", urn),
+        };
+        revisions.push(revision);
+        commit = c.parent(0).ok();
+    }
+    Ok(revisions)
+}
+
+/// Get the changes between two revisions.
+pub fn diff(old: &Revision, new: &Revision) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let (old_fields, new_fields) = (old.fields(), new.fields());
+
+    for (name, value) in &new_fields {
+        match old_fields.get(name) {
+            Some(old) if old == value => {}
+            old => changes.push(Change::Field {
+                name: name.clone(),
+                old: old.cloned(),
+                new: Some(value.clone()),
+            }),
+        }
+    }
+    for (name, value) in &old_fields {
+        if !new_fields.contains_key(name) {
+            changes.push(Change::Field {
+                name: name.clone(),
+                old: Some(value.clone()),
+                new: None,
+            });
+        }
+    }
+    for delegate in &new.delegates {
+        if !old.delegates.contains(delegate) {
+            changes.push(Change::DelegateAdded(delegate.clone()));
+        }
+    }
+    for delegate in &old.delegates {
+        if !new.delegates.contains(delegate) {
+            changes.push(Change::DelegateRemoved(delegate.clone()));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use librad::crypto::SecretKey;
+    use serde_json::json;
+
+    fn revision(payload: Value, delegates: Vec<Delegate>) -> Revision {
+        Revision {
+            commit: git::Oid::zero(),
+            revision: String::new(),
+            time: git::Time::new(0, 0),
+            payload,
+            delegates,
+            signers: vec![],
+            status: Status {
+                signed: 1,
+                quorum: 1,
+                missing: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let project = payload::Project::namespace().to_string();
+        let ens = Ens::namespace().to_string();
+        let alice = Delegate::Direct {
+            id: PeerId::from(SecretKey::new()),
+        };
+        let bob = Delegate::Direct {
+            id: PeerId::from(SecretKey::new()),
+        };
+        let old = revision(
+            json!({ project.clone(): { "name": "nakamoto", "description": "Bitcoin" } }),
+            vec![alice.clone()],
+        );
+        let new = revision(
+            json!({
+                project: { "name": "nakamoto", "default_branch": "master" },
+                ens: { "name": "nakamoto.radicle.eth" },
+            }),
+            vec![alice, bob.clone()],
+        );
+        let changes = diff(&old, &new);
+
+        assert_eq!(changes.len(), 4);
+        assert!(changes.contains(&Change::Field {
+            name: "default_branch".to_owned(),
+            old: None,
+            new: Some(json!("master")),
+        }));
+        assert!(changes.contains(&Change::Field {
+            name: "ens.name".to_owned(),
+            old: None,
+            new: Some(json!("nakamoto.radicle.eth")),
+        }));
+        assert!(changes.contains(&Change::Field {
+            name: "description".to_owned(),
+            old: Some(json!("Bitcoin")),
+            new: None,
+        }));
+        assert!(changes.contains(&Change::DelegateAdded(bob)));
+        assert!(diff(&new, &new).is_empty());
+    }
+}
@@ -1,4 +1,10 @@
 //! SSH and key-related functions.
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+use std::str::FromStr;
+
 use anyhow::{Context as _, Error, Result};
 
 use zeroize::Zeroizing;
@@ -12,7 +18,7 @@ use librad::profile::Profile;
 use librad::{PeerId, PublicKey};
 
 use lnk_clib::keys;
-use lnk_clib::keys::ssh::SshAuthSock;
+pub use lnk_clib::keys::ssh::SshAuthSock;
 
 pub use lnk_clib::keys::LIBRAD_KEY_FILE as KEY_FILE;
 
@@ -22,6 +28,9 @@ use crate::signer::{ToSigner, ZeroizingSecretKey};
 /// ssh-agent isn't present.
 pub const RAD_PASSPHRASE: &str = "RAD_PASSPHRASE";
 
+/// File listing the keys revoked by key rotations, in the profile's keys directory.
+pub const REVOKED_FILE: &str = "revoked";
+
 /// Get the radicle signer and storage.
 pub fn storage(profile: &Profile, signer: impl ToSigner) -> Result<Storage, Error> {
     let signer = match signer.to_signer(profile) {
@@ -115,6 +124,68 @@ pub fn to_ssh_fingerprint(peer_id: &PeerId) -> Result<String, std::io::Error> {
     Ok(format!("SHA256:{}", encoded.trim_end_matches('=')))
 }
 
+/// Path of the file listing the keys revoked in the given profile.
+pub fn revoked_path(profile: &Profile) -> PathBuf {
+    profile.paths().keys_dir().join(REVOKED_FILE)
+}
+
+/// Record the given key as revoked in the given profile.
+pub fn revoke(profile: &Profile, peer: &PeerId) -> Result<(), io::Error> {
+    if revoked(profile)?.contains(peer) {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(revoked_path(profile))?;
+
+    writeln!(file, "{}", peer)
+}
+
+/// Get the keys revoked in the given profile. If there is no revocation file, no keys
+/// are revoked.
+pub fn revoked(profile: &Profile) -> Result<HashSet<PeerId>, io::Error> {
+    let contents = match fs::read_to_string(revoked_path(profile)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => return Err(err),
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            PeerId::from_str(line.trim())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid revoked key"))
+        })
+        .collect()
+}
+
+/// Get the keys revoked in any of the local profiles. Since the revoked key's own
+/// profile may have been removed, all profiles are checked. Profiles whose revocations
+/// can't be read are skipped.
+pub fn revoked_locally() -> HashSet<PeerId> {
+    crate::profile::list()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|profile| revoked(profile).ok())
+        .flatten()
+        .collect()
+}
+
+/// Fail if the key of the given profile was revoked, since it shouldn't sign anymore.
+pub fn ensure_not_revoked(profile: &Profile) -> Result<(), Error> {
+    let peer = *crate::profile::read_only(profile)?.peer_id();
+
+    if revoked(profile)?.contains(&peer) {
+        return Err(crate::args::Error::WithHint {
+            err: anyhow::anyhow!("the key {} of this profile was revoked", peer),
+            hint: "The key was replaced with `rad auth --rotate`. To use the new profile, run `rad profile use`.",
+        }
+        .into());
+    }
+    Ok(())
+}
+
 /// Get a profile's secret key by providing a passphrase.
 pub fn load_secret_key(
     profile: &Profile,
@@ -144,3 +215,25 @@ pub fn pwhash(secret: SecUtf8) -> crypto::Pwhash<SecUtf8> {
 pub fn pwhash(secret: SecUtf8) -> crypto::Pwhash<SecUtf8> {
     crypto::Pwhash::new(secret, *crypto::KDF_PARAMS_TEST)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_revoke() {
+        let (storage, profile, _, _) = test::setup::profile();
+        let peer = *storage.peer_id();
+
+        assert!(revoked(&profile).unwrap().is_empty());
+        ensure_not_revoked(&profile).unwrap();
+
+        revoke(&profile, &peer).unwrap();
+        revoke(&profile, &peer).unwrap();
+
+        assert_eq!(revoked(&profile).unwrap(), std::iter::once(peer).collect());
+        assert!(revoked_locally().contains(&peer));
+        assert!(ensure_not_revoked(&profile).is_err());
+    }
+}
@@ -1,8 +1,11 @@
 //! Common radicle utilities.
 #![allow(clippy::or_fun_call)]
 pub mod args;
+pub mod bundle;
 pub mod cobs;
 pub mod config;
+pub mod delegation;
+pub mod device;
 pub mod git;
 pub mod identity;
 pub mod keys;
@@ -11,6 +14,8 @@ pub mod patch;
 pub mod person;
 pub mod profile;
 pub mod project;
+pub mod refs;
+pub mod rotation;
 pub mod seed;
 pub mod signer;
 pub mod sync;
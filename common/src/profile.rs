@@ -1,4 +1,6 @@
 //! User profile related functions.
+pub mod archive;
+
 use std::fs;
 use std::{env, fmt, path};
 
@@ -19,6 +21,9 @@ use crate::keys;
 
 /// Environment var that sets the radicle home directory.
 pub const RAD_HOME: &str = "RAD_HOME";
+/// Environment var that selects the profile to use instead of the active profile, by
+/// profile id or peer id.
+pub const RAD_PROFILE: &str = "RAD_PROFILE";
 
 /// Create a new profile.
 pub fn create<C: Crypto>(home: impl Into<LnkHome>, crypto: C) -> Result<(Profile, PeerId)>
@@ -50,8 +55,25 @@ pub fn home() -> LnkHome {
     home.unwrap_or_default()
 }
 
-/// Get the default profile. Fails if there is no profile.
+/// Get the default profile, ie. the profile selected with [`RAD_PROFILE`], or else the
+/// active profile. Fails if there is no profile.
 pub fn default() -> Result<Profile, Error> {
+    select(&home(), env::var(RAD_PROFILE).ok().as_deref())
+}
+
+/// Get the profile with the given profile id or peer id, if any, or else the active
+/// profile of the given home.
+fn select(home: &LnkHome, id: Option<&str>) -> Result<Profile, Error> {
+    if let Some(id) = id {
+        return find_in(home, id).map_err(|err| {
+            args::Error::WithHint {
+                err,
+                hint: "To list your profiles, run `rad profile ls`.",
+            }
+            .into()
+        });
+    }
+
     let error = args::Error::WithHint {
         err: anyhow!("Could not load radicle profile"),
         hint: "To setup your radicle profile, run `rad auth`.",
@@ -62,7 +84,7 @@ pub fn default() -> Result<Profile, Error> {
         hint: "To setup your radicle profile, run `rad auth --init`.",
     };
 
-    match Profile::active(&home()) {
+    match Profile::active(home) {
         Ok(Some(profile)) => Ok(profile),
         Ok(None) => Err(not_active_error.into()),
         Err(_) => Err(error.into()),
@@ -83,6 +105,26 @@ pub fn list() -> Result<Vec<Profile>, Error> {
     Profile::list(&home()).map_err(|e| e.into())
 }
 
+/// Get the active profile, if any. Unlike [`default`], this ignores [`RAD_PROFILE`].
+pub fn active() -> Result<Option<Profile>, Error> {
+    Profile::active(&home()).map_err(|e| e.into())
+}
+
+/// Find a profile by profile id or peer id.
+pub fn find(id: &str) -> Result<Profile, Error> {
+    find_in(&home(), id)
+}
+
+fn find_in(home: &LnkHome, id: &str) -> Result<Profile, Error> {
+    Profile::list(home)?
+        .into_iter()
+        .find(|p| {
+            p.id().to_string() == id
+                || read_only(p).map_or(false, |s| s.peer_id().to_string() == id)
+        })
+        .ok_or_else(|| anyhow!("profile '{}' not found", id))
+}
+
 /// Get the count of all profiles.
 pub fn count() -> Result<usize, Error> {
     let profiles = list()?;
@@ -110,12 +152,19 @@ pub fn get(id: &ProfileId) -> Result<Profile, Error> {
     }
 }
 
-/// Remove all profile directories.
+/// Remove all profile directories. Directories that don't exist, eg. of a profile that
+/// wasn't fully created, are skipped.
 pub fn remove(profile: &Profile) -> Result<(), Error> {
-    fs::remove_dir_all(profile.paths().keys_dir().parent().unwrap())?;
-    fs::remove_dir_all(profile.paths().git_dir().parent().unwrap())?;
-    fs::remove_dir_all(profile.paths().cob_cache_dir().parent().unwrap())?;
-
+    for dir in [
+        profile.paths().keys_dir(),
+        profile.paths().git_dir(),
+        profile.paths().cob_cache_dir(),
+    ] {
+        match fs::remove_dir_all(dir.parent().unwrap()) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+    }
     Ok(())
 }
 
@@ -125,3 +174,42 @@ pub fn read_only(profile: &Profile) -> Result<ReadOnly, Error> {
 
     Ok(storage)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+
+    #[test]
+    fn test_select() {
+        // Use a home of our own, since other tests change the active profile.
+        let root = env::temp_dir().join(format!("rad-test-profile-{}", uuid::Uuid::new_v4()));
+        let home = LnkHome::Root(root.clone());
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+
+        assert!(select(&home, None).is_err());
+
+        let (first, peer) = create(home.clone(), pass.clone()).unwrap();
+        let (second, _) = create(home.clone(), pass).unwrap();
+        let active = |home: &LnkHome| Profile::active(home).unwrap().unwrap().id().to_string();
+
+        // Without a selection, the active profile is used, ie. the last one created.
+        assert_eq!(select(&home, None).unwrap().id(), second.id());
+        assert_eq!(active(&home), second.id().to_string());
+
+        // A profile may be selected by profile id or peer id, without changing the
+        // active profile.
+        let selected = select(&home, Some(&first.id().to_string())).unwrap();
+        assert_eq!(selected.id(), first.id());
+        let selected = select(&home, Some(&peer.to_string())).unwrap();
+        assert_eq!(selected.id(), first.id());
+        assert_eq!(active(&home), second.id().to_string());
+
+        assert!(select(&home, Some("unknown")).is_err());
+        assert!(find_in(&home, "unknown").is_err());
+
+        fs::remove_dir_all(root).ok();
+    }
+}
@@ -0,0 +1,406 @@
+//! Encrypted profile archives, for moving a profile to another device.
+//!
+//! An archive holds the profile's keystore, its configuration, and its person identity
+//! as a bundle. Each file is stored with its SHA-256 digest, which is checked when the
+//! archive is opened. The archive is sealed with a passphrase the same way as the
+//! keystore, so it can't be read or modified without the passphrase.
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use librad::crypto::BoxedSigner;
+use librad::git::identities::{person as identity, Person};
+use librad::git::storage::Storage;
+use librad::git::Urn;
+use librad::keystore::crypto::Crypto;
+use librad::profile::{LnkHome, Profile};
+use librad::PeerId;
+
+use crate::{bundle, config, git, keys, person};
+
+/// Archive format version.
+pub const VERSION: u32 = 1;
+
+/// Name of the keystore file in an archive.
+const KEY_FILE: &str = "keys/librad.key";
+/// Name of the configuration file in an archive.
+const CONFIG_FILE: &str = "config.toml";
+/// Name of the person identity bundle in an archive.
+const PERSON_BUNDLE: &str = "person.bundle";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid archive: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("failed to seal archive: {0}")]
+    Seal(String),
+    #[error("archive could not be opened, the passphrase is wrong or the archive is corrupted")]
+    Unseal,
+    #[error("unsupported archive version {0}")]
+    Version(u32),
+    #[error("file '{0}' of the archive is corrupted")]
+    Corrupted(String),
+    #[error("file '{0}' is missing from the archive")]
+    Missing(String),
+    #[error("a profile with key {0} already exists")]
+    Exists(PeerId),
+    #[error("archive identity was bundled by {0}, not by the archived key")]
+    Peer(PeerId),
+    #[error("person {0} not found in archive")]
+    NotFound(Urn),
+    #[error(transparent)]
+    Bundle(#[from] bundle::Error),
+    #[error(transparent)]
+    Identities(#[from] librad::git::identities::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// A file of an archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    /// SHA-256 digest of the content, hex-encoded.
+    sha256: String,
+    /// Content, base64-encoded.
+    content: String,
+}
+
+impl Entry {
+    fn new(content: &[u8]) -> Self {
+        Self {
+            sha256: digest(content),
+            content: base64::encode(content),
+        }
+    }
+
+    fn content(&self, name: &str) -> Result<Vec<u8>, Error> {
+        let content =
+            base64::decode(&self.content).map_err(|_| Error::Corrupted(name.to_owned()))?;
+
+        if digest(&content) != self.sha256 {
+            return Err(Error::Corrupted(name.to_owned()));
+        }
+        Ok(content)
+    }
+}
+
+/// Sealed archive, as written to disk.
+#[derive(Serialize, Deserialize)]
+struct Sealed<B> {
+    version: u32,
+    archive: B,
+}
+
+/// Profile archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    /// Key of the archived profile.
+    peer: PeerId,
+    /// Person identity of the archived profile, if any.
+    #[serde(default, deserialize_with = "deserialize_urn")]
+    urn: Option<Urn>,
+    /// Archived files, by name.
+    files: BTreeMap<String, Entry>,
+}
+
+impl Archive {
+    /// Key of the archived profile.
+    pub fn peer(&self) -> &PeerId {
+        &self.peer
+    }
+
+    /// Person identity of the archived profile, if any.
+    pub fn urn(&self) -> Option<&Urn> {
+        self.urn.as_ref()
+    }
+
+    /// Get the content of a file, checking its integrity.
+    fn file(&self, name: &str) -> Result<Vec<u8>, Error> {
+        self.files
+            .get(name)
+            .ok_or_else(|| Error::Missing(name.to_owned()))?
+            .content(name)
+    }
+
+    /// Restore the person identity of the archive into the storage of the imported
+    /// profile, and set it as the local identity. Returns `None` if the archived profile
+    /// has no identity.
+    pub fn restore(&self, storage: &Storage) -> Result<Option<Person>, Error> {
+        let urn = match &self.urn {
+            Some(urn) => urn,
+            None => return Ok(None),
+        };
+        let dir = tempdir(storage.peer_id())?;
+        let result = self.restore_person(storage, urn, &dir);
+        fs::remove_dir_all(&dir).ok();
+
+        result.map(Some)
+    }
+
+    fn restore_person(&self, storage: &Storage, urn: &Urn, dir: &Path) -> Result<Person, Error> {
+        let path = dir.join(PERSON_BUNDLE);
+        let manifest = bundle::Manifest::path(&path);
+
+        fs::write(&path, self.file(PERSON_BUNDLE)?)?;
+        fs::write(&manifest, self.file(&manifest_name())?)?;
+
+        let manifest = bundle::Manifest::load(&path)?;
+        if manifest.peer() != storage.peer_id() {
+            return Err(Error::Peer(*manifest.peer()));
+        }
+
+        // The bundled refs are our own, so they are restored as is.
+        let namespace = format!("refs/namespaces/{}/", urn.encode_id());
+        let monorepo = storage.read_only().path();
+        let path = path.to_string_lossy();
+
+        git::git(monorepo, ["bundle", "verify", path.as_ref()])
+            .map_err(|e| bundle::Error::Mismatch(e.to_string()))?;
+        git::git(
+            monorepo,
+            [
+                "fetch",
+                "--quiet",
+                path.as_ref(),
+                &format!("+{}*:{}*", namespace, namespace),
+            ],
+        )?;
+
+        let person = identity::verify(storage, urn)?
+            .ok_or_else(|| Error::NotFound(urn.clone()))?
+            .into_inner();
+        person::set_local(storage, &person)?;
+
+        Ok(person)
+    }
+}
+
+/// Export a profile into a sealed archive at `path`. The profile's person identity is
+/// bundled with the given storage and signer.
+pub fn export<C: Crypto>(
+    profile: &Profile,
+    storage: &Storage,
+    signer: &BoxedSigner,
+    crypto: C,
+    path: &Path,
+) -> Result<Archive, Error>
+where
+    C::Error: fmt::Display,
+    C::SecretBox: Serialize,
+{
+    let mut files = BTreeMap::new();
+
+    let key = fs::read(profile.paths().keys_dir().join(keys::KEY_FILE))?;
+    files.insert(KEY_FILE.to_owned(), Entry::new(&key));
+
+    match fs::read(config::Config::path(profile)) {
+        Ok(cfg) => {
+            files.insert(CONFIG_FILE.to_owned(), Entry::new(&cfg));
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    let urn = match person::local(storage) {
+        Ok(whoami) => {
+            let urn = whoami.urn();
+            let dir = tempdir(storage.peer_id())?;
+            let bundled = bundle_person(storage, signer, &urn, &dir);
+            fs::remove_dir_all(&dir).ok();
+
+            for (name, content) in bundled? {
+                files.insert(name, Entry::new(&content));
+            }
+            Some(urn)
+        }
+        Err(_) => None,
+    };
+
+    let archive = Archive {
+        peer: *storage.peer_id(),
+        urn,
+        files,
+    };
+    let sealed = Sealed {
+        version: VERSION,
+        archive: crypto
+            .seal(serde_json::to_vec(&archive)?)
+            .map_err(|e| Error::Seal(e.to_string()))?,
+    };
+    fs::write(path, serde_json::to_vec(&sealed)?)?;
+
+    Ok(archive)
+}
+
+/// Import the sealed archive at `path` as a new profile of the given home. The profile's
+/// identity is restored with [`Archive::restore`], once its storage can be opened.
+pub fn import<C: Crypto>(
+    home: &LnkHome,
+    path: &Path,
+    crypto: C,
+) -> Result<(Profile, Archive), Error>
+where
+    C::SecretBox: DeserializeOwned,
+{
+    let archive = open(path, crypto)?;
+
+    for profile in Profile::list(home).map_err(anyhow::Error::from)? {
+        if let Ok(storage) = super::read_only(&profile) {
+            if storage.peer_id() == archive.peer() {
+                return Err(Error::Exists(archive.peer));
+            }
+        }
+    }
+
+    let profile = Profile::new(home).map_err(anyhow::Error::from)?;
+
+    // Don't leave a half-imported profile behind.
+    if let Err(err) = write(&profile, &archive) {
+        super::remove(&profile).ok();
+        return Err(err);
+    }
+    Ok((profile, archive))
+}
+
+/// Write the key and configuration of an archive to a new profile.
+fn write(profile: &Profile, archive: &Archive) -> Result<(), Error> {
+    let keys_dir = profile.paths().keys_dir();
+
+    fs::create_dir_all(keys_dir)?;
+    fs::write(keys_dir.join(keys::KEY_FILE), archive.file(KEY_FILE)?)?;
+
+    match archive.file(CONFIG_FILE) {
+        Ok(cfg) => fs::write(config::Config::path(profile), cfg)?,
+        Err(Error::Missing(_)) => {
+            config::Config::init(profile)?;
+        }
+        Err(err) => return Err(err),
+    }
+    Ok(())
+}
+
+/// Open a sealed archive, checking the integrity of its files.
+pub fn open<C: Crypto>(path: &Path, crypto: C) -> Result<Archive, Error>
+where
+    C::SecretBox: DeserializeOwned,
+{
+    let sealed: Sealed<serde_json::Value> = serde_json::from_slice(&fs::read(path)?)?;
+    if sealed.version != VERSION {
+        return Err(Error::Version(sealed.version));
+    }
+    let secret = serde_json::from_value(sealed.archive)?;
+    let unsealed = crypto.unseal(secret).map_err(|_| Error::Unseal)?;
+    let archive: Archive = serde_json::from_slice(unsealed.unsecure())?;
+
+    for (name, entry) in &archive.files {
+        entry.content(name)?;
+    }
+    archive.file(KEY_FILE)?;
+
+    Ok(archive)
+}
+
+fn bundle_person(
+    storage: &Storage,
+    signer: &BoxedSigner,
+    urn: &Urn,
+    dir: &Path,
+) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let path = dir.join(PERSON_BUNDLE);
+    bundle::export(storage.read_only(), signer, urn, &path)?;
+
+    Ok(vec![
+        (PERSON_BUNDLE.to_owned(), fs::read(&path)?),
+        (manifest_name(), fs::read(bundle::Manifest::path(&path))?),
+    ])
+}
+
+/// Name of the person bundle manifest in an archive.
+fn manifest_name() -> String {
+    format!("{}.{}", PERSON_BUNDLE, bundle::MANIFEST_EXTENSION)
+}
+
+fn tempdir(peer: &PeerId) -> Result<PathBuf, Error> {
+    let dir = env::temp_dir().join(format!("rad-archive-{}", peer));
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir)
+}
+
+fn deserialize_urn<'de, D>(deserializer: D) -> Result<Option<Urn>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s: Option<String> = Deserialize::deserialize(deserializer)?;
+    s.map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn digest(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+
+    #[test]
+    fn test_export_import() {
+        let (storage, profile, whoami, _project) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass.clone()).unwrap();
+        let path = profile.paths().git_dir().join("profile.rad");
+
+        let exported = export(&profile, &storage, &signer, pass.clone(), &path).unwrap();
+        assert_eq!(exported.urn(), Some(&whoami.urn()));
+
+        // The wrong passphrase can't open the archive.
+        let wrong = Pwhash::new(SecUtf8::from("wrong"), *KDF_PARAMS_TEST);
+        assert!(matches!(open(&path, wrong), Err(Error::Unseal)));
+
+        // A profile can't be imported twice.
+        assert!(matches!(
+            import(&crate::profile::home(), &path, pass.clone()),
+            Err(Error::Exists(_))
+        ));
+
+        let home = LnkHome::Root(env::temp_dir().join(format!("rad-import-{}", whoami.urn())));
+        let (imported, archive) = import(&home, &path, pass.clone()).unwrap();
+        let signer = test::signer(&imported, pass).unwrap();
+        let storage = keys::storage(&imported, signer).unwrap();
+
+        assert_eq!(archive.peer(), storage.peer_id());
+        assert!(Profile::list(&home)
+            .unwrap()
+            .iter()
+            .any(|p| p.id() == imported.id()));
+
+        let person = archive.restore(&storage).unwrap().unwrap();
+        assert_eq!(person.urn(), whoami.urn());
+        assert_eq!(person::local(&storage).unwrap().urn(), whoami.urn());
+    }
+
+    #[test]
+    fn test_entry_integrity() {
+        let mut entry = Entry::new(b"seeds");
+        assert_eq!(entry.content("config.toml").unwrap(), b"seeds");
+
+        entry.content = base64::encode(b"weeds");
+        assert!(matches!(
+            entry.content("config.toml"),
+            Err(Error::Corrupted(_))
+        ));
+    }
+}
@@ -115,7 +115,7 @@ impl PeerInfo {
 }
 
 /// Project delegate.
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase", tag = "type")]
 pub enum Delegate {
     /// Direct delegation, ie. public key.
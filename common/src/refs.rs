@@ -0,0 +1,165 @@
+//! Verification of the refs of remote peers against their signed refs, and rollback of
+//! ref updates that can't be verified.
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use librad::git::refs::Refs;
+use librad::git::storage::ReadOnly;
+use librad::git::Urn;
+use librad::PeerId;
+
+use crate::git;
+
+/// Categories of refs covered by a peer's signed refs, eg. `heads` for `refs/heads/*`.
+/// Other refs, such as identities and collaborative objects, are signed by other means.
+pub const SIGNED_CATEGORIES: &[&str] = &["heads", "tags", "notes"];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("peer {0} has no signed refs")]
+    NotFound(PeerId),
+    #[error("signed refs of peer {peer} are invalid: {err}")]
+    Invalid { peer: PeerId, err: String },
+    #[error("ref '{name}' of peer {peer} is not signed")]
+    Unsigned { peer: PeerId, name: String },
+    #[error("ref '{name}' of peer {peer} doesn't match its signed value")]
+    Mismatch { peer: PeerId, name: String },
+    #[error("git: {0}")]
+    Git(#[from] git2::Error),
+}
+
+/// Whether the given ref, relative to a peer, eg. `heads/master`, is covered by the
+/// peer's signed refs.
+pub fn is_signed(name: &str) -> bool {
+    name.split_once('/')
+        .map_or(false, |(category, _)| SIGNED_CATEGORIES.contains(&category))
+}
+
+/// Check the given refs of a remote peer in storage, relative to the peer, against the
+/// peer's signed refs. Refs outside of the signed categories aren't checked, but the
+/// peer must have valid signed refs.
+pub fn verify<'a>(
+    storage: &ReadOnly,
+    urn: &Urn,
+    peer: PeerId,
+    names: impl IntoIterator<Item = &'a str>,
+) -> Result<(), Error> {
+    // Nb. Loading the signed refs checks their signature.
+    let signed = match Refs::load(storage, urn, Some(peer)) {
+        Ok(Some(refs)) => refs
+            .iter_categorised()
+            .map(|((name, oid), category)| (format!("{}/{}", category, name), oid.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        Ok(None) => return Err(Error::NotFound(peer)),
+        Err(err) => {
+            return Err(Error::Invalid {
+                peer,
+                err: err.to_string(),
+            })
+        }
+    };
+    let repo = git::Repository::open_bare(storage.path())?;
+    let prefix = format!("refs/namespaces/{}/refs/remotes/{}", urn.encode_id(), peer);
+
+    for name in names.into_iter().filter(|name| is_signed(name)) {
+        let actual = repo
+            .refname_to_id(&format!("{}/{}", prefix, name))?
+            .to_string();
+
+        match signed.get(name) {
+            Some(oid) if oid == &actual => {}
+            Some(_) => {
+                return Err(Error::Mismatch {
+                    peer,
+                    name: name.to_owned(),
+                })
+            }
+            None => {
+                return Err(Error::Unsigned {
+                    peer,
+                    name: name.to_owned(),
+                })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Targets of refs before they are updated, to roll back to if the update can't be
+/// verified.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    refs: BTreeMap<String, Option<git::Oid>>,
+}
+
+impl Snapshot {
+    /// Record the current targets of the given refs. Refs that don't exist yet are
+    /// recorded as such.
+    pub fn take<'a>(repo: &git::Repository, names: impl IntoIterator<Item = &'a str>) -> Self {
+        let refs = names
+            .into_iter()
+            .map(|name| (name.to_owned(), repo.refname_to_id(name).ok()))
+            .collect();
+
+        Self { refs }
+    }
+
+    /// Restore the refs to their recorded targets. Refs that didn't exist are removed.
+    pub fn restore(&self, repo: &git::Repository) -> Result<(), git2::Error> {
+        for (name, oid) in &self.refs {
+            match oid {
+                Some(oid) => {
+                    repo.reference(name, *oid, true, "rollback")?;
+                }
+                None => {
+                    if let Ok(mut r) = repo.find_reference(name) {
+                        r.delete()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_restore() {
+        let tmp = std::env::temp_dir().join(format!("rad-test-refs-{}", uuid::Uuid::new_v4()));
+        let repo = git::Repository::init_bare(&tmp).unwrap();
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let first = repo.commit(None, &sig, &sig, "First", &tree, &[]).unwrap();
+        let second = repo.commit(None, &sig, &sig, "Second", &tree, &[]).unwrap();
+
+        repo.reference("refs/heads/master", first, false, "")
+            .unwrap();
+
+        let snapshot = Snapshot::take(&repo, ["refs/heads/master", "refs/heads/new"]);
+
+        repo.reference("refs/heads/master", second, true, "")
+            .unwrap();
+        repo.reference("refs/heads/new", second, false, "").unwrap();
+        snapshot.restore(&repo).unwrap();
+
+        assert_eq!(repo.refname_to_id("refs/heads/master").unwrap(), first);
+        assert!(repo.find_reference("refs/heads/new").is_err());
+
+        std::fs::remove_dir_all(tmp).ok();
+    }
+
+    #[test]
+    fn test_is_signed() {
+        assert!(is_signed("heads/master"));
+        assert!(is_signed("tags/v1.0"));
+        assert!(!is_signed("cobs/xyz.radicle.issue/1"));
+        assert!(!is_signed("rad/id"));
+        assert!(!is_signed("heads"));
+    }
+}
@@ -0,0 +1,288 @@
+//! Profile key rotation.
+//!
+//! A profile's monorepo is owned by the profile key, so the key can't be changed in
+//! place. Instead, a profile is created with a new key, and the person identity and
+//! projects of the old profile are migrated to it:
+//!
+//! 1. The old key replaces itself with the new key in the person's delegations, and the
+//!    person is imported into the new monorepo, where the new key signs it.
+//! 2. Each project the old key is a delegate of, directly or through the person, has its
+//!    delegations updated the same way: direct delegations to the old key are replaced,
+//!    and delegations to the person are refreshed to its new revision. Other projects,
+//!    eg. tracked projects, are left to their delegates, and aren't migrated.
+//! 3. The refs of the old key become the new key's own refs, and are signed with it.
+//!
+//! Updates take effect once signed by a quorum of delegates; identities with other
+//! delegates may need their signatures before the old key is fully replaced.
+//!
+//! If the migration fails, the identity updates made in the old monorepo are rolled
+//! back, so that the old profile remains usable as it was.
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use either::Either;
+use thiserror::Error;
+
+use librad::crypto::BoxedSigner;
+use librad::git::identities::{self, project as identity, Person};
+use librad::git::refs::{self, Refs};
+use librad::git::storage::Storage;
+use librad::git::Urn;
+use librad::PeerId;
+
+use crate::delegation::{self, Status};
+use crate::{bundle, device, git, person, project};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("git: {0}")]
+    Git(#[from] git2::Error),
+    #[error("new key {0} is the same as the old key")]
+    SameKey(PeerId),
+    #[error(transparent)]
+    Bundle(#[from] bundle::Error),
+    #[error(transparent)]
+    Device(#[from] device::Error),
+    #[error(transparent)]
+    Identities(#[from] identities::Error),
+    #[error(transparent)]
+    Person(#[from] lnk_identities::person::Error),
+    #[error(transparent)]
+    Project(#[from] lnk_identities::project::Error),
+    #[error(transparent)]
+    Local(#[from] lnk_identities::local::Error),
+    #[error(transparent)]
+    Refs(#[from] refs::stored::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Outcome of a key rotation.
+#[derive(Debug)]
+pub struct Rotation {
+    /// Rotated key.
+    pub old: PeerId,
+    /// Key replacing the rotated key.
+    pub new: PeerId,
+    /// Person identity, signed by the new key.
+    pub person: Person,
+    /// Projects migrated to the new key.
+    pub projects: Vec<Urn>,
+    /// Migrated projects whose delegation update awaits the signatures of other
+    /// delegates.
+    pub pending: Vec<Urn>,
+    /// Projects that weren't migrated, since the old key isn't one of their delegates.
+    pub skipped: Vec<Urn>,
+}
+
+/// Rotate the key of the `old` storage, signed for by `signer`, to the key of the `new`
+/// storage. The person identity and projects of the old storage are migrated to the new
+/// one. On failure, the refs of the old storage are restored.
+pub fn rotate(old: &Storage, signer: &BoxedSigner, new: &Storage) -> Result<Rotation, Error> {
+    let (old_peer, new_peer) = (*old.peer_id(), *new.peer_id());
+    if old_peer == new_peer {
+        return Err(Error::SameKey(new_peer));
+    }
+
+    let dir = env::temp_dir().join(format!("rad-rotate-{}", new_peer));
+    fs::create_dir_all(&dir)?;
+
+    let repo = git::Repository::open_bare(old.read_only().path())?;
+    let names = repo
+        .references_glob("refs/namespaces/*")?
+        .filter_map(|r| r.ok()?.name().map(ToOwned::to_owned))
+        .collect::<Vec<_>>();
+    let snapshot = crate::refs::Snapshot::take(&repo, names.iter().map(String::as_str));
+
+    let result = migrate(old, signer, new, &dir);
+    fs::remove_dir_all(&dir).ok();
+
+    if result.is_err() {
+        snapshot.restore(&repo)?;
+    }
+    result
+}
+
+fn migrate(
+    old: &Storage,
+    signer: &BoxedSigner,
+    new: &Storage,
+    dir: &Path,
+) -> Result<Rotation, Error> {
+    let (old_peer, new_peer) = (*old.peer_id(), *new.peer_id());
+
+    // Hand the person over to the new key.
+    let whoami = person::local(old)?;
+    let urn = whoami.urn();
+    let keys = whoami
+        .delegations()
+        .iter()
+        .copied()
+        .filter(|pk| pk != old_peer.as_public_key())
+        .chain(Some(*new_peer.as_public_key()))
+        .collect::<Vec<_>>();
+
+    lnk_identities::person::update(
+        old,
+        &urn,
+        Some(urn.clone()),
+        None,
+        vec![],
+        Some(keys.into_iter()),
+    )?;
+
+    let path = dir.join("person.bundle");
+    bundle::export(old.read_only(), signer, &urn, &path)?;
+    bundle::import(new.read_only(), &path)?;
+
+    let person = device::accept(new, &urn, old_peer)?.person;
+    let whoami = person::local(new)?;
+
+    // Hand each project over to the new key.
+    let mut projects = Vec::new();
+    let mut pending = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (urn, _, _) in project::list(old.read_only())? {
+        let project = identity::get(old, &urn)?
+            .ok_or_else(|| anyhow::anyhow!("project {} not found", urn))?;
+        let mut delegations = delegation::delegations(&project);
+        let direct = delegations.remove(&Either::Left(*old_peer.as_public_key()));
+
+        // We can only update the projects we are a delegate of.
+        if !direct && !delegations.contains(&Either::Right(person.urn())) {
+            skipped.push(urn);
+            continue;
+        }
+        if direct {
+            delegations.insert(Either::Left(*new_peer.as_public_key()));
+        }
+        lnk_identities::project::update(
+            old,
+            &urn,
+            Some(person.urn()),
+            None,
+            vec![],
+            Some(delegations),
+        )?;
+
+        let path = dir.join(format!("{}.bundle", urn.encode_id()));
+        bundle::export(old.read_only(), signer, &urn, &path)?;
+        bundle::import(new.read_only(), &path)?;
+
+        let project = identity::merge(new, &urn, old_peer)?;
+        if !Status::of(&project).is_quorum() {
+            pending.push(urn.clone());
+        }
+
+        promote(new, &urn, &old_peer)?;
+        whoami.link(new, &urn)?;
+        Refs::update(new, &urn)?;
+
+        projects.push(urn);
+    }
+
+    Ok(Rotation {
+        old: old_peer,
+        new: new_peer,
+        person,
+        projects,
+        pending,
+        skipped,
+    })
+}
+
+/// Make the refs of the given remote our own, except identity refs, which are managed
+/// through identity updates. Refs we already have are left untouched.
+fn promote(storage: &Storage, urn: &Urn, remote: &PeerId) -> Result<(), Error> {
+    let repo = git::Repository::open_bare(storage.read_only().path())?;
+    let namespace = format!("refs/namespaces/{}/", urn.encode_id());
+    let prefix = format!("{}refs/remotes/{}/", namespace, remote);
+    let mut promoted = Vec::new();
+
+    for r in repo.references_glob(&format!("{}*", prefix))? {
+        let r = r?;
+        if let (Some(name), Some(oid)) = (r.name(), r.target()) {
+            let name = &name[prefix.len()..];
+            if !name.starts_with("rad/") {
+                promoted.push((format!("{}refs/{}", namespace, name), oid));
+            }
+        }
+    }
+    for (name, oid) in promoted {
+        if repo.find_reference(&name).is_err() {
+            repo.reference(&name, oid, false, "rad: key rotation")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{bundle, keys, profile, test};
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+
+    #[test]
+    fn test_rotate() {
+        let (storage, profile, whoami, project) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass.clone()).unwrap();
+        let (rotated, peer) = profile::create(profile::home(), pass.clone()).unwrap();
+        let new = keys::storage(&rotated, test::signer(&rotated, pass).unwrap()).unwrap();
+
+        let rotation = rotate(&storage, &signer, &new).unwrap();
+
+        assert_eq!(rotation.old, *storage.peer_id());
+        assert_eq!(rotation.new, peer);
+        assert_eq!(rotation.person.urn(), whoami.urn());
+        assert_eq!(
+            rotation.person.delegations().iter().collect::<Vec<_>>(),
+            vec![peer.as_public_key()]
+        );
+        assert_eq!(rotation.projects, vec![project.urn()]);
+        assert!(rotation.pending.is_empty());
+        assert!(rotation.skipped.is_empty());
+        assert!(project::get(&new, &project.urn()).unwrap().is_some());
+
+        assert!(matches!(
+            rotate(&new, &signer, &new),
+            Err(Error::SameKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_rotate_skips_tracked() {
+        let (storage, profile, _, project) = test::setup::profile();
+        let (other, other_profile, _, tracked) = test::setup::profile();
+        let pass = Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST);
+        let signer = test::signer(&profile, pass.clone()).unwrap();
+        let other_signer = test::signer(&other_profile, pass.clone()).unwrap();
+
+        // A project of another peer, that we don't delegate.
+        let path = other_profile
+            .paths()
+            .git_dir()
+            .parent()
+            .unwrap()
+            .join("tracked.bundle");
+        bundle::export(other.read_only(), &other_signer, &tracked.urn(), &path).unwrap();
+        bundle::import(storage.read_only(), &path).unwrap();
+
+        let (rotated, _) = profile::create(profile::home(), pass.clone()).unwrap();
+        let new = keys::storage(&rotated, test::signer(&rotated, pass).unwrap()).unwrap();
+        let rotation = rotate(&storage, &signer, &new).unwrap();
+
+        assert_eq!(rotation.projects, vec![project.urn()]);
+        assert_eq!(rotation.skipped, vec![tracked.urn()]);
+        assert!(project::get(&new, &tracked.urn()).unwrap().is_none());
+
+        // The tracked project is left untouched.
+        let theirs = identity::get(&storage, &tracked.urn()).unwrap().unwrap();
+        assert_eq!(theirs.content_id, tracked.content_id);
+    }
+}
@@ -0,0 +1,272 @@
+//! HTTP client for the seed API.
+use std::time::Duration;
+
+use librad::crypto::peer::PeerId;
+use librad::git::Urn;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+use super::{Address, Protocol, DEFAULT_SEED_API_PORT};
+use crate::{git, project};
+
+/// Default timeout of seed API requests.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(9);
+
+/// Seed API error.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The requested resource doesn't exist on the seed.
+    #[error("{0} was not found on seed")]
+    NotFound(Url),
+    /// The seed responded with an error status.
+    #[error("seed responded with status {status} for {url}")]
+    Status { url: Url, status: u16 },
+    /// The seed couldn't be reached.
+    #[error("network error: {0}")]
+    Network(#[from] ureq::Transport),
+    /// The seed's response couldn't be decoded.
+    #[error("invalid response for {url}: {err}")]
+    Decode { url: Url, err: std::io::Error },
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+}
+
+impl Error {
+    /// Whether the resource was not found, as opposed to the seed failing to respond.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::NotFound(_))
+    }
+}
+
+/// Seed node information.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Peer {
+    pub id: PeerId,
+}
+
+/// Project hosted by a seed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    #[serde(deserialize_with = "project::deserialize_urn")]
+    pub urn: Urn,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub default_branch: String,
+    /// Head of the default branch, if known.
+    #[serde(default)]
+    pub head: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommitHeader {
+    pub summary: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Commit of a project hosted by a seed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Commit {
+    pub header: CommitHeader,
+}
+
+/// Kind of a tree entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Blob,
+    Tree,
+}
+
+/// Entry of a source tree.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: EntryKind,
+}
+
+/// Source tree of a project, at a given commit and path.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tree {
+    pub path: String,
+    pub entries: Vec<TreeEntry>,
+}
+
+/// Collaborative object summary, eg. an issue or patch.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Cob {
+    pub id: String,
+    pub title: String,
+    /// Other properties, which depend on the type of object.
+    #[serde(flatten)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Client of a seed's HTTP API.
+///
+/// Connections are re-used across requests.
+#[derive(Debug, Clone)]
+pub struct SeedClient {
+    agent: ureq::Agent,
+    url: Url,
+}
+
+impl SeedClient {
+    /// Create a client for the seed at the given address. The API is assumed to run
+    /// on the same host, with the scheme of the address. The address port is only used
+    /// if it is an API address.
+    pub fn new(addr: &Address) -> Self {
+        let (local, port) = match addr.protocol {
+            Protocol::Api { local } => (local, addr.port()),
+            Protocol::Git { local } => (local, DEFAULT_SEED_API_PORT),
+            Protocol::Link { .. } => (false, DEFAULT_SEED_API_PORT),
+        };
+        let api = Address {
+            protocol: Protocol::Api { local },
+            host: addr.host.clone(),
+            port: Some(port),
+            urn: None,
+        };
+
+        Self::from_url(api.url())
+    }
+
+    /// Create a client for the seed API at the given URL.
+    pub fn from_url(url: Url) -> Self {
+        Self {
+            agent: Self::agent(DEFAULT_TIMEOUT),
+            url,
+        }
+    }
+
+    /// Set the timeout of requests.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.agent = Self::agent(timeout);
+        self
+    }
+
+    /// Base URL of the seed API.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Get the seed's peer id.
+    pub fn peer(&self) -> Result<PeerId, Error> {
+        self.get::<Peer>("/v1/peer").map(|peer| peer.id)
+    }
+
+    /// Get the projects hosted by the seed.
+    pub fn projects(&self) -> Result<Vec<Project>, Error> {
+        self.get("/v1/projects")
+    }
+
+    /// Get a project hosted by the seed.
+    pub fn project(&self, urn: &Urn) -> Result<Project, Error> {
+        self.get(&format!("/v1/projects/{}", urn))
+    }
+
+    /// Get a project commit.
+    pub fn commit(&self, urn: &Urn, commit: &git::Oid) -> Result<Commit, Error> {
+        self.get(&format!("/v1/projects/{}/commits/{}", urn, commit))
+    }
+
+    /// Get a project's remotes.
+    pub fn remotes(&self, urn: &Urn) -> Result<Vec<project::PeerInfo>, Error> {
+        self.get(&format!("/v1/projects/{}/remotes", urn))
+    }
+
+    /// Get a project's source tree at the given commit and path.
+    pub fn tree(&self, urn: &Urn, commit: &git::Oid, path: &str) -> Result<Tree, Error> {
+        self.get(&format!(
+            "/v1/projects/{}/tree/{}/{}",
+            urn,
+            commit,
+            path.trim_start_matches('/')
+        ))
+    }
+
+    /// Get a project's issues.
+    pub fn issues(&self, urn: &Urn) -> Result<Vec<Cob>, Error> {
+        self.get(&format!("/v1/projects/{}/issues", urn))
+    }
+
+    /// Get a project's patches.
+    pub fn patches(&self, urn: &Urn) -> Result<Vec<Cob>, Error> {
+        self.get(&format!("/v1/projects/{}/patches", urn))
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let url = self.url.join(path)?;
+
+        match self.agent.get(url.as_str()).call() {
+            Ok(response) => response
+                .into_json()
+                .map_err(|err| Error::Decode { url, err }),
+            Err(ureq::Error::Status(404, _)) => Err(Error::NotFound(url)),
+            Err(ureq::Error::Status(status, _)) => Err(Error::Status { url, status }),
+            Err(ureq::Error::Transport(err)) => Err(Error::Network(err)),
+        }
+    }
+
+    fn agent(timeout: Duration) -> ureq::Agent {
+        ureq::AgentBuilder::new().timeout(timeout).build()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_client_url() {
+        let addr = Address::from_str(
+            "rad://hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa@willow.radicle.garden:8776",
+        )
+        .unwrap();
+        let client = SeedClient::new(&addr);
+        assert_eq!(client.url().as_str(), "https://willow.radicle.garden:8777/");
+
+        let addr = Address::from_str("http://localhost:8778").unwrap();
+        let client = SeedClient::new(&addr);
+        assert_eq!(client.url().as_str(), "http://localhost:8777/");
+    }
+
+    #[test]
+    fn test_client_requests() {
+        let seed = crate::test::seed::Seed::spawn();
+        let client = SeedClient::from_url(seed.url());
+        let urn = Urn::from_str("rad:git:hnrkbjokbt439jk3p1dsi67u3mca85yiy7fiy").unwrap();
+        let other = Urn::from_str("rad:git:hnrkbtw9t1of4ykjy6er4qqwxtc54k9943eto").unwrap();
+        let remote = project::PeerInfo {
+            id: seed.peer,
+            person: None,
+            delegate: true,
+        };
+
+        seed.host(&urn, "acme", "master", &[remote]);
+
+        assert_eq!(client.peer().unwrap(), seed.peer);
+        assert_eq!(client.projects().unwrap().len(), 1);
+        assert_eq!(client.project(&urn).unwrap().name, "acme");
+        assert_eq!(client.remotes(&urn).unwrap()[0].id, seed.peer);
+        assert!(client.project(&other).unwrap_err().is_not_found());
+
+        seed.route(
+            format!("/v1/projects/{}/patches", urn),
+            serde_json::json!([]),
+        );
+        assert!(client.patches(&urn).unwrap().is_empty());
+        assert_eq!(
+            seed.requests().last(),
+            Some(&format!("/v1/projects/{}/patches", urn))
+        );
+    }
+}
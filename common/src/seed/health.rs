@@ -0,0 +1,252 @@
+//! Seed health checks.
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use librad::git::Urn;
+use librad::profile::Profile;
+use serde::{Deserialize, Serialize};
+
+use super::SeedClient;
+use crate::config::SeedConfig;
+
+/// Name of the file holding the results of previous health checks, in the profile.
+pub const CACHE_FILE_NAME: &str = "seeds.health.json";
+/// Health checks older than this are not used to rank seeds.
+pub const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Timeout of each probe.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(6);
+
+/// Result of probing a seed endpoint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Probe {
+    /// Time it took for the endpoint to respond, if it did.
+    pub latency: Option<Duration>,
+    /// Error encountered while probing, if any.
+    pub error: Option<String>,
+}
+
+impl Probe {
+    pub fn is_ok(&self) -> bool {
+        self.latency.is_some()
+    }
+
+    fn timed<T, E: ToString>(f: impl FnOnce() -> Result<T, E>) -> Self {
+        let start = Instant::now();
+
+        match f() {
+            Ok(_) => Self {
+                latency: Some(start.elapsed()),
+                error: None,
+            },
+            Err(err) => Self {
+                latency: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+}
+
+/// Health of a seed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Health {
+    /// HTTP API probe, via `/v1/peer`.
+    pub api: Probe,
+    /// Git endpoint probe.
+    pub git: Probe,
+    /// Resolution of the P2P endpoint's address. The P2P protocol runs over QUIC, and
+    /// the endpoint itself isn't probed, so this only tells whether the seed's host
+    /// name resolves.
+    pub dns: Probe,
+    /// Whether the seed hosts the project, if a project was given and the seed could
+    /// be queried.
+    pub project: Option<bool>,
+    /// When the seed was probed, in seconds since the epoch.
+    pub timestamp: u64,
+}
+
+impl Health {
+    /// Whether the seed is usable for syncing.
+    pub fn is_healthy(&self) -> bool {
+        self.api.is_ok() && self.dns.is_ok() && self.project != Some(false)
+    }
+
+    /// Whether this health check is recent enough to be relied upon.
+    pub fn is_fresh(&self) -> bool {
+        now().saturating_sub(self.timestamp) < CACHE_TTL.as_secs()
+    }
+}
+
+/// Probe a seed's endpoints, and check whether it hosts the given project.
+pub fn probe(seed: &SeedConfig, project: Option<&Urn>) -> Health {
+    let agent = ureq::AgentBuilder::new().timeout(PROBE_TIMEOUT).build();
+    let client = SeedClient::from_url(seed.api.clone()).timeout(PROBE_TIMEOUT);
+    let api = Probe::timed(|| client.peer());
+
+    let git = Probe::timed(|| match agent.get(seed.git.as_str()).call() {
+        // Any HTTP response means the endpoint is up.
+        Ok(_) | Err(ureq::Error::Status(_, _)) => Ok(()),
+        Err(err) => Err(err),
+    });
+
+    let dns = Probe::timed(|| {
+        let host = seed
+            .p2p
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("missing host in seed URL"))?;
+        let port = seed.p2p.port().unwrap_or(super::DEFAULT_SEED_P2P_PORT);
+
+        (host, port)
+            .to_socket_addrs()?
+            .next()
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("seed address could not be resolved"))
+    });
+
+    let project = project
+        .filter(|_| api.is_ok())
+        .and_then(|urn| match client.remotes(urn) {
+            Ok(_) => Some(true),
+            Err(err) if err.is_not_found() => Some(false),
+            Err(_) => None,
+        });
+
+    Health {
+        api,
+        git,
+        dns,
+        project,
+        timestamp: now(),
+    }
+}
+
+/// Results of previous health checks, keyed by seed P2P URL.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    seeds: HashMap<String, Health>,
+}
+
+impl Cache {
+    /// Path of the cache file for the given profile.
+    pub fn path(profile: &Profile) -> PathBuf {
+        profile.paths().seeds_file().with_file_name(CACHE_FILE_NAME)
+    }
+
+    /// Load the cache. Returns an empty cache if it can't be read.
+    pub fn load(profile: &Profile) -> Self {
+        fs::read(Self::path(profile))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, profile: &Profile) -> Result<(), io::Error> {
+        let json = serde_json::to_vec_pretty(self)?;
+
+        fs::write(Self::path(profile), json)
+    }
+
+    /// Get the latest fresh health check of a seed.
+    pub fn get(&self, seed: &SeedConfig) -> Option<&Health> {
+        self.seeds
+            .get(seed.p2p.as_str())
+            .filter(|health| health.is_fresh())
+    }
+
+    pub fn insert(&mut self, seed: &SeedConfig, health: Health) {
+        self.seeds.insert(seed.p2p.to_string(), health);
+    }
+
+    /// Rank seeds by health, except for the first seed, which is the configured default,
+    /// and stays first. Other seeds known to be healthy come next, ordered by API latency,
+    /// then seeds with unknown health, then unhealthy seeds. The configured order is
+    /// otherwise preserved.
+    pub fn rank(&self, seeds: &mut [SeedConfig]) {
+        let others = match seeds.split_first_mut() {
+            Some((_, others)) => others,
+            None => return,
+        };
+        others.sort_by_key(|seed| match self.get(seed) {
+            Some(health) if health.is_healthy() => (0, health.api.latency),
+            None => (1, None),
+            Some(_) => (2, None),
+        });
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+    use url::Host;
+
+    fn health(latency: Option<u64>) -> Health {
+        let probe = Probe {
+            latency: latency.map(Duration::from_millis),
+            error: None,
+        };
+        Health {
+            api: probe.clone(),
+            git: probe.clone(),
+            dns: probe,
+            project: None,
+            timestamp: now(),
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        let seed = crate::test::seed::Seed::spawn();
+        let urn = Urn::from_str("rad:git:hnrkbjokbt439jk3p1dsi67u3mca85yiy7fiy").unwrap();
+        let other = Urn::from_str("rad:git:hnrkbtw9t1of4ykjy6er4qqwxtc54k9943eto").unwrap();
+
+        seed.host(&urn, "acme", "master", &[]);
+
+        let health = probe(&seed.config(), Some(&urn));
+        assert!(health.api.is_ok());
+        assert!(health.dns.is_ok());
+        assert_eq!(health.project, Some(true));
+        assert!(health.is_healthy());
+
+        let health = probe(&seed.config(), Some(&other));
+        assert_eq!(health.project, Some(false));
+        assert!(!health.is_healthy());
+    }
+
+    #[test]
+    fn test_rank() {
+        let seed = |host: &str| SeedConfig::new(Host::Domain(host.to_owned()), None, None);
+        let (a, b, c, d, e) = (seed("a"), seed("b"), seed("c"), seed("d"), seed("e"));
+        let mut cache = Cache::default();
+        let mut stale = health(Some(1));
+
+        stale.timestamp = 0;
+
+        cache.insert(&a, health(None));
+        cache.insert(&b, health(Some(300)));
+        cache.insert(&e, health(None));
+        cache.insert(&c, health(Some(10)));
+        cache.insert(&d, stale);
+
+        // The default seed stays first, even when unhealthy.
+        let mut seeds = vec![e, a, b, c, d];
+        cache.rank(&mut seeds);
+
+        let hosts = seeds
+            .iter()
+            .map(|s| s.p2p.host_str().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(hosts, vec!["e", "c", "b", "d", "a"]);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod external;
+
 use zeroize::Zeroizing;
 
 use librad::crypto::keystore::sign::ed25519;
@@ -0,0 +1,292 @@
+//! External signers, for keys that live outside of the profile's keystore, eg. in a
+//! hardware token or an OS keychain.
+//!
+//! An external signer is an executable that is run for each request. It reads a single
+//! JSON request line from its standard input, and writes a single JSON response line to
+//! its standard output. Its standard error is left attached to the terminal, so that it
+//! can ask the user to eg. touch their token.
+//!
+//! Requests are one of:
+//!
+//! * `{"op":"public-key"}`, answered with `{"key":"<peer-id>"}`.
+//! * `{"op":"sign","key":"<peer-id>","data":"<base64>"}`, answered with
+//!   `{"signature":"<base64>"}`, an Ed25519 signature of the data.
+//!
+//! Failures are answered with `{"error":"<message>"}`, or a non-zero exit status.
+//! Signatures are verified before they are used.
+//!
+//! Profiles are always created with a key generated in software, so the signer must hold
+//! the profile's existing key: for a hardware token, the key is imported into the token
+//! after `rad auth --init`. Tokens that only sign with keys they generate can't be used.
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use librad::crypto::keystore::sign::ed25519;
+use librad::crypto::{BoxedSignError, BoxedSigner, Signature};
+use librad::profile::Profile;
+use librad::PeerId;
+
+use lnk_clib::keys;
+
+use super::ToSigner;
+use crate::{config, profile};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to run external signer '{0}': {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("external signer exited with {0}")]
+    Exit(std::process::ExitStatus),
+    #[error("invalid external signer response: {0}")]
+    Protocol(String),
+    #[error("external signer failed: {0}")]
+    Signer(String),
+    #[error("external signer key {actual} doesn't match the profile key {expected}")]
+    KeyMismatch { expected: PeerId, actual: PeerId },
+    #[error("external signer returned an invalid signature")]
+    InvalidSignature,
+    #[error("failed to read signer configuration from '{0}': {1}")]
+    Config(PathBuf, #[source] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "kebab-case")]
+enum Request {
+    PublicKey,
+    Sign { key: PeerId, data: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    key: Option<String>,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+/// Signer that delegates signing to an external program.
+#[derive(Debug, Clone)]
+pub struct ExternalSigner {
+    program: PathBuf,
+    args: Vec<String>,
+    peer: PeerId,
+}
+
+impl ExternalSigner {
+    /// Create a signer from the given program and arguments. The program is asked for
+    /// its public key.
+    pub fn new(program: &Path, args: &[String]) -> Result<Self, Error> {
+        let key = request(program, args, &Request::PublicKey)?
+            .key
+            .ok_or_else(|| Error::Protocol("missing key".to_owned()))?;
+        let peer = PeerId::from_str(&key)
+            .map_err(|_| Error::Protocol(format!("invalid key '{}'", key)))?;
+
+        Ok(Self {
+            program: program.to_path_buf(),
+            args: args.to_vec(),
+            peer,
+        })
+    }
+
+    /// Load the external signer configured for the given profile, if any. Fails if the
+    /// profile configuration can't be read, or if the signer's key isn't the profile key.
+    pub fn load(profile: &Profile) -> Result<Option<Self>, Error> {
+        let cfg = match config::Config::profile(profile) {
+            Ok(config) => match config.signer {
+                Some(cfg) => cfg,
+                None => return Ok(None),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Config(config::Config::path(profile), err)),
+        };
+        let expected = *profile::read_only(profile)?.peer_id();
+        let signer = Self::new(&cfg.program, &cfg.args)?;
+
+        if signer.peer != expected {
+            return Err(Error::KeyMismatch {
+                expected,
+                actual: signer.peer,
+            });
+        }
+        Ok(Some(signer))
+    }
+
+    /// Key of the signer.
+    pub fn peer_id(&self) -> &PeerId {
+        &self.peer
+    }
+
+    /// Sign the given data, and verify the signature.
+    pub fn sign(&self, data: &[u8]) -> Result<ed25519::Signature, Error> {
+        let response = request(
+            &self.program,
+            &self.args,
+            &Request::Sign {
+                key: self.peer,
+                data: base64::encode(data),
+            },
+        )?;
+        let signature = response
+            .signature
+            .ok_or_else(|| Error::Protocol("missing signature".to_owned()))?;
+        let bytes = base64::decode(&signature)
+            .map_err(|_| Error::Protocol("signature is not valid base64".to_owned()))?;
+
+        if bytes.len() != 64 {
+            return Err(Error::InvalidSignature);
+        }
+        let mut sig = [0; 64];
+        sig.copy_from_slice(&bytes);
+
+        if !self
+            .peer
+            .as_public_key()
+            .verify(&Signature::from(ed25519::Signature(sig)), data)
+        {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(ed25519::Signature(sig))
+    }
+}
+
+/// Run the signer program with a single request, and read its response.
+fn request(program: &Path, args: &[String], request: &Request) -> Result<Response, Error> {
+    let io = |e| Error::Io(program.to_path_buf(), e);
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(io)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let line = serde_json::to_string(request).map_err(|e| Error::Protocol(e.to_string()))?;
+        writeln!(stdin, "{}", line).map_err(io)?;
+    }
+    let output = child.wait_with_output().map_err(io)?;
+    if !output.status.success() {
+        return Err(Error::Exit(output.status));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().unwrap_or_default();
+    let response: Response =
+        serde_json::from_str(line).map_err(|e| Error::Protocol(e.to_string()))?;
+
+    if let Some(err) = response.error {
+        return Err(Error::Signer(err));
+    }
+    Ok(response)
+}
+
+impl ToSigner for ExternalSigner {
+    fn to_signer(self, _profile: &Profile) -> Result<BoxedSigner, keys::ssh::Error> {
+        Ok(BoxedSigner::new(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl ed25519::Signer for ExternalSigner {
+    type Error = BoxedSignError;
+
+    fn public_key(&self) -> ed25519::PublicKey {
+        let mut key = [0; 32];
+        key.copy_from_slice(self.peer.as_public_key().as_ref());
+
+        ed25519::PublicKey(key)
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<ed25519::Signature, Self::Error> {
+        ExternalSigner::sign(self, data).map_err(BoxedSignError::from_std_error)
+    }
+}
+
+impl librad::Signer for ExternalSigner {
+    fn sign_blocking(
+        &self,
+        data: &[u8],
+    ) -> Result<librad::keystore::sign::Signature, <Self as ed25519::Signer>::Error> {
+        ExternalSigner::sign(self, data).map_err(BoxedSignError::from_std_error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use librad::crypto::SecretKey;
+    use librad::Signer as _;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Write a stub signer, that answers with the given key and signature.
+    fn stub(key: &PeerId, signature: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rad-signer-{}", key));
+        let script = format!(
+            "#!/bin/sh\n\
+             read -r request\n\
+             case \"$request\" in\n\
+               *public-key*) echo '{{\"key\":\"{}\"}}' ;;\n\
+               *sign*) echo '{{\"signature\":\"{}\"}}' ;;\n\
+               *) echo '{{\"error\":\"unknown request\"}}' ;;\n\
+             esac\n",
+            key,
+            base64::encode(signature)
+        );
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn test_external_signer() {
+        let key = SecretKey::new();
+        let peer = PeerId::from(key.clone());
+        let signature = key.sign_blocking(b"radicle").unwrap();
+        let program = stub(&peer, &signature.0);
+
+        let signer = ExternalSigner::new(&program, &[]).unwrap();
+        assert_eq!(signer.peer_id(), &peer);
+        assert_eq!(signer.sign(b"radicle").unwrap().0, signature.0);
+
+        // The stub always answers with the same signature, which is only valid for the
+        // data it was made for.
+        assert!(matches!(
+            signer.sign(b"heartwood"),
+            Err(Error::InvalidSignature)
+        ));
+        fs::remove_file(&program).ok();
+
+        assert!(matches!(
+            ExternalSigner::new(&program, &[]),
+            Err(Error::Io(..))
+        ));
+    }
+
+    #[test]
+    fn test_load() {
+        let (_, profile, _, _) = crate::test::setup::profile();
+        let path = config::Config::path(&profile);
+
+        fs::write(&path, "seed = []\n").unwrap();
+        assert!(ExternalSigner::load(&profile).unwrap().is_none());
+
+        // A broken configuration isn't mistaken for the absence of a signer.
+        fs::write(&path, "seed = []\n[signer]\nargs = 1\n").unwrap();
+        assert!(matches!(
+            ExternalSigner::load(&profile),
+            Err(Error::Config(..))
+        ));
+
+        fs::remove_file(&path).ok();
+        assert!(ExternalSigner::load(&profile).unwrap().is_none());
+    }
+}
@@ -1,11 +1,16 @@
+mod fetch;
 mod push;
+pub mod queue;
 
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
+use std::iter;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time;
 
 use anyhow::anyhow;
+use futures::stream::{self, StreamExt as _};
+use thiserror::Error;
 
 use librad::crypto::BoxedSigner;
 use librad::git::Urn;
@@ -17,72 +22,302 @@ use librad::net::{
     quic::ConnectPeer,
     replication, Network,
 };
+use librad::paths::Paths;
 use librad::profile::Profile;
-use librad::Signer;
+use librad::{PeerId, Signer};
 use link_async::Spawner;
 use lnk_clib::seed::store::FileStore;
+use url::Url;
 
 pub use lnk_clib::seed::{Seed, Seeds};
 pub use lnk_sync::Mode;
 
 use crate::config;
 use crate::nonempty::NonEmpty;
+use crate::seed::health;
+
+/// Default number of seeds synced concurrently.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Default timeout when syncing with a seed.
+pub const DEFAULT_TIMEOUT: time::Duration = time::Duration::from_secs(9);
+
+/// Refs to sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refs {
+    /// All refs.
+    All,
+    /// Collaborative objects only, ie. `refs/cobs/*`.
+    Cobs,
+    /// Code only, ie. `refs/heads/*`.
+    Code,
+}
+
+impl Default for Refs {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+impl Refs {
+    /// Whether the given ref, relative to a remote, eg. `heads/master`, should be synced.
+    /// Identity refs are always synced, since they are needed to verify the others.
+    pub fn matches(&self, name: &str) -> bool {
+        let is_identity = name == "rad/id" || name.starts_with("rad/ids/");
+
+        match self {
+            Self::All => true,
+            Self::Cobs => is_identity || name.starts_with("cobs/"),
+            Self::Code => is_identity || name.starts_with("heads/"),
+        }
+    }
+}
+
+/// Sync options.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Whether to fetch, push, or both.
+    pub mode: Mode,
+    /// Refs to fetch. Pushes are handled by the seed, which always fetches all refs.
+    pub refs: Refs,
+    /// Timeout of each operation with a seed.
+    pub timeout: time::Duration,
+    /// Maximum number of seeds synced at the same time.
+    pub concurrency: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            refs: Refs::default(),
+            timeout: DEFAULT_TIMEOUT,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+impl From<Mode> for Options {
+    fn from(mode: Mode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+}
+
+/// Result of fetching from a seed.
+#[derive(Debug)]
+pub enum Fetch {
+    /// All refs were replicated.
+    Replicated(replication::Success),
+    /// The given refs were fetched.
+    Refs(Vec<String>),
+}
+
+/// Error fetching from a seed.
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error(transparent)]
+    Replicate(#[from] client::error::Replicate),
+    #[error(transparent)]
+    Refs(#[from] fetch::Error),
+}
 
 /// Sync result of a seed.
 #[derive(Debug)]
 pub struct SyncResult {
     pub seed: Seed<Vec<SocketAddr>>,
-    pub fetch: Option<Result<replication::Success, client::error::Replicate>>,
+    pub fetch: Option<Result<Fetch, FetchError>>,
     pub push: Option<Result<push::Success, push::Error>>,
 }
 
+impl SyncResult {
+    /// Whether all operations attempted with this seed succeeded.
+    pub fn is_success(&self) -> bool {
+        !matches!(self.fetch, Some(Err(_))) && !matches!(self.push, Some(Err(_)))
+    }
+
+    /// The first error encountered while syncing with this seed, if any.
+    pub fn error(&self) -> Option<String> {
+        match (&self.fetch, &self.push) {
+            (Some(Err(err)), _) => Some(err.to_string()),
+            (_, Some(Err(err))) => Some(err.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// Progress of a sync with a given seed.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// Fetching from the seed.
+    Fetching,
+    /// Pushing to the seed.
+    Pushing,
+    /// Progress message sent by the seed while pushing.
+    Progress(&'a str),
+    /// Syncing with the seed is done.
+    Done(&'a SyncResult),
+}
+
 /// Sync the given URN with the provided list of seeds.
+///
+/// Up to `options.concurrency` seeds are synced at the same time. Seeds that can't be
+/// resolved are skipped. Progress is reported through the `progress` callback, as it
+/// happens, for each seed.
+///
+/// When only some refs are to be fetched, they are fetched via the seeds' git endpoint,
+/// into the monorepo of the given profile. Refs covered by signed refs are verified
+/// against the signed refs of their peer; collaborative objects aren't. The git endpoint
+/// of a seed is taken from the profile configuration if the seed is configured, and
+/// otherwise derived from the seed's host.
 pub async fn sync<S, E>(
     client: &Client<S, E>,
+    profile: &Profile,
     urn: Urn,
-    seeds: Seeds,
-    mode: Mode,
-    timeout: time::Duration,
+    seeds: impl IntoIterator<Item = Seed<String>>,
+    options: Options,
+    progress: impl Fn(&PeerId, Event),
 ) -> Vec<SyncResult>
 where
     S: Signer + Clone,
     E: ConnectPeer + Clone + Send + Sync + 'static,
 {
-    let mut syncs = Vec::with_capacity(seeds.len());
-    let is_push = mode.is_push();
-    let is_fetch = mode.is_fetch();
-    let Seeds(seeds) = seeds;
+    // The local storage, for selective fetches.
+    let paths = if options.refs != Refs::All && options.mode.is_fetch() {
+        Some(profile.paths().clone())
+    } else {
+        None
+    };
+    // The configured seeds, for their git endpoints.
+    let configured = if paths.is_some() {
+        config::Config::load(profile)
+            .map(|cfg| cfg.seed)
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let configured = &configured;
+    let paths = &paths;
+    let progress = &progress;
+    let urn = &urn;
 
-    for seed in seeds {
-        let fetch = if is_fetch {
+    stream::iter(seeds)
+        .map(|seed| async move {
+            let (Seeds(mut resolved), _) = Seeds::resolve(iter::once(&seed)).await;
+            let resolved = resolved.pop()?;
+            let git = configured
+                .iter()
+                .find(|cfg| cfg.peer() == Some(seed.peer))
+                .map(|cfg| &cfg.git);
+            let result =
+                sync_seed(client, urn, &seed, git, resolved, paths, options, progress).await;
+            progress(&result.seed.peer, Event::Done(&result));
+
+            Some(result)
+        })
+        .buffer_unordered(options.concurrency.max(1))
+        .filter_map(|result| async move { result })
+        .collect()
+        .await
+}
+
+/// Sync the given URN with a single seed.
+#[allow(clippy::too_many_arguments)]
+async fn sync_seed<S, E>(
+    client: &Client<S, E>,
+    urn: &Urn,
+    addr: &Seed<String>,
+    git: Option<&Url>,
+    seed: Seed<Vec<SocketAddr>>,
+    paths: &Option<Paths>,
+    options: Options,
+    progress: &impl Fn(&PeerId, Event),
+) -> SyncResult
+where
+    S: Signer + Clone,
+    E: ConnectPeer + Clone + Send + Sync + 'static,
+{
+    let Options {
+        mode,
+        refs,
+        timeout,
+        ..
+    } = options;
+
+    let fetch = if mode.is_fetch() {
+        progress(&seed.peer, Event::Fetching);
+
+        let result = if refs == Refs::All {
             match tokio::time::timeout(timeout, client.replicate(seed.clone(), urn.clone(), None))
                 .await
             {
-                Ok(result) => Some(result),
-                Err(_) => Some(Err(client::error::Replicate::NoConnection(
-                    client::error::NoConnection(seed.peer),
-                ))),
+                Ok(result) => result.map(Fetch::Replicated).map_err(FetchError::from),
+                Err(_) => Err(FetchError::Replicate(
+                    client::error::Replicate::NoConnection(client::error::NoConnection(seed.peer)),
+                )),
             }
         } else {
-            None
+            fetch_refs(urn, addr, git, paths, refs, timeout)
+                .await
+                .map(Fetch::Refs)
+                .map_err(FetchError::from)
         };
+        Some(result)
+    } else {
+        None
+    };
 
-        let push = if is_push {
-            Some(push::push(client, urn.clone(), seed.clone(), timeout).await)
-        } else {
-            None
-        };
+    let push = if mode.is_push() {
+        progress(&seed.peer, Event::Pushing);
 
-        syncs.push(SyncResult { seed, fetch, push })
-    }
-    syncs
+        Some(
+            push::push(client, urn.clone(), seed.clone(), timeout, |msg| {
+                progress(&seed.peer, Event::Progress(msg))
+            })
+            .await,
+        )
+    } else {
+        None
+    };
+
+    SyncResult { seed, fetch, push }
+}
+
+/// Fetch the given refs from the seed's git endpoint. If the endpoint isn't given, it is
+/// derived from the seed's host.
+async fn fetch_refs(
+    urn: &Urn,
+    seed: &Seed<String>,
+    git: Option<&Url>,
+    paths: &Option<Paths>,
+    refs: Refs,
+    timeout: time::Duration,
+) -> Result<Vec<String>, fetch::Error> {
+    let paths = paths.clone().ok_or(fetch::Error::NoStorage)?;
+    let git = match git {
+        Some(git) => git.clone(),
+        None => config::SeedConfig::try_from(seed.clone())
+            .map(|cfg| cfg.git)
+            .map_err(|_| fetch::Error::NoEndpoint)?,
+    };
+    // Nb. Like with `rad track --remote`, the repository is found under the endpoint
+    // by URN id.
+    let url = git
+        .join(&urn.encode_id())
+        .map_err(|_| fetch::Error::NoEndpoint)?
+        .to_string();
+
+    tokio::time::timeout(timeout, fetch::fetch(paths, urn.clone(), url, refs))
+        .await
+        .map_err(|_| fetch::Error::Timeout)?
 }
 
+/// Sync client, as created by [`client`].
+pub type SyncClient = Client<BoxedSigner, SendOnly>;
+
 /// Create a sync client.
-pub async fn client(
-    signer: BoxedSigner,
-    profile: &Profile,
-) -> anyhow::Result<Client<BoxedSigner, SendOnly>> {
+pub async fn client(signer: BoxedSigner, profile: &Profile) -> anyhow::Result<SyncClient> {
     let spawner = Spawner::from_current().ok_or(anyhow!("cannot create spawner"))?;
     let network = Network::default();
     let config = client::Config {
@@ -102,9 +337,13 @@ pub async fn client(
 /// First checks local (working copy) config, then global.
 pub fn seeds(profile: &Profile) -> anyhow::Result<NonEmpty<Seed<String>>> {
     if let Ok(config) = config::Config::load(profile) {
-        let seeds = config
-            .seeds()
-            .cloned()
+        let mut seeds = config.seeds().cloned().collect::<Vec<_>>();
+
+        // After the default seed, prefer seeds that were found to be healthy.
+        health::Cache::load(profile).rank(&mut seeds);
+
+        let seeds = seeds
+            .into_iter()
             .map(|s| s.try_into())
             .collect::<Result<Vec<_>, _>>()?;
         if let Ok(seeds) = seeds.try_into() {
@@ -112,9 +351,9 @@ pub fn seeds(profile: &Profile) -> anyhow::Result<NonEmpty<Seed<String>>> {
         }
     }
 
-    // Fallback to global seeds if no local seeds are configured.
-    // Nb. These seeds are in a different format, and eventually we will consolidate the
-    // seed configurations under a unified format. For now we support both.
+    // Fallback to the legacy seeds file if no seeds are configured.
+    // Nb. These seeds are in a different format, and are migrated to the profile
+    // configuration by `rad seed`.
     let seeds_file = profile.paths().seeds_file();
     let store = FileStore::<String>::new(seeds_file)?;
     let seeds: Vec<_> = store.iter()?.collect::<Result<_, _>>()?;
@@ -123,3 +362,121 @@ pub fn seeds(profile: &Profile) -> anyhow::Result<NonEmpty<Seed<String>>> {
         .try_into()
         .map_err(|_| anyhow!("No seeds configured for profile {}", profile.id()))
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use librad::crypto::keystore::crypto::{Pwhash, KDF_PARAMS_TEST};
+    use librad::crypto::keystore::pinentry::SecUtf8;
+    use librad::git::tracking;
+
+    use super::*;
+    use crate::{git, test};
+
+    #[test]
+    fn test_sync() {
+        // The remote peer, and the local peer, which tracks it.
+        let (remote, _, _, project) = test::setup::profile();
+        let (local, profile, _, _) = test::setup::profile();
+        let urn = project.urn();
+        let peer = *remote.peer_id();
+        let seed = test::seed::Seed::spawn();
+
+        tracking::track(
+            &local,
+            &urn,
+            Some(peer),
+            tracking::config::Config::default(),
+            tracking::policy::Track::Any,
+        )
+        .unwrap()
+        .ok();
+        seed.request_pull(remote.read_only(), &urn);
+
+        // An issue of the remote peer, on the seed.
+        let repo = seed.repository(&urn);
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = repo.commit(None, &sig, &sig, "Issue", &tree, &[]).unwrap();
+        let issue = format!("refs/remotes/{}/cobs/xyz.radicle.issue/1", peer);
+        repo.reference(&issue, oid, true, "").unwrap();
+
+        // The seed is configured in the local profile, along with its git endpoint.
+        let mut cfg = config::Config::scope(config::Scope::Profile, &profile).unwrap();
+        cfg.add(seed.config());
+        cfg.write(config::Config::path(&profile)).unwrap();
+
+        let addr = Seed::<String>::try_from(seed.config()).unwrap();
+        let signer = test::signer(
+            &profile,
+            Pwhash::new(SecUtf8::from(test::USER_PASS), *KDF_PARAMS_TEST),
+        )
+        .unwrap();
+        let options = Options {
+            mode: Mode::All,
+            refs: Refs::Cobs,
+            timeout: time::Duration::from_secs(3),
+            ..Options::default()
+        };
+        let events = Mutex::new(Vec::new());
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let results = rt.block_on(async {
+            let client = client(signer, &profile).await.unwrap();
+
+            sync(
+                &client,
+                &profile,
+                urn.clone(),
+                [addr],
+                options,
+                |peer, event| {
+                    let event = match event {
+                        Event::Fetching => "fetching",
+                        Event::Pushing => "pushing",
+                        Event::Progress(_) => return,
+                        Event::Done(_) => "done",
+                    };
+                    events.lock().unwrap().push((*peer, event));
+                },
+            )
+            .await
+        });
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].seed.peer, seed.peer);
+        assert_eq!(
+            events.into_inner().unwrap(),
+            vec![
+                (seed.peer, "fetching"),
+                (seed.peer, "pushing"),
+                (seed.peer, "done")
+            ]
+        );
+
+        // The issue was fetched from the git endpoint of the seed.
+        match &results[0].fetch {
+            Some(Ok(Fetch::Refs(fetched))) => assert!(fetched.contains(&issue)),
+            other => panic!("unexpected fetch result: {:?}", other),
+        }
+        let monorepo = git::Repository::open_bare(local.path()).unwrap();
+        assert_eq!(
+            monorepo
+                .find_reference(&format!("refs/namespaces/{}/{}", urn.encode_id(), issue))
+                .unwrap()
+                .target(),
+            Some(oid)
+        );
+
+        // Pushing goes over P2P, which the test seed doesn't serve: the push fails, and
+        // is reported.
+        assert!(matches!(results[0].push, Some(Err(_))));
+        assert!(!results[0].is_success());
+        assert!(results[0].error().is_some());
+    }
+}
@@ -0,0 +1,220 @@
+//! Fetching a subset of refs from a seed, via its git endpoint.
+//!
+//! P2P replication always fetches all the refs of an identity. When only some refs are
+//! needed, eg. only collaborative objects, we instead fetch these refs from the seed's
+//! git endpoint, into the local monorepo. As with replication, only the refs of tracked
+//! peers are fetched.
+//!
+//! Refs in the categories covered by signed refs, ie. branches, tags and notes, are only
+//! kept if they match the peer's signed refs. Collaborative object refs aren't covered by
+//! signed refs, so they are kept as fetched: their changes are signed individually, and
+//! checked when the objects are loaded, but nothing guarantees that the fetched refs are
+//! the peer's latest.
+//!
+//! Nb. The pinned `librad` replication can't be restricted to a subset of refs, which is
+//! why replication isn't used here.
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use librad::git::storage::ReadOnly;
+use librad::git::{tracking, Urn};
+use librad::paths::Paths;
+use librad::PeerId;
+
+use super::Refs;
+use crate::{git, refs, tokio};
+
+/// Signed refs of a peer, relative to the peer. They are always fetched, to verify the
+/// other refs against.
+const SIGNED_REFS: &str = "rad/signed_refs";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("seed has no git endpoint")]
+    NoEndpoint,
+    #[error("local storage could not be opened")]
+    NoStorage,
+    #[error("timed out")]
+    Timeout,
+    #[error("tracked peers could not be read: {0}")]
+    Tracking(String),
+    #[error(transparent)]
+    Verification(#[from] refs::Error),
+    #[error("storage: {0}")]
+    Storage(#[from] git2::Error),
+    #[error("git: {0}")]
+    Git(#[from] anyhow::Error),
+    #[error("fetch task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// Fetch the refs of the given project matching `refs`, from the git repository at `url`,
+/// into the monorepo. Only the refs of tracked peers are fetched, along with their signed
+/// refs. If a peer's signed refs are invalid, or its refs covered by them don't match,
+/// they are rolled back. Collaborative object refs aren't verified.
+///
+/// Returns the names of the fetched refs.
+pub(super) async fn fetch(
+    paths: Paths,
+    urn: Urn,
+    url: String,
+    refs: Refs,
+) -> Result<Vec<String>, Error> {
+    // Nb. `git` is blocking, so we run it on a separate thread, to not hold up the other
+    // seeds being synced.
+    tokio::task::spawn_blocking(move || {
+        let storage = ReadOnly::open(&paths).map_err(|_| Error::NoStorage)?;
+        let local = *storage.peer_id();
+        let tracked = tracking::tracked(&storage, Some(&urn))
+            .map_err(|e| Error::Tracking(e.to_string()))?
+            .filter_map(|tracked| tracked.ok().and_then(|t| t.peer_id()))
+            .filter(|peer| peer != &local)
+            .collect::<HashSet<_>>();
+
+        // Refs to fetch, by peer.
+        let remote = git::git(storage.path(), ["ls-remote", url.as_str()])?;
+        let mut wanted: HashMap<PeerId, Vec<String>> = HashMap::new();
+        for (peer, name) in remote
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(_, name)| git::parse_remote(name))
+        {
+            if tracked.contains(&peer) && (name == SIGNED_REFS || refs.matches(name)) {
+                wanted.entry(peer).or_default().push(name.to_owned());
+            }
+        }
+
+        let repo = git::Repository::open_bare(storage.path())?;
+        let namespace = format!("refs/namespaces/{}/refs/remotes", urn.encode_id());
+        let mut fetched = Vec::new();
+
+        for (peer, names) in wanted {
+            // Without signed refs, there is nothing to verify the peer's refs against.
+            if !names.iter().any(|name| name == SIGNED_REFS) {
+                continue;
+            }
+            let targets = names
+                .iter()
+                .map(|name| format!("{}/{}/{}", namespace, peer, name))
+                .collect::<Vec<_>>();
+            let snapshot = refs::Snapshot::take(&repo, targets.iter().map(String::as_str));
+            let refspecs = names
+                .iter()
+                .zip(&targets)
+                .map(|(name, target)| format!("+refs/remotes/{}/{}:{}", peer, name, target));
+            let args = ["fetch", "--quiet", url.as_str()]
+                .iter()
+                .map(|a| a.to_string())
+                .chain(refspecs)
+                .collect::<Vec<_>>();
+
+            let result = git::git(storage.path(), args)
+                .map_err(Error::from)
+                .and_then(|_| {
+                    refs::verify(&storage, &urn, peer, names.iter().map(String::as_str))
+                        .map_err(Error::from)
+                });
+            if let Err(err) = result {
+                snapshot.restore(&repo)?;
+                return Err(err);
+            }
+            fetched.extend(
+                names
+                    .into_iter()
+                    .filter(|name| name != SIGNED_REFS)
+                    .map(|name| format!("refs/remotes/{}/{}", peer, name)),
+            );
+        }
+        Ok(fetched)
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use librad::crypto::SecretKey;
+
+    use crate::test;
+
+    #[test]
+    fn test_fetch_refs() {
+        // The remote peer, and the local peer, which tracks it.
+        let (remote, _, _, project) = test::setup::profile();
+        let (local, profile, _, _) = test::setup::profile();
+        let urn = project.urn();
+        let peer = *remote.peer_id();
+        let untracked = PeerId::from(SecretKey::new());
+
+        tracking::track(
+            &local,
+            &urn,
+            Some(peer),
+            tracking::config::Config::default(),
+            tracking::policy::Track::Any,
+        )
+        .unwrap()
+        .ok();
+
+        // The seed, with the signed refs of the remote peer, and refs that aren't signed.
+        let seed = test::seed::Seed::spawn();
+        seed.request_pull(remote.read_only(), &urn);
+
+        let repo = seed.repository(&urn);
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &sig, &sig, "Initial", &tree, &[])
+            .unwrap();
+
+        for (peer, name) in [
+            (peer, "cobs/xyz.radicle.issue/1"),
+            (peer, "heads/master"),
+            (untracked, "cobs/xyz.radicle.issue/2"),
+            (untracked, "rad/signed_refs"),
+        ] {
+            repo.reference(&format!("refs/remotes/{}/{}", peer, name), oid, true, "")
+                .unwrap();
+        }
+
+        let url = seed.git_url().join(&urn.encode_id()).unwrap().to_string();
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let fetched = rt
+            .block_on(fetch(
+                profile.paths().clone(),
+                urn.clone(),
+                url.clone(),
+                Refs::Cobs,
+            ))
+            .unwrap();
+
+        assert!(fetched.contains(&format!("refs/remotes/{}/cobs/xyz.radicle.issue/1", peer)));
+        assert!(fetched.contains(&format!("refs/remotes/{}/rad/id", peer)));
+        assert!(!fetched.iter().any(|name| name.ends_with("heads/master")));
+        assert!(!fetched
+            .iter()
+            .any(|name| name.contains(&untracked.to_string())));
+
+        // The remote's branch isn't signed: it is rolled back.
+        let result = rt.block_on(fetch(profile.paths().clone(), urn.clone(), url, Refs::Code));
+        assert!(matches!(
+            result,
+            Err(Error::Verification(refs::Error::Unsigned { .. }))
+        ));
+
+        let monorepo = git::Repository::open_bare(local.path()).unwrap();
+        assert!(monorepo
+            .find_reference(&format!(
+                "refs/namespaces/{}/refs/remotes/{}/heads/master",
+                urn.encode_id(),
+                peer
+            ))
+            .is_err());
+    }
+}
@@ -0,0 +1,233 @@
+//! Queue of failed sync operations, to be retried later, eg. once the network is back.
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use librad::git::Urn;
+use librad::profile::Profile;
+use serde::{Deserialize, Serialize};
+
+use super::{Mode, Seed};
+use crate::project;
+
+/// Name of the queue file, in the profile.
+pub const FILE_NAME: &str = "sync-queue.json";
+
+/// A sync operation that failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Identity to sync.
+    #[serde(deserialize_with = "project::deserialize_urn")]
+    pub urn: Urn,
+    /// Sync mode.
+    #[serde(with = "mode")]
+    pub mode: Mode,
+    /// Seeds the sync failed with, in `<peer-id>@<host>:<port>` form.
+    pub seeds: Vec<String>,
+    /// Number of attempts made.
+    pub attempts: u32,
+}
+
+impl Entry {
+    /// Parse the seeds of this entry. Invalid seeds are skipped.
+    pub fn seeds(&self) -> Vec<Seed<String>> {
+        self.seeds
+            .iter()
+            .filter_map(|s| Seed::from_str(s).ok())
+            .collect()
+    }
+}
+
+/// Persistent queue of failed sync operations.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Queue {
+    entries: Vec<Entry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Queue {
+    /// Path of the queue file for the given profile.
+    pub fn path(profile: &Profile) -> PathBuf {
+        // Nb. Like the profile config, this is stored next to the seeds file.
+        profile.paths().seeds_file().with_file_name(FILE_NAME)
+    }
+
+    /// Load the queue of the given profile. Returns an empty queue if there is no
+    /// queue file.
+    pub fn load(profile: &Profile) -> Result<Self, io::Error> {
+        let path = Self::path(profile);
+        let mut queue: Self = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(err) => return Err(err),
+        };
+        queue.path = path;
+
+        Ok(queue)
+    }
+
+    /// Write the queue back to disk. The queue is written to a temporary file first, and
+    /// then renamed into place, so that it is never left partially written, eg. when
+    /// `rad sync --watch` and another command save it at the same time.
+    pub fn save(&self) -> Result<(), io::Error> {
+        let json = serde_json::to_vec_pretty(self)?;
+        let tmp = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record a failed sync, with the seeds it failed with. If the same sync was already
+    /// queued, its seeds are replaced, since seeds that have since succeeded no longer
+    /// need to be retried, and its attempt count is incremented.
+    pub fn enqueue<'a>(
+        &mut self,
+        urn: &Urn,
+        mode: Mode,
+        seeds: impl IntoIterator<Item = &'a Seed<String>>,
+    ) {
+        let seeds = seeds.into_iter().map(|s| s.to_string());
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|e| &e.urn == urn && e.mode == mode)
+        {
+            entry.seeds = seeds.collect();
+            entry.attempts += 1;
+        } else {
+            self.entries.push(Entry {
+                urn: urn.clone(),
+                mode,
+                seeds: seeds.collect(),
+                attempts: 1,
+            });
+        }
+    }
+
+    /// Remove a sync from the queue, eg. after it succeeded.
+    pub fn dequeue(&mut self, urn: &Urn, mode: Mode) -> Option<Entry> {
+        let ix = self
+            .entries
+            .iter()
+            .position(|e| &e.urn == urn && e.mode == mode)?;
+
+        Some(self.entries.remove(ix))
+    }
+}
+
+/// (De)serialization of sync modes.
+mod mode {
+    use super::Mode;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mode: &Mode, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(match mode {
+            Mode::Fetch => "fetch",
+            Mode::Push => "push",
+            Mode::All => "all",
+        })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mode, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "fetch" => Ok(Mode::Fetch),
+            "push" => Ok(Mode::Push),
+            "all" => Ok(Mode::All),
+            other => Err(de::Error::custom(format!("unknown sync mode '{}'", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_enqueue_dequeue() {
+        let urn = Urn::from_str("rad:git:hnrkmg77m8tfzj4gi4pa4mbhgysfgzwntjpao").unwrap();
+        let seed: Seed<String> =
+            "hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa@pine.radicle.garden:8776"
+                .parse()
+                .unwrap();
+        let mut queue = Queue::default();
+
+        queue.enqueue(&urn, Mode::Push, [&seed]);
+        queue.enqueue(&urn, Mode::Push, [&seed]);
+        queue.enqueue(&urn, Mode::Fetch, [&seed]);
+
+        assert_eq!(queue.len(), 2);
+
+        let json = serde_json::to_string(&queue).unwrap();
+        let mut queue: Queue = serde_json::from_str(&json).unwrap();
+        let entry = queue.dequeue(&urn, Mode::Push).unwrap();
+
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.seeds().len(), 1);
+        assert_eq!(entry.seeds()[0].peer, seed.peer);
+        assert_eq!(queue.len(), 1);
+        assert!(queue.dequeue(&urn, Mode::Push).is_none());
+    }
+
+    #[test]
+    fn test_enqueue_replaces_seeds() {
+        let urn = Urn::from_str("rad:git:hnrkbjokbt439jk3p1dsi67u3mca85yiy7fiy").unwrap();
+        let pine: Seed<String> =
+            "hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa@pine.radicle.garden:8776"
+                .parse()
+                .unwrap();
+        let willow: Seed<String> =
+            "hyd7wpd8p5aqnm9htsfoatxkckmw6ingnsdudns9code5xq17h1rhw@willow.radicle.garden:8776"
+                .parse()
+                .unwrap();
+        let mut queue = Queue::default();
+
+        queue.enqueue(&urn, Mode::Push, [&pine, &willow]);
+        // On retry, only one of the seeds still fails.
+        queue.enqueue(&urn, Mode::Push, [&willow]);
+
+        let entry = queue.dequeue(&urn, Mode::Push).unwrap();
+
+        assert_eq!(entry.attempts, 2);
+        assert_eq!(entry.seeds().len(), 1);
+        assert_eq!(entry.seeds()[0].peer, willow.peer);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let (_, profile, _, project) = crate::test::setup::profile();
+        let seed: Seed<String> =
+            "hyb5to4rshftx4apgmu9s6wnsp4ddmp1mz6ijh4qqey7fb8wrpawxa@pine.radicle.garden:8776"
+                .parse()
+                .unwrap();
+        let mut queue = Queue::load(&profile).unwrap();
+
+        queue.enqueue(&project.urn(), Mode::Push, [&seed]);
+        queue.save().unwrap();
+
+        let queue = Queue::load(&profile).unwrap();
+        assert_eq!(queue.len(), 1);
+
+        let dir = Queue::path(&profile).parent().unwrap().to_path_buf();
+        assert!(!fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .any(|e| e.path().extension() == Some("tmp".as_ref())));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod seed;
+
 use std::fmt;
 use std::path::Path;
 use std::{env, error};
@@ -0,0 +1,339 @@
+//! In-process seed, for testing without network access.
+//!
+//! Serves the seed's `/v1` HTTP API on a local port, from data registered by the test,
+//! and a git endpoint on the local filesystem. Request-pulls are accepted in-process,
+//! with [`Seed::request_pull`]. The P2P protocol isn't served: syncing with this seed
+//! over P2P fails like with an unreachable seed.
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use librad::crypto::SecretKey;
+use librad::git::storage::ReadOnly;
+use librad::git::Urn;
+use librad::PeerId;
+use serde_json::{json, Value};
+use url::{Host, Url};
+
+use crate::config::SeedConfig;
+use crate::git;
+use crate::project::PeerInfo;
+
+type Routes = Arc<Mutex<HashMap<String, Value>>>;
+
+/// A local seed, serving its HTTP API until dropped.
+pub struct Seed {
+    /// The seed's peer id.
+    pub peer: PeerId,
+    addr: SocketAddr,
+    /// Directory of the git endpoint, with a repository per project.
+    git: PathBuf,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<String>>>,
+    shutdown: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl Seed {
+    /// Start a seed with a random peer id.
+    pub fn spawn() -> Self {
+        Self::with_peer(PeerId::from(SecretKey::new()))
+    }
+
+    /// Start a seed with the given peer id.
+    pub fn with_peer(peer: PeerId) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes = Routes::default();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let git = env::temp_dir().join(format!("rad-test-seed-{}", uuid::Uuid::new_v4()));
+
+        fs::create_dir_all(&git).unwrap();
+        routes
+            .lock()
+            .unwrap()
+            .insert("/v1/peer".to_owned(), json!({ "id": peer }));
+
+        let handle = thread::spawn({
+            let routes = routes.clone();
+            let requests = requests.clone();
+            let shutdown = shutdown.clone();
+
+            move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        serve(stream, &routes, &requests).ok();
+                    }
+                }
+            }
+        });
+
+        Self {
+            peer,
+            addr,
+            git,
+            routes,
+            requests,
+            shutdown,
+            handle: Some(handle),
+        }
+    }
+
+    /// Base URL of the HTTP API.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.addr)).unwrap()
+    }
+
+    /// Base URL of the git endpoint. Project repositories are found under it, by URN id.
+    pub fn git_url(&self) -> Url {
+        Url::from_directory_path(&self.git).unwrap()
+    }
+
+    /// Seed configuration pointing to this seed. The P2P port is not served.
+    pub fn config(&self) -> SeedConfig {
+        let mut cfg = SeedConfig::new(Host::Ipv4([127, 0, 0, 1].into()), Some(self.peer), None);
+        cfg.api = self.url();
+        cfg.git = self.git_url();
+        cfg
+    }
+
+    /// The seed's git repository of the given project, created if needed. Refs are
+    /// stored as the refs of remotes, eg. `refs/remotes/<peer>/heads/master`.
+    pub fn repository(&self, urn: &Urn) -> git::Repository {
+        let path = self.git.join(urn.encode_id());
+
+        git::Repository::open_bare(&path)
+            .or_else(|_| git::Repository::init_bare(&path))
+            .unwrap()
+    }
+
+    /// Accept a request-pull of the given project, from the peer of the given storage.
+    /// Like a real seed, the requester's refs of the project are fetched, along with
+    /// the refs of the remotes it tracks. The requester is then listed as a remote of
+    /// the project, and the commits of its branches are served by the API.
+    ///
+    /// Returns the fetched refs.
+    pub fn request_pull(&self, storage: &ReadOnly, urn: &Urn) -> Vec<String> {
+        let monorepo = git::Repository::open_bare(storage.path()).unwrap();
+        let repo = self.repository(urn);
+        let peer = *storage.peer_id();
+        let namespace = format!("refs/namespaces/{}/", urn.encode_id());
+        let mut refspecs = Vec::new();
+        let mut fetched = Vec::new();
+
+        for r in monorepo
+            .references_glob(&format!("{}*", namespace))
+            .unwrap()
+            .flatten()
+        {
+            if let Some(name) = r.name().and_then(|n| n.strip_prefix(&namespace)) {
+                let target = match git::parse_remote(name) {
+                    Some(_) => name.to_owned(),
+                    None => format!("refs/remotes/{}/{}", peer, name.trim_start_matches("refs/")),
+                };
+                refspecs.push(format!("+{}{}:{}", namespace, name, target));
+                fetched.push(target);
+            }
+        }
+        if fetched.is_empty() {
+            return fetched;
+        }
+
+        let mut args = vec![
+            "fetch".to_owned(),
+            "--quiet".to_owned(),
+            storage.path().display().to_string(),
+        ];
+        args.extend(refspecs);
+        git::git(repo.path(), args).unwrap();
+
+        for name in &fetched {
+            if let Some((_, branch)) = git::parse_remote(name) {
+                if !branch.starts_with("heads/") {
+                    continue;
+                }
+                let commit = repo.find_reference(name).unwrap().peel_to_commit().unwrap();
+
+                self.route(
+                    format!("/v1/projects/{}/commits/{}", urn, commit.id()),
+                    json!({
+                        "header": {
+                            "summary": commit.summary().unwrap_or_default(),
+                            "description": commit.body().unwrap_or_default(),
+                        }
+                    }),
+                );
+            }
+        }
+
+        let mut routes = self.routes.lock().unwrap();
+        let remotes = routes
+            .entry(format!("/v1/projects/{}/remotes", urn))
+            .or_insert_with(|| json!([]));
+        if let Some(remotes) = remotes.as_array_mut() {
+            let id = json!(peer);
+            if !remotes.iter().any(|r| r["id"] == id) {
+                remotes.push(json!(PeerInfo {
+                    id: peer,
+                    person: None,
+                    delegate: false,
+                }));
+            }
+        }
+        fetched
+    }
+
+    /// Respond to `GET` requests on the given path with the given JSON value.
+    pub fn route(&self, path: impl Into<String>, value: Value) {
+        self.routes.lock().unwrap().insert(path.into(), value);
+    }
+
+    /// Host a project, with the given remotes.
+    pub fn host(&self, urn: &Urn, name: &str, default_branch: &str, remotes: &[PeerInfo]) {
+        let project = json!({
+            "urn": urn.to_string(),
+            "name": name,
+            "description": "",
+            "defaultBranch": default_branch,
+        });
+        let mut routes = self.routes.lock().unwrap();
+        let projects = routes
+            .entry("/v1/projects".to_owned())
+            .or_insert_with(|| json!([]));
+
+        if let Some(projects) = projects.as_array_mut() {
+            projects.push(project.clone());
+        }
+        routes.insert(format!("/v1/projects/{}", urn), project);
+        routes.insert(format!("/v1/projects/{}/remotes", urn), json!(remotes));
+    }
+
+    /// Paths requested so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Seed {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Unblock the listener.
+        TcpStream::connect(self.addr).ok();
+
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+        fs::remove_dir_all(&self.git).ok();
+    }
+}
+
+/// Serve a single HTTP request.
+fn serve(
+    mut stream: TcpStream,
+    routes: &Routes,
+    requests: &Mutex<Vec<String>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+
+    reader.read_line(&mut line)?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let path = parts.next().unwrap_or_default().to_owned();
+
+    // Skip headers.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    requests.lock().unwrap().push(path.clone());
+
+    let body = if method == "GET" {
+        routes.lock().unwrap().get(&path).cloned()
+    } else {
+        None
+    };
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body.to_string()),
+        None => ("404 Not Found", json!({ "error": "not found" }).to_string()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::seed::SeedClient;
+    use crate::{project, test};
+
+    #[test]
+    fn test_request_pull() {
+        let (storage, _, _, project) = test::setup::profile();
+        let urn = project.urn();
+        let peer = *storage.peer_id();
+        let seed = Seed::spawn();
+
+        // Publish a branch.
+        let monorepo = git::Repository::open_bare(storage.path()).unwrap();
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = monorepo
+            .find_tree(monorepo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = monorepo
+            .commit(None, &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+        monorepo
+            .reference(
+                &format!("refs/namespaces/{}/refs/heads/master", urn.encode_id()),
+                oid,
+                true,
+                "",
+            )
+            .unwrap();
+
+        let fetched = seed.request_pull(storage.read_only(), &urn);
+        assert!(fetched.contains(&format!("refs/remotes/{}/rad/id", peer)));
+        assert!(fetched.contains(&format!("refs/remotes/{}/heads/master", peer)));
+
+        // The branch is listed by the git endpoint, as with `rad track --remote`.
+        let heads = project::list_seed_heads(&monorepo, &seed.git_url(), &urn).unwrap();
+        assert_eq!(heads[&peer], vec![("master".to_owned(), oid)]);
+
+        // The requester is a remote of the project, and its commits are served.
+        let client = SeedClient::from_url(seed.url());
+        assert_eq!(client.remotes(&urn).unwrap()[0].id, peer);
+        assert_eq!(
+            client.commit(&urn, &oid).unwrap().header.summary,
+            "Initial commit"
+        );
+        assert_eq!(
+            seed.requests().last(),
+            Some(&format!("/v1/projects/{}/commits/{}", urn, oid))
+        );
+
+        // Pulling again doesn't list the requester twice.
+        seed.request_pull(storage.read_only(), &urn);
+        assert_eq!(client.remotes(&urn).unwrap().len(), 1);
+    }
+}
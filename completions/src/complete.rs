@@ -0,0 +1,140 @@
+//! Dynamic completion of command arguments, used by the completion scripts via the
+//! hidden `rad __complete` command.
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fmt;
+
+use anyhow::anyhow;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::cobs::{issue, patch};
+use radicle_common::profile::Profile;
+use radicle_common::{git, profile, project};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "__complete",
+    description: "List the values of a command's arguments, for shell completion",
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad __complete <command> [<prefix>]
+
+    Lists the values the arguments of the given command can take, one per line,
+    eg. the ids of the current project's patches for `patch`. If a prefix is given,
+    only the values starting with it are listed.
+
+Options
+
+    --help    Print help
+"#,
+};
+
+/// Commands whose arguments are completed dynamically.
+pub const COMMANDS: &[&str] = &[
+    "comment", "issue", "merge", "patch", "profile", "remote", "review", "track", "untrack",
+];
+
+#[derive(Debug)]
+pub struct Options {
+    pub command: String,
+    pub prefix: String,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut command: Option<String> = None;
+        let mut prefix: Option<String> = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Value(val) if command.is_none() => {
+                    command = Some(val.to_string_lossy().to_string());
+                }
+                Value(val) if prefix.is_none() => {
+                    prefix = Some(val.to_string_lossy().to_string());
+                }
+                _ => return Err(anyhow!(arg.unexpected())),
+            }
+        }
+
+        Ok((
+            Options {
+                command: command.ok_or_else(|| anyhow!("a command must be specified"))?,
+                prefix: prefix.unwrap_or_default(),
+            },
+            vec![],
+        ))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let values = match options.command.as_str() {
+        "comment" | "merge" | "patch" | "review" => cobs(&ctx.profile()?, &*patch::TYPENAME)?,
+        "issue" => cobs(&ctx.profile()?, &*issue::TYPENAME)?,
+        "track" | "untrack" => peers(&ctx.profile()?)?,
+        "remote" => remotes()?,
+        "profile" => profile::list()?
+            .iter()
+            .map(|p| p.id().to_string())
+            .collect(),
+        _ => BTreeSet::new(),
+    };
+
+    for value in values.iter().filter(|v| v.starts_with(&options.prefix)) {
+        term::print(value);
+    }
+    Ok(())
+}
+
+/// Ids of the current project's collaborative objects of the given type, ours and our
+/// tracked peers'. They are read from the refs of the monorepo, so that no signer is
+/// needed.
+fn cobs(profile: &Profile, typename: impl fmt::Display) -> anyhow::Result<BTreeSet<String>> {
+    let (urn, _) = project::cwd()?;
+    let repo = git::Repository::open_bare(profile.paths().git_dir())?;
+    let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+    let mut ids = BTreeSet::new();
+
+    for glob in [
+        format!("{}/cobs/{}/*", namespace, typename),
+        format!("{}/remotes/*/cobs/{}/*", namespace, typename),
+    ] {
+        for r in repo.references_glob(&glob)?.flatten() {
+            if let Some(id) = r.name().and_then(|name| name.rsplit('/').next()) {
+                ids.insert(id.to_owned());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Peers tracked in the current project.
+fn peers(profile: &Profile) -> anyhow::Result<BTreeSet<String>> {
+    let (urn, _) = project::cwd()?;
+    let storage = profile::read_only(profile)?;
+    let project = project::get(&storage, &urn)?
+        .ok_or_else(|| anyhow!("project {} not found in local storage", urn))?;
+
+    Ok(project::tracked(&project, &storage)?
+        .into_keys()
+        .map(|peer| peer.to_string())
+        .collect())
+}
+
+/// Radicle remotes of the current working copy.
+fn remotes() -> anyhow::Result<BTreeSet<String>> {
+    let repo = git::repository()?;
+
+    Ok(git::remotes(&repo)?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect())
+}
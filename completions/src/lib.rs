@@ -0,0 +1,453 @@
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_terminal as term;
+
+pub mod complete;
+
+pub const HELP: Help = Help {
+    name: "completions",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad completions <bash | zsh | fish>
+
+    Prints the completion script of the given shell. Commands, their operations and
+    options are completed, as well as the patch and issue ids, tracked peers and
+    remote names of the current project, and profile ids.
+
+Examples
+
+    rad completions bash > ~/.local/share/bash-completion/completions/rad
+    rad completions zsh > ~/.zfunc/_rad
+    rad completions fish > ~/.config/fish/completions/rad.fish
+
+Options
+
+    --help    Print help
+"#,
+};
+
+/// Commands of `rad`, completed after its global options.
+const COMMANDS: &[Help] = &[
+    #[cfg(feature = "ethereum")]
+    rad_help::rad_account::HELP,
+    rad_help::rad_auth::HELP,
+    rad_help::rad_checkout::HELP,
+    rad_help::rad_clone::HELP,
+    rad_help::rad_cob::HELP,
+    rad_help::rad_comment::HELP,
+    crate::HELP,
+    rad_help::rad_delegate::HELP,
+    rad_help::rad_edit::HELP,
+    #[cfg(feature = "ethereum")]
+    rad_help::rad_ens::HELP,
+    #[cfg(feature = "ethereum")]
+    rad_help::rad_gov::HELP,
+    rad_help::HELP,
+    rad_help::rad_identity::HELP,
+    rad_help::rad_init::HELP,
+    rad_help::rad_inspect::HELP,
+    rad_help::rad_issue::HELP,
+    rad_help::rad_ls::HELP,
+    rad_help::rad_merge::HELP,
+    rad_help::rad_patch::HELP,
+    rad_help::rad_path::HELP,
+    rad_help::rad_profile::HELP,
+    rad_help::rad_pull::HELP,
+    rad_help::rad_push::HELP,
+    rad_help::rad_remote::HELP,
+    rad_help::rad_review::HELP,
+    rad_help::rad_rm::HELP,
+    rad_help::rad_seed::HELP,
+    rad_help::rad_self::HELP,
+    rad_help::rad_sync::HELP,
+    rad_help::rad_track::HELP,
+    rad_help::rad_untrack::HELP,
+    rad_help::rad_verify::HELP,
+];
+
+/// Options of `rad` itself, given before the command.
+const GLOBAL_OPTIONS: &[&str] = &["--profile", "--no-prompt", "--yes", "--help", "--version"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            _ => Err(anyhow!("unsupported shell '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub shell: Shell,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut shell: Option<Shell> = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Value(val) if shell.is_none() => {
+                    shell = Some(val.to_string_lossy().parse()?);
+                }
+                _ => return Err(anyhow!(arg.unexpected())),
+            }
+        }
+
+        Ok((
+            Options {
+                shell: shell.ok_or_else(|| anyhow!("a shell must be specified"))?,
+            },
+            vec![],
+        ))
+    }
+}
+
+pub fn run(options: Options, _ctx: impl term::Context) -> anyhow::Result<()> {
+    let specs = COMMANDS.iter().map(Spec::new).collect::<Vec<_>>();
+    let script = match options.shell {
+        Shell::Bash => bash(&specs),
+        Shell::Zsh => zsh(&specs),
+        Shell::Fish => fish(&specs),
+    };
+    term::print(script);
+
+    Ok(())
+}
+
+/// What can be completed for a command, extracted from its usage.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Spec {
+    /// Name of the command.
+    pub name: &'static str,
+    /// Operations of the command, eg. `ls` in `rad seed ls`.
+    pub operations: Vec<String>,
+    /// Long and short options of the command.
+    pub options: Vec<String>,
+}
+
+impl Spec {
+    /// Extract the operations and options of a command from its usage. Operations are
+    /// found in the usage lines, eg. `rad seed ls`, and options in the usage lines and
+    /// in the lines listing options.
+    pub fn new(help: &Help) -> Self {
+        let usage = format!("rad {} ", help.name);
+        let mut spec = Spec {
+            name: help.name,
+            ..Spec::default()
+        };
+
+        for line in help.usage.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix(&usage) {
+                if let Some(op) = rest.split_whitespace().next().filter(|w| is_name(w)) {
+                    push(&mut spec.operations, op.to_owned());
+                }
+            } else if !line.starts_with('-') {
+                continue;
+            }
+            for token in line.split(|c: char| c.is_whitespace() || c == ',' || c == '|') {
+                for option in options(token) {
+                    push(&mut spec.options, option);
+                }
+            }
+        }
+        push(&mut spec.options, String::from("--help"));
+
+        spec
+    }
+
+    /// Whether the arguments of the command are completed with `rad __complete`.
+    pub fn is_dynamic(&self) -> bool {
+        complete::COMMANDS.contains(&self.name)
+    }
+
+    /// Operations and options of the command.
+    fn words(&self) -> Vec<&str> {
+        self.operations
+            .iter()
+            .chain(self.options.iter())
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+fn push(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Options in a token of a usage line, eg. `[--title`, `-u` or `--[no-]sync`.
+fn options(token: &str) -> Vec<String> {
+    let token = token.trim_matches(|c| matches!(c, '[' | ']' | '(' | ')' | '`' | '.'));
+    let token = token.split('=').next().unwrap_or_default();
+
+    if let Some(name) = token.strip_prefix("--[no-]") {
+        if is_name(name) {
+            return vec![format!("--{}", name), format!("--no-{}", name)];
+        }
+    } else if let Some(name) = token.strip_prefix("--") {
+        if is_name(name) {
+            return vec![token.to_owned()];
+        }
+    } else if let Some(name) = token.strip_prefix('-') {
+        if name.len() == 1 && is_name(name) {
+            return vec![token.to_owned()];
+        }
+    }
+    vec![]
+}
+
+/// Words completed in place of the command.
+fn commands(specs: &[Spec]) -> String {
+    specs
+        .iter()
+        .map(|s| s.name)
+        .chain(GLOBAL_OPTIONS.iter().copied())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const BASH: &str = r#"# Completion of `rad`, generated with `rad completions bash`.
+
+_rad() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" cmd="" words="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            --profile) ((i++)) ;;
+            -*) ;;
+            *) cmd="${COMP_WORDS[i]}"; break ;;
+        esac
+    done
+    if [[ -z "$cmd" && "${COMP_WORDS[COMP_CWORD-1]}" == "--profile" ]]; then
+        words="$(rad __complete profile "$cur" 2>/dev/null)"
+    else
+        case "$cmd" in
+{cases}
+        esac
+    fi
+    COMPREPLY=($(compgen -W "$words" -- "$cur"))
+}
+
+complete -F _rad rad
+"#;
+
+fn bash(specs: &[Spec]) -> String {
+    let mut cases = vec![format!(
+        "            \"\") words=\"{}\" ;;",
+        commands(specs)
+    )];
+
+    for spec in specs {
+        let mut words = spec.words().join(" ");
+        if spec.is_dynamic() {
+            words.push_str(&format!(
+                " $(rad __complete {} \"$cur\" 2>/dev/null)",
+                spec.name
+            ));
+        }
+        cases.push(format!("            {}) words=\"{}\" ;;", spec.name, words));
+    }
+    BASH.replace("{cases}", &cases.join("\n"))
+}
+
+const ZSH: &str = r#"#compdef rad
+# Completion of `rad`, generated with `rad completions zsh`.
+
+_rad() {
+    local cmd="" i
+    local -a candidates
+    for ((i = 2; i < CURRENT; i++)); do
+        case "${words[i]}" in
+            --profile) ((i++)) ;;
+            -*) ;;
+            *) cmd="${words[i]}"; break ;;
+        esac
+    done
+    if [[ -z "$cmd" && "${words[CURRENT-1]}" == "--profile" ]]; then
+        candidates=(${${(f)"$(rad __complete profile "${words[CURRENT]}" 2>/dev/null)"}:#})
+    else
+        case "$cmd" in
+{cases}
+        esac
+    fi
+    compadd -a candidates
+}
+
+if [[ "$funcstack[1]" == "_rad" ]]; then
+    _rad "$@"
+else
+    compdef _rad rad
+fi
+"#;
+
+fn zsh(specs: &[Spec]) -> String {
+    let mut cases = vec![format!(
+        "            \"\") candidates=({}) ;;",
+        commands(specs)
+    )];
+
+    for spec in specs {
+        let mut words = spec.words().join(" ");
+        if spec.is_dynamic() {
+            words.push_str(&format!(
+                " ${{${{(f)\"$(rad __complete {} \"${{words[CURRENT]}}\" 2>/dev/null)\"}}:#}}",
+                spec.name
+            ));
+        }
+        cases.push(format!(
+            "            {}) candidates=({}) ;;",
+            spec.name, words
+        ));
+    }
+    ZSH.replace("{cases}", &cases.join("\n"))
+}
+
+fn fish(specs: &[Spec]) -> String {
+    let mut lines = vec![
+        String::from("# Completion of `rad`, generated with `rad completions fish`."),
+        String::new(),
+        String::from("complete -c rad -f"),
+        format!(
+            "complete -c rad -n __fish_use_subcommand -a \"{}\"",
+            specs.iter().map(|s| s.name).collect::<Vec<_>>().join(" ")
+        ),
+        String::from(
+            "complete -c rad -n __fish_use_subcommand -l profile -x \
+             -a \"(rad __complete profile (commandline -ct) 2>/dev/null)\"",
+        ),
+    ];
+    for option in GLOBAL_OPTIONS.iter().filter(|o| **o != "--profile") {
+        lines.push(format!(
+            "complete -c rad -n __fish_use_subcommand {}",
+            fish_option(option)
+        ));
+    }
+
+    for spec in specs {
+        let condition = format!("-n \"__fish_seen_subcommand_from {}\"", spec.name);
+
+        if !spec.operations.is_empty() {
+            lines.push(format!(
+                "complete -c rad {} -a \"{}\"",
+                condition,
+                spec.operations.join(" ")
+            ));
+        }
+        for option in &spec.options {
+            lines.push(format!(
+                "complete -c rad {} {}",
+                condition,
+                fish_option(option)
+            ));
+        }
+        if spec.is_dynamic() {
+            lines.push(format!(
+                "complete -c rad {} -a \"(rad __complete {} (commandline -ct) 2>/dev/null)\"",
+                condition, spec.name
+            ));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn fish_option(option: &str) -> String {
+    match option.strip_prefix("--") {
+        Some(long) => format!("-l {}", long),
+        None => format!("-s {}", option.trim_start_matches('-')),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const USAGE: &str = r#"
+Usage
+
+    rad thing ls
+    rad thing add <name> [--fetch | -f] [--[no-]sync]
+    rad thing rm <name | peer-id>
+    rad thing [<option>...]
+
+    Things are added with `--fetch` unless told otherwise, see `rad --help`.
+
+Options
+
+    -v, --verbose       Verbose output
+    --seed <address>    Seed to use (default: none)
+    --help              Print help
+"#;
+
+    #[test]
+    fn test_spec() {
+        let spec = Spec::new(&Help {
+            name: "thing",
+            description: "",
+            version: "",
+            usage: USAGE,
+        });
+
+        assert_eq!(spec.name, "thing");
+        assert_eq!(spec.operations, vec!["ls", "add", "rm"]);
+        assert_eq!(
+            spec.options,
+            vec![
+                "--fetch",
+                "-f",
+                "--sync",
+                "--no-sync",
+                "-v",
+                "--verbose",
+                "--seed",
+                "--help"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scripts() {
+        let specs = COMMANDS.iter().map(Spec::new).collect::<Vec<_>>();
+        let patch = specs.iter().find(|s| s.name == "patch").unwrap();
+
+        assert!(patch.is_dynamic());
+        assert!(patch.options.contains(&String::from("--no-sync")));
+
+        for script in [bash(&specs), zsh(&specs), fish(&specs)] {
+            assert!(script.contains("rad __complete patch"));
+            assert!(script.contains("completions"));
+            assert!(!script.contains("{cases}"));
+        }
+    }
+}
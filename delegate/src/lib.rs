@@ -0,0 +1,302 @@
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use either::Either;
+
+use librad::git::identities::Project;
+use librad::PeerId;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::delegation::{self, Delegation, Pending, Status};
+use radicle_common::project::{self, Delegate};
+use radicle_common::{keys, profile, Urn};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "delegate",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad delegate ls [--project <urn>]
+    rad delegate add <peer-id | person-urn> [--project <urn>]
+    rad delegate rm <peer-id | person-urn> [--project <urn>]
+    rad delegate accept <peer-id> [--project <urn>]
+
+    Manages the delegates of a project, ie. the keys or persons allowed to update its
+    identity. If no project is specified, the project in the current working directory
+    is used.
+
+    Adding or removing a delegate updates the project identity, signed by you. The update
+    only takes effect once signed by a quorum of the current delegates, ie. more than
+    half of them. Until then, it is pending, and is listed by `rad delegate ls`, along
+    with the delegates that still need to sign it.
+
+    To sign an update proposed by another delegate, fetch it with `rad sync`, then run
+    `rad delegate accept` with the peer id of the delegate who proposed it.
+
+Options
+
+    --project <urn>    Project to manage the delegates of
+    --help             Print help
+"#,
+};
+
+#[derive(Debug)]
+pub enum Operation {
+    List,
+    Add { delegate: Delegation },
+    Remove { delegate: Delegation },
+    Accept { peer: PeerId },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OperationName {
+    List,
+    Add,
+    Remove,
+    Accept,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub op: Operation,
+    pub urn: Option<Urn>,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut op: Option<OperationName> = None;
+        let mut delegate: Option<String> = None;
+        let mut urn: Option<Urn> = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Long("project") => {
+                    let value = parser.value()?;
+                    let value = value.to_string_lossy();
+
+                    urn = Some(
+                        Urn::from_str(&value).map_err(|_| anyhow!("invalid URN '{}'", value))?,
+                    );
+                }
+                Value(val) if op.is_none() => match val.to_string_lossy().as_ref() {
+                    "ls" | "list" => op = Some(OperationName::List),
+                    "add" => op = Some(OperationName::Add),
+                    "rm" => op = Some(OperationName::Remove),
+                    "accept" => op = Some(OperationName::Accept),
+
+                    unknown => anyhow::bail!("unknown operation '{}'", unknown),
+                },
+                Value(val) if delegate.is_none() => {
+                    delegate = Some(val.to_string_lossy().to_string());
+                }
+                _ => {
+                    return Err(anyhow!(arg.unexpected()));
+                }
+            }
+        }
+
+        let op = match op.unwrap_or(OperationName::List) {
+            OperationName::List => Operation::List,
+            OperationName::Add => Operation::Add {
+                delegate: parse_delegate(
+                    &delegate.ok_or_else(|| anyhow!("a delegate must be specified"))?,
+                )?,
+            },
+            OperationName::Remove => Operation::Remove {
+                delegate: parse_delegate(
+                    &delegate.ok_or_else(|| anyhow!("a delegate must be specified"))?,
+                )?,
+            },
+            OperationName::Accept => {
+                let peer = delegate.ok_or_else(|| anyhow!("a peer id must be specified"))?;
+                let peer =
+                    PeerId::from_str(&peer).map_err(|_| anyhow!("invalid peer id '{}'", peer))?;
+
+                Operation::Accept { peer }
+            }
+        };
+
+        Ok((Options { op, urn }, vec![]))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let urn = match options.urn {
+        Some(urn) => urn,
+        None => project::cwd().map(|(urn, _)| urn)?,
+    };
+
+    match options.op {
+        Operation::List => {
+            let storage = profile::read_only(&profile)?;
+            let meta = project::get(&storage, &urn)?
+                .ok_or_else(|| anyhow!("project {} not found in local storage", urn))?;
+
+            let mut table = term::Table::default();
+            for delegate in &meta.delegates {
+                let (id, name) = match delegate {
+                    Delegate::Direct { id } => (
+                        id.default_encoding(),
+                        project::PeerInfo::get(id, &meta, &storage).name(),
+                    ),
+                    Delegate::Indirect { urn, ids } => (
+                        urn.to_string(),
+                        ids.iter()
+                            .next()
+                            .map(|id| project::PeerInfo::get(id, &meta, &storage).name())
+                            .unwrap_or_default(),
+                    ),
+                };
+                table.push([term::format::tertiary(id), term::format::bold(name)]);
+            }
+            term::info!(
+                "Delegates of {} {}",
+                term::format::highlight(&meta.name),
+                term::format::dim(format!(
+                    "(quorum {} of {})",
+                    meta.delegates.len() / 2 + 1,
+                    meta.delegates.len()
+                ))
+            );
+            table.render();
+
+            let pending = delegation::pending(&storage, &urn)?;
+            if !pending.is_empty() {
+                term::blank();
+                term::info!("Pending updates");
+
+                for p in &pending {
+                    print_pending(p, storage.peer_id());
+                }
+            }
+        }
+        Operation::Add { delegate } => {
+            let signer = term::signer(&profile)?;
+            let storage = keys::storage(&profile, signer)?;
+            let project = delegation::add(&storage, &urn, delegate.clone())?;
+
+            term::success!(
+                "Delegate {} added",
+                term::format::highlight(display(&delegate))
+            );
+            print_status(&project, storage.peer_id());
+        }
+        Operation::Remove { delegate } => {
+            let signer = term::signer(&profile)?;
+            let storage = keys::storage(&profile, signer)?;
+            let project = delegation::remove(&storage, &urn, delegate.clone())?;
+
+            term::success!(
+                "Delegate {} removed",
+                term::format::highlight(display(&delegate))
+            );
+            print_status(&project, storage.peer_id());
+        }
+        Operation::Accept { peer } => {
+            let signer = term::signer(&profile)?;
+            let storage = keys::storage(&profile, signer)?;
+            let project = delegation::accept(&storage, &urn, peer)?;
+
+            term::success!(
+                "Signed update {} proposed by {}",
+                term::format::dim(revision(&project)),
+                term::format::tertiary(peer)
+            );
+            print_status(&project, storage.peer_id());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the signature status of an update we signed.
+fn print_status(project: &Project, local: &PeerId) {
+    let status = Status::of(project);
+
+    if status.is_quorum() {
+        term::success!("Update signed by a quorum of delegates, and now in effect");
+    } else {
+        term::info!(
+            "Update is pending, with {} of {} required signature(s)",
+            status.signed,
+            status.quorum
+        );
+        term::tip!(
+            "Other delegates must sync and run `rad delegate accept {}`",
+            local
+        );
+    }
+    term::tip!("To publish the update, run `rad sync`");
+}
+
+fn print_pending(pending: &Pending, local: &PeerId) {
+    let proposer = match &pending.peer {
+        Some(peer) => term::format::tertiary(peer),
+        None => term::format::dim("you"),
+    };
+    term::indented(&format!(
+        "{} proposed by {} {}",
+        term::format::bold(revision(&pending.project)),
+        proposer,
+        term::format::dim(format!(
+            "({} of {} required signature(s))",
+            pending.status.signed, pending.status.quorum
+        ))
+    ));
+
+    let mut awaiting_us = false;
+    for delegate in &pending.status.missing {
+        if delegate.contains(local) {
+            awaiting_us = true;
+        }
+        let id = match delegate {
+            Delegate::Direct { id } => id.default_encoding(),
+            Delegate::Indirect { urn, .. } => urn.to_string(),
+        };
+        term::indented(&format!(
+            "  {} {}",
+            term::format::dim("awaiting"),
+            term::format::tertiary(id)
+        ));
+    }
+    if let (true, Some(peer)) = (awaiting_us, &pending.peer) {
+        term::tip!("To sign it, run `rad delegate accept {}`", peer);
+    }
+}
+
+/// Parse a delegate, either a peer id or a person URN.
+fn parse_delegate(s: &str) -> anyhow::Result<Delegation> {
+    if let Ok(peer) = PeerId::from_str(s) {
+        Ok(Either::Left(*peer.as_public_key()))
+    } else if let Ok(urn) = Urn::from_str(s) {
+        Ok(Either::Right(urn))
+    } else {
+        Err(anyhow!(
+            "invalid delegate '{}', expected a peer id or URN",
+            s
+        ))
+    }
+}
+
+/// Short form of a project identity revision.
+fn revision(project: &Project) -> String {
+    format!("{:.7}", project.revision.to_string())
+}
+
+fn display(delegate: &Delegation) -> String {
+    match delegate {
+        Either::Left(pk) => PeerId::from(*pk).default_encoding(),
+        Either::Right(urn) => urn.to_string(),
+    }
+}
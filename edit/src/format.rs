@@ -0,0 +1,232 @@
+//! Formats identity documents can be edited in.
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Number of lines of an error annotation.
+const ANNOTATION_LINES: usize = 3;
+
+/// Document format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => write!(f, "JSON"),
+            Self::Toml => write!(f, "TOML"),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "toml" => Ok(Self::Toml),
+            _ => Err(anyhow!("unknown format '{}', expected 'json' or 'toml'", s)),
+        }
+    }
+}
+
+/// Error parsing a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// Location of the error in the document, as a one-based line and column.
+    pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, col)) => write!(f, "{} (line {}, column {})", self.message, line, col),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl Format {
+    /// File extension, so that editors pick the right syntax.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => ".json",
+            Self::Toml => ".toml",
+        }
+    }
+
+    /// Prefix of the lines used to annotate a document.
+    fn comment(&self) -> &'static str {
+        match self {
+            Self::Json => "//",
+            Self::Toml => "#",
+        }
+    }
+
+    /// Serialize a value into a document.
+    pub fn serialize<T: Serialize>(&self, value: &T) -> anyhow::Result<String> {
+        let value = serde_json::to_value(value)?;
+
+        match self {
+            Self::Json => Ok(serde_json::to_string_pretty(&value)?),
+            // Nb. TOML has no null, so unset fields are left out. Going through a TOML
+            // value makes sure tables are emitted after plain values.
+            Self::Toml => Ok(toml::to_string_pretty(&toml::Value::try_from(
+                strip_nulls(value),
+            )?)?),
+        }
+    }
+
+    /// Deserialize a document, ignoring its annotation, if any.
+    pub fn deserialize<T: DeserializeOwned>(&self, doc: &str) -> Result<T, ParseError> {
+        let doc = self.strip(doc);
+        let value = match self {
+            Self::Json => serde_json::from_str::<Value>(&doc).map_err(|e| ParseError {
+                location: Some((e.line(), e.column())),
+                message: without_location(e.to_string()),
+            })?,
+            Self::Toml => {
+                let value = toml::from_str::<toml::Value>(&doc).map_err(|e| ParseError {
+                    location: e.line_col().map(|(l, c)| (l + 1, c + 1)),
+                    message: without_location(e.to_string()),
+                })?;
+                serde_json::to_value(value).map_err(|e| ParseError {
+                    message: e.to_string(),
+                    location: None,
+                })?
+            }
+        };
+
+        serde_json::from_value(value).map_err(|e| ParseError {
+            message: e.to_string(),
+            location: None,
+        })
+    }
+
+    /// Annotate a document with an error. Any previous annotation is replaced, and the
+    /// location of the error is adjusted to the annotated document.
+    pub fn annotate(&self, doc: &str, err: &ParseError) -> String {
+        let err = ParseError {
+            message: err.message.clone(),
+            location: err
+                .location
+                .map(|(line, col)| (line + ANNOTATION_LINES, col)),
+        };
+        format!(
+            "{c} error: {}\n{c} Fix the document and save to try again, or quit without saving to abort.\n\n{}",
+            err,
+            self.strip(doc),
+            c = self.comment(),
+        )
+    }
+
+    /// Remove the annotation of a document, if any.
+    pub fn strip(&self, doc: &str) -> String {
+        let mut lines = doc.lines().peekable();
+        let mut stripped = false;
+
+        while let Some(line) = lines.peek() {
+            if line.starts_with(self.comment()) {
+                stripped = true;
+                lines.next();
+            } else {
+                break;
+            }
+        }
+        if stripped && lines.peek().map_or(false, |l| l.trim().is_empty()) {
+            lines.next();
+        }
+        lines.collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Remove the `at line .. column ..` suffix of parser errors.
+fn without_location(message: String) -> String {
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_owned(),
+        None => message,
+    }
+}
+
+/// Remove null values from a JSON value, recursively.
+fn strip_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .filter(|v| !v.is_null())
+                .map(strip_nulls)
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_roundtrip() {
+        let value = json!({
+            "https://radicle.xyz/link/identities/project/v1": {
+                "name": "nakamoto",
+                "description": null,
+                "default_branch": "master",
+            }
+        });
+
+        for format in [Format::Json, Format::Toml] {
+            let doc = format.serialize(&value).unwrap();
+            let parsed: Value = format.deserialize(&doc).unwrap();
+
+            assert_eq!(parsed, strip_nulls(value.clone()), "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_annotate() {
+        let doc = "{\n  \"name\": \"nakamoto\"\n  \"default_branch\": \"master\"\n}";
+
+        for format in [Format::Json, Format::Toml] {
+            let err = format.deserialize::<Value>(doc).unwrap_err();
+            let annotated = format.annotate(doc, &err);
+            let (line, _) = err.location.unwrap();
+
+            // The annotation is ignored when parsing, and the error location is shifted
+            // to match the annotated document.
+            assert_eq!(format.strip(&annotated), doc);
+            assert_eq!(
+                format
+                    .deserialize::<Value>(&annotated)
+                    .unwrap_err()
+                    .location,
+                err.location
+            );
+            assert!(annotated.starts_with(&format!("{} error:", format.comment())));
+            assert!(annotated.contains(&format!("line {}", line + ANNOTATION_LINES)));
+        }
+    }
+}
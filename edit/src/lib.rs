@@ -1,6 +1,11 @@
+mod format;
+
 use std::ffi::OsString;
 use std::str::FromStr;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
 use radicle_common::args::{Args, Error, Help};
 use radicle_common::keys;
 use radicle_terminal as term;
@@ -12,6 +17,8 @@ use link_identities::payload::{PersonPayload, ProjectPayload};
 
 use anyhow::anyhow;
 
+pub use format::Format;
+
 pub const HELP: Help = Help {
     name: "edit",
     description: env!("CARGO_PKG_DESCRIPTION"),
@@ -24,8 +31,13 @@ Usage
     Edits the identity document pointed to by the URN. If it isn't specified,
     the current project is edited.
 
+    The document is opened in your editor, as JSON, or as TOML with `--format toml`.
+    If the edited document is invalid, the editor is re-opened with the error at the
+    top. Before the identity is updated, the changes are shown for confirmation.
+
 Options
 
+    --format <format>   Format to edit the document in: `json` (default) or `toml`
     --help              Print help
 "#,
 };
@@ -33,6 +45,7 @@ Options
 #[derive(Default, Debug, Eq, PartialEq)]
 pub struct Options {
     pub urn: Option<Urn>,
+    pub format: Format,
 }
 
 impl Args for Options {
@@ -41,9 +54,14 @@ impl Args for Options {
 
         let mut parser = lexopt::Parser::from_args(args);
         let mut urn: Option<Urn> = None;
+        let mut format = Format::default();
 
         while let Some(arg) = parser.next()? {
             match arg {
+                Long("format") => {
+                    let value = parser.value()?;
+                    format = Format::from_str(&value.to_string_lossy())?;
+                }
                 Long("help") => {
                     return Err(Error::Help.into());
                 }
@@ -60,7 +78,7 @@ impl Args for Options {
             }
         }
 
-        Ok((Options { urn }, vec![]))
+        Ok((Options { urn, format }, vec![]))
     }
 }
 
@@ -79,28 +97,24 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
 
     match identity {
         SomeIdentity::Project(_) => {
-            let payload = serde_json::to_string_pretty(
-                project::verify(&storage, &urn)?
-                    .ok_or_else(|| anyhow!("Couldn't get project's identity doc"))?
-                    .payload(),
-            )?;
-            match term::Editor::new().edit(&payload)? {
-                Some(updated_payload) => {
-                    let payload: ProjectPayload = serde_json::from_str(&updated_payload)?;
+            let payload = project::verify(&storage, &urn)?
+                .ok_or_else(|| anyhow!("Couldn't get project's identity doc"))?
+                .payload()
+                .clone();
+            match edit::<ProjectPayload>(&payload, options.format)? {
+                Some(payload) => {
                     project::update(&storage, &urn, None, payload, None)?;
                 }
                 None => return Err(anyhow!("Operation aborted!")),
             }
         }
         SomeIdentity::Person(_) => {
-            let payload = serde_json::to_string_pretty(
-                person::verify(&storage, &urn)?
-                    .ok_or_else(|| anyhow!("Couldn't get person's identity doc"))?
-                    .payload(),
-            )?;
-            match term::Editor::new().edit(&payload)? {
-                Some(updated_payload) => {
-                    let payload: PersonPayload = serde_json::from_str(&updated_payload)?;
+            let payload = person::verify(&storage, &urn)?
+                .ok_or_else(|| anyhow!("Couldn't get person's identity doc"))?
+                .payload()
+                .clone();
+            match edit::<PersonPayload>(&payload, options.format)? {
+                Some(payload) => {
                     person::update(&storage, &urn, None, payload, None)?;
                 }
                 None => return Err(anyhow!("Operation aborted!")),
@@ -115,3 +129,126 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Edit a document in the given format, until it is valid or the user gives up. The
+/// changes are shown for confirmation. Returns `None` if the edit was aborted, or if
+/// nothing changed.
+fn edit<T>(value: &T, format: Format) -> anyhow::Result<Option<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let original = format.serialize(value)?;
+    let mut doc = original.clone();
+
+    loop {
+        let edited = match term::Editor::new()
+            .extension(format.extension())
+            .edit(&doc)?
+        {
+            Some(edited) => edited,
+            None => return Ok(None),
+        };
+
+        match format.deserialize::<T>(&edited) {
+            Ok(updated) => {
+                let updated_doc = format.serialize(&updated)?;
+                let changes = diff(&original, &updated_doc);
+
+                if changes.iter().all(|c| matches!(c, Change::Same(_))) {
+                    term::info!("No changes made");
+                    return Ok(None);
+                }
+                print_diff(&changes);
+
+                if term::confirm("Update identity?")? {
+                    return Ok(Some(updated));
+                }
+                return Ok(None);
+            }
+            Err(err) => {
+                term::warning(&format!("Invalid {} document: {}", format, err));
+                doc = format.annotate(&edited, &err);
+            }
+        }
+    }
+}
+
+/// A line of a diff.
+#[derive(Debug, PartialEq, Eq)]
+enum Change<'a> {
+    Same(&'a str),
+    Added(&'a str),
+    Removed(&'a str),
+}
+
+/// Line diff of two documents, based on their longest common subsequence. Identity
+/// documents are small, so the quadratic cost is fine.
+fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(Change::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().map(|l| Change::Removed(l)));
+    changes.extend(new[j..].iter().map(|l| Change::Added(l)));
+    changes
+}
+
+fn print_diff(changes: &[Change]) {
+    term::blank();
+    for change in changes {
+        match change {
+            Change::Same(line) => term::indented(&term::format::dim(format!("  {}", line))),
+            Change::Added(line) => term::indented(&term::format::positive(format!("+ {}", line))),
+            Change::Removed(line) => term::indented(&term::format::negative(format!("- {}", line))),
+        }
+    }
+    term::blank();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = "{\n  \"name\": \"nakamoto\",\n  \"default_branch\": \"master\"\n}";
+        let new = "{\n  \"name\": \"satoshi\",\n  \"default_branch\": \"master\"\n}";
+
+        assert_eq!(
+            diff(old, new),
+            vec![
+                Change::Same("{"),
+                Change::Removed("  \"name\": \"nakamoto\","),
+                Change::Added("  \"name\": \"satoshi\","),
+                Change::Same("  \"default_branch\": \"master\""),
+                Change::Same("}"),
+            ]
+        );
+        assert!(diff(old, old).iter().all(|c| matches!(c, Change::Same(_))));
+    }
+}
@@ -1,3 +1,4 @@
+use std::convert::TryInto;
 use std::ffi::OsString;
 
 use anyhow::anyhow;
@@ -169,7 +170,7 @@ fn set_ens_payload(name: &str, storage: &Storage) -> anyhow::Result<()> {
     if term::confirm(format!(
         "Associate local identity with ENS name {}?",
         term::format::highlight(&name)
-    )) {
+    ))? {
         let doc = person::set_ens_payload(
             person::Ens {
                 name: name.to_owned(),
@@ -215,9 +216,11 @@ async fn setup(
 
     let seed_host: String = term::text_input("Seed host", None)?;
     let seed_url = url::Url::parse(&format!("https://{}", seed_host))?;
+    let seed_addr: seed::Address = seed_url.try_into()?;
+    let seed_client = seed::SeedClient::new(&seed_addr);
 
     let spinner = term::spinner("Querying seed...");
-    let seed_id = match seed::get_seed_id(seed_url) {
+    let seed_id = match seed_client.peer() {
         Ok(id) => {
             spinner.clear();
             term::text_input("Seed ID", Some(id))?
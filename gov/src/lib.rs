@@ -276,6 +276,9 @@ where
     table.render();
     term::blank();
 
+    if !term::interactive() {
+        anyhow::bail!(term::no_prompt("vote", None));
+    }
     if let Some(vote) = term::select(&["approve", "reject"], &"approve") {
         let vote = *vote == "approve";
         let call = governance.cast_vote(id, vote)?;
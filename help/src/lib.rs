@@ -8,12 +8,15 @@ pub use rad_account;
 pub use rad_auth;
 pub use rad_checkout;
 pub use rad_clone;
+pub use rad_cob;
 pub use rad_comment;
+pub use rad_delegate;
 pub use rad_edit;
 #[cfg(feature = "ethereum")]
 pub use rad_ens;
 #[cfg(feature = "ethereum")]
 pub use rad_gov;
+pub use rad_identity;
 pub use rad_init;
 pub use rad_inspect;
 pub use rad_issue;
@@ -21,15 +24,18 @@ pub use rad_ls;
 pub use rad_merge;
 pub use rad_patch;
 pub use rad_path;
+pub use rad_profile;
 pub use rad_pull;
 pub use rad_push;
 pub use rad_remote;
 pub use rad_review;
 pub use rad_rm;
+pub use rad_seed;
 pub use rad_self;
 pub use rad_sync;
 pub use rad_track;
 pub use rad_untrack;
+pub use rad_verify;
 
 pub const HELP: Help = Help {
     name: "help",
@@ -52,12 +58,17 @@ const COMMANDS: &[Help] = &[
     rad_track::HELP,
     rad_untrack::HELP,
     rad_sync::HELP,
+    rad_seed::HELP,
+    rad_verify::HELP,
     #[cfg(feature = "ethereum")]
     rad_ens::HELP,
     #[cfg(feature = "ethereum")]
     rad_account::HELP,
     rad_rm::HELP,
     rad_edit::HELP,
+    rad_delegate::HELP,
+    rad_identity::HELP,
+    rad_profile::HELP,
     crate::HELP,
 ];
 
@@ -83,7 +94,7 @@ impl Args for Options {
 }
 
 pub fn run(_options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
-    println!("Usage: rad <command> [--help]");
+    println!("Usage: rad [--profile <id>] [--no-prompt | --yes] <command> [--help]");
 
     if ctx.profile().is_err() {
         println!();
@@ -111,6 +122,18 @@ pub fn run(_options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
     println!();
     println!("See `rad <command> --help` to learn about a specific command.");
     println!();
+    println!("With `--no-prompt`, commands don't prompt: prompts take their default, or fail");
+    println!("naming the option to use. This is also the case when the standard input isn't a");
+    println!("terminal. Confirmations have no default: they fail unless `--yes` is used, which");
+    println!("accepts them, and otherwise behaves like `--no-prompt`.");
+    println!();
+    println!("Aliases may be defined in the `[alias]` table of your profile's `config.toml`,");
+    println!("eg. `pl = \"patch --list\"`. Their arguments are split as by a shell, so that");
+    println!("quotes group words, eg. `lgtm = \"comment -m 'looks good'\"`.");
+    println!();
+    println!("Other commands are run as `rad-<command>` executables found in your PATH, with");
+    println!("the profile in use exported as `RAD_PROFILE`.");
+    println!();
 
     Ok(())
 }
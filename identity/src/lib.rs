@@ -0,0 +1,166 @@
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use chrono::prelude::*;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::identity::history::{self, Change, Revision};
+use radicle_common::{fmt, git, profile, project, Urn};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "identity",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad identity log [<urn>] [<option>...]
+
+    Shows the revisions of a project or person identity document, latest first. For
+    each revision, the delegates who signed it are shown, whether it was signed by a
+    quorum of delegates, and what changed from the previous revision: payload fields,
+    including extensions such as ENS names, and delegations.
+
+    If no URN is specified, the project in the current working directory is used.
+
+Options
+
+    --help    Print help
+"#,
+};
+
+#[derive(Debug)]
+pub enum Operation {
+    Log,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub op: Operation,
+    pub urn: Option<Urn>,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut op: Option<Operation> = None;
+        let mut urn: Option<Urn> = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Value(val) if op.is_none() => match val.to_string_lossy().as_ref() {
+                    "log" => op = Some(Operation::Log),
+
+                    unknown => anyhow::bail!("unknown operation '{}'", unknown),
+                },
+                Value(val) if urn.is_none() => {
+                    let val = val.to_string_lossy();
+                    urn = Some(Urn::from_str(&val).map_err(|_| anyhow!("invalid URN '{}'", val))?);
+                }
+                _ => return Err(anyhow!(arg.unexpected())),
+            }
+        }
+
+        Ok((
+            Options {
+                op: op.unwrap_or(Operation::Log),
+                urn,
+            },
+            vec![],
+        ))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let storage = profile::read_only(&profile)?;
+    let urn = match options.urn {
+        Some(urn) => urn,
+        None => project::cwd().map(|(urn, _)| urn)?,
+    };
+
+    match options.op {
+        Operation::Log => {
+            let revisions = history::history(&storage, &urn)?;
+
+            for (i, revision) in revisions.iter().enumerate() {
+                print_revision(revision, revisions.get(i + 1));
+            }
+            term::info!("{}", term::format::highlight(&urn));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_revision(revision: &Revision, parent: Option<&Revision>) {
+    let status = &revision.status;
+    let quorum = if status.is_quorum() {
+        term::format::positive(format!("quorum {}/{}", status.signed, status.quorum))
+    } else {
+        term::format::negative(format!("pending {}/{}", status.signed, status.quorum))
+    };
+
+    term::info!(
+        "{} {} {} {}",
+        term::format::yellow("revision"),
+        term::format::yellow(fmt::oid(&revision.commit)),
+        term::format::dim(time(&revision.time)),
+        quorum
+    );
+    for signer in &revision.signers {
+        term::indented(&format!(
+            "{} {}",
+            term::format::dim("signed by"),
+            term::format::tertiary(signer)
+        ));
+    }
+    for delegate in &status.missing {
+        term::indented(&format!(
+            "{} {}",
+            term::format::dim("awaiting"),
+            term::format::tertiary(delegate)
+        ));
+    }
+
+    match parent {
+        Some(parent) => {
+            for change in history::diff(parent, revision) {
+                let line = match &change {
+                    Change::Field { new: None, .. } | Change::DelegateRemoved(_) => {
+                        term::format::negative(format!("- {}", change))
+                    }
+                    Change::Field { old: None, .. } | Change::DelegateAdded(_) => {
+                        term::format::positive(format!("+ {}", change))
+                    }
+                    Change::Field { .. } => term::format::yellow(format!("~ {}", change)),
+                };
+                term::indented(&line);
+            }
+        }
+        None => {
+            for (name, value) in revision.fields() {
+                term::indented(&term::format::positive(format!("+ {}: {}", name, value)));
+            }
+            for delegate in &revision.delegates {
+                term::indented(&term::format::positive(format!("+ delegate: {}", delegate)));
+            }
+        }
+    }
+    term::blank();
+}
+
+fn time(time: &git::Time) -> String {
+    let offset = FixedOffset::east(time.offset_minutes() * 60);
+
+    Utc.timestamp(time.seconds(), 0)
+        .with_timezone(&offset)
+        .to_rfc2822()
+}
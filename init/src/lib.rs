@@ -166,20 +166,22 @@ pub fn init(options: Options, profile: &profile::Profile) -> anyhow::Result<()>
         .and_then(|head| head.shorthand().map(|h| h.to_owned()))
         .ok_or_else(|| anyhow!("error: repository head does not point to any commits"))?;
 
-    let name = options.name.unwrap_or_else(|| {
-        let default = path.file_name().map(|f| f.to_string_lossy().to_string());
-        term::text_input("Name", default).unwrap()
-    });
-    let description = options
-        .description
-        .unwrap_or_else(|| term::text_input("Description", None).unwrap());
-    let branch = options.branch.unwrap_or_else(|| {
-        if interactive.yes() {
-            term::text_input("Default branch", Some(head)).unwrap()
-        } else {
-            head
+    let name = match options.name {
+        Some(name) => name,
+        None => {
+            let default = path.file_name().map(|f| f.to_string_lossy().to_string());
+            term::text_input_for("--name", "Name", default)?
         }
-    });
+    };
+    let description = match options.description {
+        Some(description) => description,
+        None => term::text_input_for("--description", "Description", None)?,
+    };
+    let branch = match options.branch {
+        Some(branch) => branch,
+        None if interactive.yes() => term::text_input("Default branch", Some(head))?,
+        None => head,
+    };
 
     let mut spinner = term::spinner("Initializing...");
     let payload = project::payload(name, description, branch.clone());
@@ -270,7 +272,7 @@ pub fn setup_signing(
         term::confirm(&format!(
             "Configure 🌱 signing key {} in local checkout?",
             term::format::tertiary(key),
-        ))
+        ))?
     } else {
         true
     };
@@ -292,7 +294,9 @@ pub fn setup_signing(
 
                 if ssh_keys.contains(&ssh_key) {
                     term::success!("Signing key is already in {} file", gitsigners);
-                } else if term::confirm(&format!("Add signing key to {}?", gitsigners)) {
+                } else if interactive.no()
+                    || term::confirm(&format!("Add signing key to {}?", gitsigners))?
+                {
                     git::add_gitsigners(repo, [peer_id])?;
                 }
             }
@@ -189,6 +189,14 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
             }
         }
         Operation::Create { title, description } => {
+            if !term::interactive() {
+                let title = title.ok_or_else(|| term::no_prompt("title", Some("--title")))?;
+                let description = description.unwrap_or_default();
+
+                issues.create(&project, &title, description.trim(), &[])?;
+
+                return Ok(());
+            }
             let meta = Metadata {
                 title: title.unwrap_or("Enter a title".to_owned()),
                 labels: vec![],
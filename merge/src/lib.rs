@@ -212,7 +212,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
         merge_style_pretty
     );
 
-    if options.interactive && !term::confirm("Confirm?") {
+    if options.interactive && !term::confirm("Confirm?")? {
         anyhow::bail!("merge aborted by user");
     }
 
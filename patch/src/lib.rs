@@ -260,6 +260,11 @@ fn update(
     options: Options,
     profile: &Profile,
 ) -> anyhow::Result<()> {
+    let patch = if patch.is_conflicted() {
+        resolve(patch, patch_id, patches, project)?
+    } else {
+        patch
+    };
     let (current, current_revision) = patch.latest();
 
     if &*current_revision.oid == head {
@@ -272,7 +277,7 @@ fn update(
         term::format::tertiary(common::fmt::cob(&patch_id)),
         term::format::dim(format!("R{}", current)),
         term::format::secondary(common::fmt::oid(&current_revision.oid)),
-        term::format::dim(format!("R{}", current + 1)),
+        term::format::dim(format!("R{}", patch.revisions.len())),
         term::format::secondary(common::fmt::oid(head)),
     );
     let message = options.message.get(REVISION_MSG);
@@ -281,15 +286,18 @@ fn update(
     term::patch::print_commits_ahead_behind(repo, *head, *current_revision.oid)?;
     term::blank();
 
-    if !term::confirm("Continue?") {
+    if !term::confirm("Continue?")? {
         anyhow::bail!("patch update aborted by user");
     }
 
     let new = patches.update(&project.urn, &patch_id, message, *base, *head)?;
-    assert_eq!(new, current + 1);
 
     term::blank();
-    term::success!("Patch {} updated 🌱", term::format::highlight(patch_id));
+    term::success!(
+        "Patch {} updated to {} 🌱",
+        term::format::highlight(patch_id),
+        term::format::dim(format!("R{}", new))
+    );
     term::blank();
 
     if options.sync {
@@ -308,6 +316,64 @@ fn update(
     Ok(())
 }
 
+/// Resolve conflicting revisions of a patch, eg. published concurrently from two
+/// devices. Either all conflicting revisions are superseded by the new revision, or
+/// one of them is kept and the others are discarded.
+fn resolve(
+    patch: Patch,
+    patch_id: PatchId,
+    patches: &PatchStore,
+    project: &project::Metadata,
+) -> anyhow::Result<Patch> {
+    let heads = patch.heads();
+
+    term::warning("this patch has conflicting revisions");
+    term::blank();
+    for (ix, revision) in &heads {
+        term::info!(
+            "{} {} {} {}",
+            term::format::dim(format!("R{}", ix)),
+            term::format::secondary(common::fmt::oid(&revision.oid)),
+            term::format::tertiary(common::fmt::peer(&revision.peer)),
+            term::format::dim(revision.timestamp),
+        );
+    }
+    term::blank();
+
+    let supersede = String::from("Supersede all with the new revision");
+    let mut options = vec![supersede.clone()];
+    options.extend(heads.iter().map(|(ix, revision)| {
+        format!(
+            "Keep R{} ({}) and discard the others",
+            ix,
+            common::fmt::oid(&revision.oid)
+        )
+    }));
+
+    let selection = term::select_with_prompt("Resolve conflict", &options, &supersede)
+        .ok_or_else(|| anyhow!("patch update aborted by user"))?;
+    let keep = options
+        .iter()
+        .position(|o| o == selection)
+        .and_then(|i| i.checked_sub(1))
+        .map(|i| heads[i].1.id);
+
+    if let Some(keep) = keep {
+        let discard = heads
+            .iter()
+            .map(|(_, r)| r.id)
+            .filter(|id| *id != keep)
+            .collect::<Vec<_>>();
+
+        patches.discard(&project.urn, &patch_id, &discard)?;
+
+        return patches
+            .get(&project.urn, &patch_id)?
+            .ok_or_else(|| anyhow!("couldn't load patch {}", patch_id));
+    }
+    Ok(patch)
+}
+
 fn create(
     storage: &Storage,
     profile: &Profile,
@@ -432,7 +498,7 @@ fn create(
     };
 
     if let Some((id, patch)) = patch {
-        if term::confirm("Update?") {
+        if term::confirm("Update?")? {
             term::blank();
 
             return update(
@@ -467,7 +533,7 @@ fn create(
     term::patch::list_commits(&commits)?;
     term::blank();
 
-    if !term::confirm("Continue?") {
+    if !term::confirm("Continue?")? {
         anyhow::bail!("patch proposal aborted by user");
     }
 
@@ -502,7 +568,7 @@ fn create(
     )));
     term::blank();
 
-    if !term::confirm("Create patch?") {
+    if !term::confirm("Create patch?")? {
         anyhow::bail!("patch proposal aborted by user");
     }
 
@@ -618,7 +684,7 @@ pub fn print(
     }
     author_info.push(term::format::dim(patch.timestamp));
 
-    let revision = patch.revisions.last();
+    let (_, revision) = patch.latest();
     term::info!(
         "{} {} {} {} {}",
         term::format::bold(&patch.title),
@@ -629,6 +695,14 @@ pub fn print(
     );
     term::info!("{}", author_info.join(" "));
 
+    if patch.is_conflicted() {
+        term::warning(&format!(
+            "patch has {} conflicting revisions, run `rad patch --update {}` to resolve",
+            patch.heads().len(),
+            common::fmt::cob(patch_id)
+        ));
+    }
+
     let mut timeline = Vec::new();
     for merge in &revision.merges {
         let peer = project::PeerInfo::get(&merge.peer, project, storage);
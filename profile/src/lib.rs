@@ -0,0 +1,375 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context as _};
+
+use librad::crypto::keystore::pinentry::SecUtf8;
+use librad::git::identities::Person;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::profile::archive;
+use radicle_common::signer::ToSigner;
+use radicle_common::{config, keys, person, profile};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "profile",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad profile ls
+    rad profile use [<id>]
+    rad profile show [<id>]
+    rad profile rm <id> [--yes]
+    rad profile export <file> [--stdin]
+    rad profile import <file> [--stdin]
+
+    Profiles are designated by their profile id, or by their peer id. If no profile is
+    given to `use`, it is selected interactively; if none is given to `show`, the
+    active profile is shown. A profile can only be removed when it isn't active.
+
+    Commands run against the active profile, unless another profile is selected with
+    `rad --profile <id>`, or the `RAD_PROFILE` environment variable. This doesn't
+    change the active profile.
+
+    To move a profile to another device, export it to a file, and import that file on
+    the other device. The archive holds the profile's key, its configuration and its
+    identity. It is encrypted with the profile's passphrase, and checked for integrity
+    when imported. Projects aren't part of the archive: once the profile is imported,
+    they can be cloned again with `rad clone`.
+
+    The imported profile becomes the active profile.
+
+    A passphrase may be given via the environment variable `RAD_PASSPHRASE` or
+    via the standard input stream if `--stdin` is used.
+
+Options
+
+    --yes      Don't ask for confirmation before removing a profile
+    --stdin    Read passphrase from stdin (default: false)
+    --help     Print help
+"#,
+};
+
+#[derive(Debug)]
+pub enum Operation {
+    List,
+    Use { id: Option<String> },
+    Show { id: Option<String> },
+    Remove { id: String, confirm: bool },
+    Export { file: PathBuf },
+    Import { file: PathBuf },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OperationName {
+    List,
+    Use,
+    Show,
+    Remove,
+    Export,
+    Import,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub op: Operation,
+    pub stdin: bool,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut op: Option<OperationName> = None;
+        let mut value: Option<OsString> = None;
+        let mut stdin = false;
+        let mut confirm = true;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Long("stdin") => {
+                    stdin = true;
+                }
+                Long("yes") if op == Some(OperationName::Remove) => {
+                    confirm = false;
+                }
+                Value(val) if op.is_none() => match val.to_string_lossy().as_ref() {
+                    "ls" | "list" => op = Some(OperationName::List),
+                    "use" => op = Some(OperationName::Use),
+                    "show" => op = Some(OperationName::Show),
+                    "rm" => op = Some(OperationName::Remove),
+                    "export" => op = Some(OperationName::Export),
+                    "import" => op = Some(OperationName::Import),
+
+                    unknown => anyhow::bail!("unknown operation '{}'", unknown),
+                },
+                Value(val) if value.is_none() => {
+                    value = Some(val);
+                }
+                _ => {
+                    return Err(anyhow!(arg.unexpected()));
+                }
+            }
+        }
+
+        let id = value.as_ref().map(|v| v.to_string_lossy().to_string());
+        let file = value.map(PathBuf::from);
+        let op = match op.unwrap_or(OperationName::List) {
+            OperationName::List => Operation::List,
+            OperationName::Use => Operation::Use { id },
+            OperationName::Show => Operation::Show { id },
+            OperationName::Remove => Operation::Remove {
+                id: id.ok_or_else(|| anyhow!("a profile must be specified"))?,
+                confirm,
+            },
+            OperationName::Export => Operation::Export {
+                file: file.ok_or_else(|| anyhow!("a file must be specified"))?,
+            },
+            OperationName::Import => Operation::Import {
+                file: file.ok_or_else(|| anyhow!("a file must be specified"))?,
+            },
+        };
+
+        Ok((Options { op, stdin }, vec![]))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    match options.op {
+        Operation::List => list(),
+        Operation::Use { id } => select(id),
+        Operation::Show { id } => show(id, ctx),
+        Operation::Remove { id, confirm } => remove(&id, confirm),
+        Operation::Export { file } => export(&file, options.stdin, ctx),
+        Operation::Import { file } => import(&file, options.stdin),
+    }
+}
+
+fn list() -> anyhow::Result<()> {
+    let active = profile::active()?;
+    let mut table = term::Table::default();
+
+    for p in profile::list()? {
+        let storage = profile::read_only(&p)?;
+        let name = profile::name(Some(&p)).unwrap_or_default();
+        let marker = if active.as_ref().map(|a| a.id()) == Some(p.id()) {
+            term::format::positive("*")
+        } else {
+            String::from(" ")
+        };
+
+        table.push([
+            marker,
+            term::format::highlight(p.id()),
+            term::format::tertiary(storage.peer_id()),
+            term::format::dim(name),
+        ]);
+    }
+    table.render();
+
+    Ok(())
+}
+
+fn select(id: Option<String>) -> anyhow::Result<()> {
+    let selection = match id {
+        Some(id) => profile::find(&id)?,
+        None if !term::interactive() => {
+            return Err(term::no_prompt("profile", Some("rad profile use <id>")));
+        }
+        None => {
+            let profiles = profile::list()?;
+            let active = profile::active()?
+                .ok_or_else(|| anyhow!("no active profile, specify a profile to use"))?;
+
+            match term::profile_select(&profiles, &active) {
+                Some(p) => p.clone(),
+                None => return Ok(()),
+            }
+        }
+    };
+    profile::set(selection.id())?;
+
+    term::success!(
+        "Profile {} activated",
+        term::display::Identity::new(&selection).styled()
+    );
+
+    Ok(())
+}
+
+fn show(id: Option<String>, ctx: impl term::Context) -> anyhow::Result<()> {
+    let p = match id {
+        Some(id) => profile::find(&id)?,
+        None => ctx.profile()?,
+    };
+    let storage = profile::read_only(&p)?;
+    let active = profile::active()?.map_or(false, |a| a.id() == p.id());
+    let mut table = term::Table::default();
+
+    term::info!(
+        "Profile {}{}",
+        term::format::secondary(p.id()),
+        if active {
+            term::format::dim(" (active)")
+        } else {
+            String::new()
+        }
+    );
+
+    if let Some(urn) = storage.config()?.user()? {
+        if let Some(person) = person::get(&storage, &urn)? {
+            table.push([
+                String::from("Name"),
+                term::format::tertiary(&person.subject().name),
+            ]);
+        }
+        table.push([String::from("URN"), term::format::tertiary(&urn)]);
+    }
+    table.push([
+        String::from("Peer ID"),
+        term::format::tertiary(storage.peer_id()),
+    ]);
+    table.push([
+        String::from("Config"),
+        term::format::tertiary(config::Config::path(&p).display()),
+    ]);
+    table.push([
+        String::from("Storage (git)"),
+        term::format::tertiary(p.paths().git_dir().display()),
+    ]);
+    table.push([
+        String::from("Storage (keys)"),
+        term::format::tertiary(p.paths().keys_dir().display()),
+    ]);
+    table.render_tree();
+
+    Ok(())
+}
+
+fn remove(id: &str, confirm: bool) -> anyhow::Result<()> {
+    let p = profile::find(id)?;
+
+    if profile::active()?.map_or(false, |a| a.id() == p.id()) {
+        anyhow::bail!(
+            "profile {} is active, activate another profile with `rad profile use` first",
+            p.id()
+        );
+    }
+    if confirm
+        && !term::confirm(format!(
+            "Remove profile {}? Its key and storage will be deleted.",
+            term::display::Identity::new(&p).styled()
+        ))?
+    {
+        anyhow::bail!("profile removal aborted");
+    }
+    profile::remove(&p)?;
+
+    term::success!("Profile {} removed", term::format::highlight(p.id()));
+
+    Ok(())
+}
+
+fn export(file: &Path, stdin: bool, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+
+    term::info!(
+        "Exporting profile {}",
+        term::display::Identity::new(&profile).styled()
+    );
+
+    let passphrase = term::read_passphrase(stdin, false)?;
+    let spinner = term::spinner("Unsealing key...");
+    let signer = keys::load_secret_key(&profile, passphrase.clone())
+        .context("invalid passphrase supplied")?
+        .to_signer(&profile)?;
+    spinner.finish();
+
+    let storage = keys::storage(&profile, signer.clone())?;
+    let spinner = term::spinner("Writing archive...");
+    let archive = archive::export(&profile, &storage, &signer, keys::pwhash(passphrase), file)?;
+    spinner.finish();
+
+    term::success!(
+        "Profile {} exported to {}",
+        term::format::highlight(profile.id()),
+        term::format::highlight(file.display())
+    );
+    if archive.urn().is_none() {
+        term::warning("The profile has no identity, only its key and configuration were exported.");
+    }
+    term::tip!(
+        "Run `rad profile import {}` on the other device",
+        file.display()
+    );
+
+    Ok(())
+}
+
+fn import(file: &Path, stdin: bool) -> anyhow::Result<()> {
+    let passphrase = term::read_passphrase(stdin, false)?;
+    let spinner = term::spinner("Opening archive...");
+    let (profile, archive) =
+        match archive::import(&profile::home(), file, keys::pwhash(passphrase.clone())) {
+            Ok(imported) => {
+                spinner.finish();
+                imported
+            }
+            Err(err) => {
+                spinner.failed();
+                return Err(err.into());
+            }
+        };
+
+    match restore(&profile, &archive, passphrase.clone()) {
+        Ok(Some(person)) => {
+            term::success!(
+                "Identity {} {} restored",
+                term::format::highlight(&person.subject().name),
+                term::format::dim(person.urn())
+            );
+        }
+        Ok(None) => {}
+        Err(err) => {
+            // Don't leave a half-imported profile behind, so that the import can be retried.
+            profile::remove(&profile)?;
+            return Err(err);
+        }
+    }
+    profile::set(profile.id())?;
+
+    term::success!(
+        "Profile {} imported and activated",
+        term::format::highlight(profile.id())
+    );
+    term::info!(
+        "Your radicle Peer ID is {}.",
+        term::format::highlight(archive.peer())
+    );
+
+    if let Ok(sock) = keys::ssh_auth_sock() {
+        keys::add(&profile, keys::pwhash(passphrase), sock)?;
+        term::success!("Radicle key added to ssh-agent");
+    }
+
+    Ok(())
+}
+
+/// Restore the identity of an archive into the storage of its newly imported profile.
+fn restore(
+    profile: &profile::Profile,
+    archive: &archive::Archive,
+    passphrase: SecUtf8,
+) -> anyhow::Result<Option<Person>> {
+    let signer = keys::load_secret_key(profile, passphrase)?.to_signer(profile)?;
+    let storage = keys::storage(profile, signer)?;
+
+    Ok(archive.restore(&storage)?)
+}
@@ -141,8 +141,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
                 seeds: options.seed.into_iter().collect(),
                 verbose: options.verbose,
                 mode: Mode::Push,
-                origin: None,
-                sync_self: false,
+                ..rad_sync::Options::default()
             },
             ctx,
         )?;
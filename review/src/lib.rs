@@ -164,7 +164,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
         patch_id_pretty,
         term::format::dim(format!("R{}", revision_ix)),
         term::format::tertiary(patch.author.name())
-    )) {
+    ))? {
         anyhow::bail!("Patch review aborted");
     }
 
@@ -248,6 +248,17 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
         ));
     }
 
+    // Rewards are only sent with an explicit token, amount and strategy.
+    if !term::interactive() {
+        if options.token.is_none() {
+            return Err(term::no_prompt("token", Some("--token")));
+        } else if options.amount.is_none() {
+            return Err(term::no_prompt("amount", Some("--amount")));
+        } else if options.strategy.is_none() {
+            return Err(term::no_prompt("strategy", Some("--strategy")));
+        }
+    }
+
     let token: Token = options
         .token
         .or_else(|| {
@@ -362,7 +373,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    if term::confirm("Do you wish to proceed?") {
+    if term::confirm("Do you wish to proceed?")? {
         let sum = amounts
             .iter()
             .fold(Some(U256::from(0_u64)), |sum, amt| {
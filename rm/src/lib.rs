@@ -126,7 +126,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
                     || term::confirm(format!(
                         "Are you sure you would like to delete {}?",
                         term::format::dim(namespace.display())
-                    ))
+                    ))?
                 {
                     rad_untrack::execute(urn, None, rad_untrack::Options { peer: None }, &profile)?;
                     fs::remove_dir_all(namespace)?;
@@ -155,7 +155,7 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
                             "Are you sure you would like to remove {} ({})?",
                             term::format::dim(peer_id),
                             term::format::dim(username)
-                        ))
+                        ))?
                     {
                         return Ok(());
                     }
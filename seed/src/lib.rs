@@ -0,0 +1,365 @@
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::config::{Config, Scope, SeedConfig};
+use radicle_common::profile::Profile;
+use radicle_common::project;
+use radicle_common::seed::{self, health, Address, Protocol};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "seed",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad seed ls [--project]
+    rad seed add <address> [--name <name>] [--project] [--no-verify]
+    rad seed rm <name | address> [--project]
+    rad seed set-default <name | address> [--project]
+    rad seed status [<name | address>] [--project]
+
+    Seeds are configured in the active profile's `config.toml`, or with `--project`,
+    in the `Radicle.toml` file of the current working copy. The first seed listed is
+    the default seed.
+
+    A seed address is either a host name, eg. `seed.acme.org`, or of the form
+    `<peer-id>@<host>[:<port>]`. Before a seed is added, its peer id is queried
+    via its HTTP API, and checked against the given one, if any.
+
+    The `status` operation probes the HTTP API and git endpoints of the configured
+    seeds, and checks that their host names resolve. The P2P endpoint itself isn't
+    probed. When run from a project, it also checks whether the seeds host it.
+    Results are remembered, so that healthy seeds are preferred when syncing, after
+    the default seed.
+
+    Seeds found in the legacy profile seeds file are migrated to the profile
+    configuration when this command is run.
+
+Examples
+
+    rad seed add seed.acme.org --name acme
+    rad seed set-default acme
+
+Options
+
+    --project       Use the configuration of the current project
+    --name <name>   Name of the seed being added
+    --no-verify     Don't query the seed for its peer id
+    --help          Print help
+"#,
+};
+
+#[derive(Debug)]
+pub enum Operation {
+    List,
+    Add {
+        address: Address,
+        name: Option<String>,
+        verify: bool,
+    },
+    Remove {
+        seed: String,
+    },
+    SetDefault {
+        seed: String,
+    },
+    Status {
+        seed: Option<String>,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OperationName {
+    List,
+    Add,
+    Remove,
+    SetDefault,
+    Status,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub op: Operation,
+    pub scope: Scope,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut op: Option<OperationName> = None;
+        let mut seed: Option<String> = None;
+        let mut name: Option<String> = None;
+        let mut scope = Scope::Profile;
+        let mut verify = true;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Long("project") => {
+                    scope = Scope::Local;
+                }
+                Long("name") if op == Some(OperationName::Add) => {
+                    name = Some(parser.value()?.to_string_lossy().to_string());
+                }
+                Long("no-verify") if op == Some(OperationName::Add) => {
+                    verify = false;
+                }
+                Value(val) if op.is_none() => match val.to_string_lossy().as_ref() {
+                    "ls" | "list" => op = Some(OperationName::List),
+                    "add" => op = Some(OperationName::Add),
+                    "rm" => op = Some(OperationName::Remove),
+                    "set-default" => op = Some(OperationName::SetDefault),
+                    "status" => op = Some(OperationName::Status),
+
+                    unknown => anyhow::bail!("unknown operation '{}'", unknown),
+                },
+                Value(val) if seed.is_none() => {
+                    seed = Some(val.to_string_lossy().to_string());
+                }
+                _ => {
+                    return Err(anyhow!(arg.unexpected()));
+                }
+            }
+        }
+
+        let op = match op.unwrap_or(OperationName::List) {
+            OperationName::List => Operation::List,
+            OperationName::Add => Operation::Add {
+                address: parse_address(
+                    &seed.ok_or_else(|| anyhow!("a seed address must be specified"))?,
+                )?,
+                name,
+                verify,
+            },
+            OperationName::Remove => Operation::Remove {
+                seed: seed.ok_or_else(|| anyhow!("a seed must be specified"))?,
+            },
+            OperationName::SetDefault => Operation::SetDefault {
+                seed: seed.ok_or_else(|| anyhow!("a seed must be specified"))?,
+            },
+            OperationName::Status => Operation::Status { seed },
+        };
+
+        Ok((Options { op, scope }, vec![]))
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+
+    migrate(&profile)?;
+
+    let path = Config::scope_path(options.scope, &profile);
+    let mut config = Config::scope(options.scope, &profile)?;
+
+    match options.op {
+        Operation::List => {
+            let mut table = term::Table::default();
+
+            for (i, seed) in config.seeds().enumerate() {
+                table.push([
+                    term::format::bold(seed.name.clone().unwrap_or_default()),
+                    seed.p2p
+                        .host_str()
+                        .map(term::format::highlight)
+                        .unwrap_or_default(),
+                    seed.peer()
+                        .map(|p| term::format::tertiary(p.default_encoding()))
+                        .unwrap_or_default(),
+                    if i == 0 {
+                        term::format::dim("(default)")
+                    } else {
+                        String::new()
+                    },
+                ]);
+            }
+            if config.seeds().next().is_none() {
+                term::info!(
+                    "No seeds configured in {}",
+                    term::format::dim(path.display())
+                );
+            }
+            table.render();
+        }
+        Operation::Add {
+            address,
+            name,
+            verify,
+        } => {
+            let seed = resolve(address, name, verify)?;
+            let host = seed.p2p.host_str().unwrap_or_default().to_owned();
+
+            if config.add(seed) {
+                term::success!("Seed {} added", term::format::highlight(host));
+            } else {
+                term::success!("Seed {} updated", term::format::highlight(host));
+            }
+            config.write(&path)?;
+        }
+        Operation::Remove { seed } => {
+            if config.remove(&seed).is_none() {
+                anyhow::bail!("seed '{}' not found in {}", seed, path.display());
+            }
+            config.write(&path)?;
+
+            term::success!("Seed {} removed", term::format::highlight(seed));
+        }
+        Operation::SetDefault { seed } => {
+            if !config.set_default(&seed) {
+                anyhow::bail!("seed '{}' not found in {}", seed, path.display());
+            }
+            config.write(&path)?;
+
+            term::success!("Seed {} is now the default", term::format::highlight(seed));
+        }
+        Operation::Status { seed } => {
+            let seeds = if let Some(seed) = seed {
+                vec![config
+                    .find(&seed)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("seed '{}' not found in {}", seed, path.display()))?]
+            } else {
+                config.seeds().cloned().collect()
+            };
+            status(&seeds, &profile)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse a seed address, either a host name or a `<peer-id>@<host>[:<port>]` address,
+/// optionally prefixed with `rad://`.
+fn parse_address(s: &str) -> anyhow::Result<Address> {
+    let addr = if s.contains("://") {
+        Address::from_str(s)?
+    } else {
+        Address::from_str(&format!("rad://{}", s))?
+    };
+
+    if !matches!(addr.protocol, Protocol::Link { .. }) {
+        anyhow::bail!("invalid seed address '{}': expected `rad://` scheme", s);
+    }
+    Ok(addr)
+}
+
+/// Get the seed configuration of the given address, querying the seed for its peer id
+/// if `verify` is set.
+fn resolve(address: Address, name: Option<String>, verify: bool) -> anyhow::Result<SeedConfig> {
+    let mut peer = address.peer();
+
+    if verify {
+        let client = seed::SeedClient::new(&address);
+        let spinner = term::spinner(format!(
+            "Querying seed {}...",
+            term::format::highlight(&address.host)
+        ));
+
+        match client.peer() {
+            Ok(id) if peer.map_or(true, |p| p == id) => {
+                peer = Some(id);
+                spinner.finish();
+            }
+            Ok(id) => {
+                return Err(spinner.error(anyhow!(
+                    "seed peer id mismatch: expected {}, got {}",
+                    peer.map(|p| p.default_encoding()).unwrap_or_default(),
+                    id.default_encoding()
+                )));
+            }
+            Err(err) => {
+                return Err(spinner.error(anyhow::Error::new(err).context("failed to query seed")));
+            }
+        }
+    }
+
+    let peer = peer.ok_or_else(|| {
+        anyhow!("seed peer id unknown: specify it as `<peer-id>@<host>`, or omit `--no-verify`")
+    })?;
+    let mut seed = SeedConfig::new(address.host, Some(peer), name);
+
+    if address.port.is_some() {
+        seed.p2p.set_port(address.port).ok();
+    }
+    Ok(seed)
+}
+
+/// Probe the given seeds and print their health.
+fn status(seeds: &[SeedConfig], profile: &Profile) -> anyhow::Result<()> {
+    let project = project::cwd().ok().map(|(urn, _)| urn);
+    let mut cache = health::Cache::load(profile);
+    let mut table = term::Table::default();
+
+    for seed in seeds {
+        let name = seed
+            .name
+            .clone()
+            .or_else(|| seed.p2p.host_str().map(ToOwned::to_owned))
+            .unwrap_or_default();
+        let spinner = term::spinner(format!("Probing {}...", term::format::highlight(&name)));
+        let health = health::probe(seed, project.as_ref());
+
+        spinner.clear();
+
+        table.push([
+            if health.is_healthy() {
+                term::format::positive("✓")
+            } else {
+                term::format::negative("✗")
+            },
+            term::format::bold(&name),
+            format!("api {}", format_probe(&health.api)),
+            format!("git {}", format_probe(&health.git)),
+            format!(
+                "dns {}",
+                if health.dns.is_ok() {
+                    term::format::positive("resolved")
+                } else {
+                    term::format::negative("unresolved")
+                }
+            ),
+            match health.project {
+                Some(true) => term::format::positive("hosts project"),
+                Some(false) => term::format::negative("doesn't host project"),
+                None => String::new(),
+            },
+        ]);
+        cache.insert(seed, health);
+    }
+    table.render();
+    cache.save(profile)?;
+
+    Ok(())
+}
+
+/// Format a probe result for display.
+fn format_probe(probe: &health::Probe) -> String {
+    match probe.latency {
+        Some(latency) => term::format::positive(format!("{}ms", latency.as_millis())),
+        None => term::format::negative("down"),
+    }
+}
+
+/// Migrate the legacy seeds file into the profile configuration.
+fn migrate(profile: &Profile) -> anyhow::Result<()> {
+    let mut config = Config::scope(Scope::Profile, profile)?;
+    let migrated = config.migrate(profile)?;
+
+    if !migrated.is_empty() {
+        term::info!(
+            "Migrated {} seed(s) from the legacy seeds file to {}",
+            migrated.len(),
+            term::format::dim(Config::path(profile).display())
+        );
+    }
+    Ok(())
+}
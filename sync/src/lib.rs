@@ -1,9 +1,15 @@
 #![allow(clippy::or_fun_call)]
+mod watch;
+
 use std::convert::TryInto;
 use std::ffi::OsString;
 use std::iter;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time;
 
+use librad::crypto::BoxedSigner;
+use librad::git::storage::ReadOnly;
 use librad::git::Storage;
 use librad::git::Urn;
 use librad::profile::Profile;
@@ -11,8 +17,9 @@ use librad::profile::Profile;
 use radicle_common::args;
 use radicle_common::args::{Args, Error, Help};
 use radicle_common::nonempty::NonEmpty;
+use radicle_common::sync::queue::Queue;
 use radicle_common::sync::Mode;
-use radicle_common::{identity, keys, person, project, sync, tokio};
+use radicle_common::{bundle, identity, keys, person, project, sync, tokio};
 use radicle_terminal as term;
 
 use anyhow::anyhow;
@@ -26,18 +33,46 @@ pub const HELP: Help = Help {
     usage: r#"
 Usage
 
-    rad sync [<urn> | <url>] [--seed <address>]... [<options>...]
+    rad sync [<urn> | <url>] [--seed <address>]... [--cobs-only | --code-only] [<options>...]
     rad sync --self [--seed <address>]...
+    rad sync --pending
+    rad sync --watch [--seed <address>]... [--interval <secs>] [--log <path>]
+    rad sync [<urn>] --export-bundle <file>
 
     If a <urn> is specified, seeds may be given via the `--seed` option.
     If a <url> is specified, the seed is implied.
     If neither is specified, the URN and seed of the current project is used.
     If the project has no configured seed, the active profile's default seed list is used.
 
+    When pushing to some seeds fails, the sync is queued in the active profile, and can
+    be retried with `--pending`, eg. once back online.
+
+    With `--cobs-only` or `--code-only`, only the matching refs of tracked peers are
+    fetched, via the seeds' git endpoint rather than P2P replication. Branches, tags
+    and notes are verified against the peers' signed refs. Collaborative objects
+    aren't covered by signed refs, and are fetched without verification.
+    Pushing always lets the seed fetch all refs.
+
+    With `--watch`, all local projects are fetched from the seeds at a regular
+    interval, and pushed to them on startup and whenever their local refs change.
+    Results are logged to a file. This runs until interrupted.
+
+    With `--export-bundle`, the project is written to a git bundle, along with a
+    manifest of its refs signed with your key, for importing on a device without
+    network access, with `rad clone --from-bundle`.
+
 Options
 
     --seed <address>    Sync to the given seed (may be specified multiple times)
     --self              Sync your local identity only
+    --pending           Retry the queued syncs that previously failed
+    --cobs-only         Only fetch collaborative objects, eg. issues and patches
+    --code-only         Only fetch code, ie. branches
+    --watch             Keep syncing all local projects in the background
+    --interval <secs>   Interval between syncs, with `--watch` (default: 60)
+    --log <path>        Log file, with `--watch` (default: `sync.log` in the profile)
+    --export-bundle <file>
+                        Export the project to a bundle, instead of syncing
     --help              Print help
 
 Seed addresses
@@ -55,8 +90,25 @@ pub struct Options {
     pub origin: Option<identity::Origin>,
     pub seeds: Vec<sync::Seed<String>>,
     pub mode: Mode,
+    pub refs: sync::Refs,
     pub verbose: bool,
     pub sync_self: bool,
+    pub pending: bool,
+    pub watch: bool,
+    pub interval: time::Duration,
+    pub log: Option<PathBuf>,
+    pub export_bundle: Option<PathBuf>,
+}
+
+impl Options {
+    /// Options to sync with.
+    pub fn sync_options(&self) -> sync::Options {
+        sync::Options {
+            mode: self.mode,
+            refs: self.refs,
+            ..sync::Options::default()
+        }
+    }
 }
 
 impl Args for Options {
@@ -67,6 +119,12 @@ impl Args for Options {
         let mut verbose = false;
         let mut origin = None;
         let mut sync_self = false;
+        let mut pending = false;
+        let mut refs = sync::Refs::All;
+        let mut watch = false;
+        let mut interval = None;
+        let mut log = None;
+        let mut export_bundle = None;
         let mut unparsed = Vec::new();
         let mut seeds = Vec::new();
 
@@ -81,6 +139,37 @@ impl Args for Options {
                 Long("self") => {
                     sync_self = true;
                 }
+                Long("pending") => {
+                    pending = true;
+                }
+                Long("export-bundle") => {
+                    export_bundle = Some(PathBuf::from(parser.value()?));
+                }
+                Long("watch") => {
+                    watch = true;
+                }
+                Long("interval") => {
+                    let value = parser.value()?;
+                    let secs = value
+                        .to_string_lossy()
+                        .parse::<u64>()
+                        .map_err(|_| anyhow!("invalid interval: expected a number of seconds"))?;
+
+                    interval = Some(time::Duration::from_secs(secs.max(1)));
+                }
+                Long("log") => {
+                    log = Some(PathBuf::from(parser.value()?));
+                }
+                Long(flag @ ("cobs-only" | "code-only")) => {
+                    if refs != sync::Refs::All {
+                        anyhow::bail!("`--cobs-only` and `--code-only` can't be combined");
+                    }
+                    refs = if flag == "cobs-only" {
+                        sync::Refs::Cobs
+                    } else {
+                        sync::Refs::Code
+                    };
+                }
                 Long("seed") => {
                     let value = parser.value()?;
                     let value = value.to_string_lossy();
@@ -108,6 +197,9 @@ impl Args for Options {
             }
         }
 
+        if !watch && (interval.is_some() || log.is_some()) {
+            anyhow::bail!("`--interval` and `--log` can only be used with `--watch`");
+        }
         if let (
             &[_, ..],
             Some(identity::Origin {
@@ -126,7 +218,13 @@ impl Args for Options {
                 origin,
                 seeds,
                 mode: Mode::default(),
+                refs,
                 sync_self,
+                pending,
+                watch,
+                interval: interval.unwrap_or(watch::DEFAULT_INTERVAL),
+                log,
+                export_bundle,
                 verbose,
             },
             unparsed,
@@ -137,14 +235,44 @@ impl Args for Options {
 pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
     let profile = ctx.profile()?;
     let signer = term::signer(&profile)?;
-    let storage = keys::storage(&profile, signer)?;
     let rt = tokio::runtime::Runtime::new()?;
+
+    if options.pending {
+        return sync_pending(&profile, rt);
+    }
+    if options.watch {
+        let seeds = match NonEmpty::from_vec(options.seeds.clone()) {
+            Some(seeds) => seeds,
+            None => sync::seeds(&profile)?,
+        };
+        let log = options
+            .log
+            .clone()
+            .unwrap_or_else(|| watch::log_path(&profile));
+
+        return watch::watch(
+            &profile,
+            signer,
+            seeds,
+            options.sync_options(),
+            options.interval,
+            &log,
+            rt,
+        );
+    }
+
+    let storage = keys::storage(&profile, signer.clone())?;
+
     let urn = if let Some(origin) = &options.origin {
         origin.urn.clone()
     } else {
         project::cwd().map(|(urn, _)| urn)?
     };
 
+    if let Some(path) = &options.export_bundle {
+        return export_bundle(&urn, path, storage.read_only(), &signer);
+    }
+
     let seeds = if let Some(seed) = options.origin.as_ref().and_then(|o| o.seed.clone()) {
         NonEmpty::new(seed)
     } else if let Ok(seeds) = options.seeds.clone().try_into() {
@@ -160,6 +288,77 @@ pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
     }
 }
 
+/// Export a project to a bundle, for importing with `rad clone --from-bundle`.
+pub fn export_bundle(
+    urn: &Urn,
+    path: &Path,
+    storage: &ReadOnly,
+    signer: &BoxedSigner,
+) -> anyhow::Result<()> {
+    let spinner = term::spinner(format!(
+        "Exporting {} to {}...",
+        term::format::highlight(urn),
+        term::format::highlight(path.display())
+    ));
+    let manifest = match bundle::export(storage, signer, urn, path) {
+        Ok(manifest) => manifest,
+        Err(err) => return Err(spinner.error(err.into())),
+    };
+    spinner.finish();
+
+    term::success!(
+        "Exported {} ref(s), with manifest {}",
+        manifest.refs().count(),
+        term::format::dim(bundle::Manifest::path(path).display())
+    );
+
+    Ok(())
+}
+
+/// Retry the syncs that previously failed, with the seeds they failed with.
+pub fn sync_pending(profile: &Profile, rt: tokio::runtime::Runtime) -> anyhow::Result<()> {
+    let queue = Queue::load(profile)?;
+
+    if queue.is_empty() {
+        term::info!("No pending syncs");
+        return Ok(());
+    }
+
+    for entry in queue.entries() {
+        let seeds = match NonEmpty::from_vec(entry.seeds()) {
+            Some(seeds) => seeds,
+            None => {
+                term::warning(&format!("Skipping {}: no valid seeds", entry.urn));
+                continue;
+            }
+        };
+
+        term::headline(&format!(
+            "Syncing 🌱 identity {} with {} seed(s) {}",
+            term::format::highlight(&entry.urn),
+            term::format::dim(seeds.len()),
+            term::format::dim(format!("(attempt {})", entry.attempts + 1))
+        ));
+
+        // Nb. The queue is updated by the sync itself.
+        let signer = term::signer(profile)?;
+        if let Err(err) =
+            term::sync::sync(entry.urn.clone(), seeds, entry.mode, profile, signer, &rt)
+        {
+            term::error(err);
+        }
+        term::blank();
+    }
+
+    let remaining = Queue::load(profile)?;
+    if !remaining.is_empty() {
+        anyhow::bail!("{} sync(s) failed and remain pending", remaining.len());
+    }
+    term::success!("All pending syncs completed");
+
+    Ok(())
+}
+
 pub fn sync_self(
     profile: &Profile,
     seeds: NonEmpty<sync::Seed<String>>,
@@ -176,7 +375,7 @@ pub fn sync_self(
     ));
 
     let signer = term::signer(profile)?;
-    let _result = term::sync::sync(urn, seeds, options.mode, profile, signer, &rt)?;
+    let _result = term::sync::sync(urn, seeds, options.sync_options(), profile, signer, &rt)?;
 
     if options.verbose {
         // TODO: When sync result is usable, output should go here.
@@ -204,7 +403,7 @@ pub fn sync(
     let _result = term::sync::sync(
         urn.clone(),
         seeds.clone(),
-        options.mode,
+        options.sync_options(),
         profile,
         signer,
         &rt,
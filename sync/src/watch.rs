@@ -0,0 +1,202 @@
+//! Background syncing, with `rad sync --watch`.
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time;
+
+use librad::crypto::BoxedSigner;
+use librad::git::storage::ReadOnly;
+use librad::git::Urn;
+use librad::profile::Profile;
+
+use radicle_common::nonempty::NonEmpty;
+use radicle_common::sync::{self, Mode, SyncResult};
+use radicle_common::{git, keys, project, tokio};
+use radicle_terminal as term;
+
+/// Default interval between syncs.
+pub const DEFAULT_INTERVAL: time::Duration = time::Duration::from_secs(60);
+/// Name of the log file, in the profile.
+pub const LOG_FILE_NAME: &str = "sync.log";
+
+/// Path of the default log file for the given profile.
+pub fn log_path(profile: &Profile) -> PathBuf {
+    profile.paths().seeds_file().with_file_name(LOG_FILE_NAME)
+}
+
+/// Sync log, written to a file.
+struct Log {
+    file: File,
+}
+
+impl Log {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self { file })
+    }
+
+    fn write(&mut self, msg: impl fmt::Display) {
+        writeln!(self.file, "{} {}", chrono::Utc::now().to_rfc3339(), msg).ok();
+    }
+
+    fn results(&mut self, urn: &Urn, op: &str, results: &[SyncResult]) {
+        for result in results {
+            match result.error() {
+                Some(err) => self.write(format!(
+                    "{} {} {} error: {}",
+                    urn, op, result.seed.peer, err
+                )),
+                None => self.write(format!("{} {} {} ok", urn, op, result.seed.peer)),
+            }
+        }
+    }
+}
+
+/// Periodically fetch all local projects from the given seeds, and push them whenever
+/// their local refs change. Runs until interrupted.
+pub fn watch(
+    profile: &Profile,
+    signer: BoxedSigner,
+    seeds: NonEmpty<sync::Seed<String>>,
+    options: sync::Options,
+    interval: time::Duration,
+    log_file: &Path,
+    rt: tokio::runtime::Runtime,
+) -> anyhow::Result<()> {
+    let storage = keys::storage(profile, signer.clone())?;
+    let mut log = Log::open(log_file)?;
+
+    term::info!(
+        "Syncing 🌱 with {} seed(s) every {}s, logging to {}",
+        term::format::dim(seeds.len()),
+        interval.as_secs(),
+        term::format::dim(log_file.display())
+    );
+    log.write(format!("watching with {} seed(s)", seeds.len()));
+
+    rt.block_on(async {
+        // Nb. A single client is kept for the lifetime of the process.
+        let client = sync::client(signer, profile).await?;
+
+        run(
+            &client,
+            profile,
+            storage.read_only(),
+            &seeds,
+            options,
+            interval,
+            &mut log,
+        )
+        .await
+    })
+}
+
+/// Sync loop.
+async fn run(
+    client: &sync::SyncClient,
+    profile: &Profile,
+    storage: &ReadOnly,
+    seeds: &NonEmpty<sync::Seed<String>>,
+    options: sync::Options,
+    interval: time::Duration,
+    log: &mut Log,
+) -> anyhow::Result<()> {
+    let mut snapshots: HashMap<Urn, HashMap<String, git::Oid>> = HashMap::new();
+
+    loop {
+        // Nb. Errors are logged, and retried on the next iteration, so that the
+        // daemon keeps running.
+        let projects = match project::list(storage) {
+            Ok(projects) => projects,
+            Err(err) => {
+                log.write(format!("error listing projects: {}", err));
+                Vec::new()
+            }
+        };
+
+        for (urn, meta, _) in projects {
+            let results = sync::sync(
+                client,
+                profile,
+                urn.clone(),
+                seeds.iter().cloned(),
+                sync::Options {
+                    mode: Mode::Fetch,
+                    ..options
+                },
+                |_, _| {},
+            )
+            .await;
+            log.results(&urn, "fetch", &results);
+
+            let refs = match local_refs(storage, &urn) {
+                Ok(refs) => refs,
+                Err(err) => {
+                    log.write(format!("{} error: {}", urn, err));
+                    continue;
+                }
+            };
+            // Projects are pushed on the first iteration, since they may have changed
+            // while we weren't watching.
+            let changed = snapshots.get(&urn) != Some(&refs);
+            snapshots.insert(urn.clone(), refs);
+
+            if !changed || !options.mode.is_push() {
+                continue;
+            }
+            term::info!(
+                "Pushing {} ({})",
+                term::format::highlight(&meta.name),
+                term::format::dim(&urn)
+            );
+
+            let results = sync::sync(
+                client,
+                profile,
+                urn.clone(),
+                seeds.iter().cloned(),
+                sync::Options {
+                    mode: Mode::Push,
+                    ..options
+                },
+                |_, _| {},
+            )
+            .await;
+            log.results(&urn, "push", &results);
+
+            // Failed pushes can be retried with `rad sync --pending`.
+            let failed = seeds
+                .iter()
+                .filter(|s| {
+                    !results
+                        .iter()
+                        .any(|r| r.seed.peer == s.peer && r.is_success())
+                })
+                .collect::<Vec<_>>();
+            if let Err(err) = term::sync::record(&urn, Mode::Push, &failed, profile) {
+                log.write(format!("error saving sync queue: {}", err));
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Get the local refs of a project, ie. not including the refs of remotes.
+fn local_refs(storage: &ReadOnly, urn: &Urn) -> anyhow::Result<HashMap<String, git::Oid>> {
+    let repo = git::Repository::open_bare(storage.path())?;
+    let namespace = format!("refs/namespaces/{}/refs/", urn.encode_id());
+    let mut refs = HashMap::new();
+
+    for r in repo.references_glob(&format!("{}*", namespace))? {
+        let r = r?;
+        if let (Some(name), Some(oid)) = (r.name(), r.target()) {
+            if !name[namespace.len()..].starts_with("remotes/") {
+                refs.insert(name.to_owned(), oid);
+            }
+        }
+    }
+    Ok(refs)
+}
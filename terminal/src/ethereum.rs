@@ -19,6 +19,9 @@ where
     let chain_id = provider.get_chainid().await?.as_u64();
 
     if let Some(keypath) = &options.keystore {
+        if !term::interactive() {
+            return Err(term::no_prompt("keystore password", None));
+        }
         let password = term::secret_input_with_prompt("Keystore password");
         let spinner = term::spinner("Decrypting keystore...");
         let signer = LocalWallet::decrypt_keystore(keypath, password.unsecure())
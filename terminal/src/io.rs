@@ -1,5 +1,5 @@
-use std::fmt;
 use std::str::FromStr;
+use std::{env, fmt, io};
 
 use zeroize::Zeroizing;
 
@@ -11,6 +11,7 @@ use dialoguer::{console::style, console::Style, theme::ColorfulTheme, Input, Pas
 
 use radicle_common::cobs::issue::Issue;
 use radicle_common::cobs::shared::CommentId;
+use radicle_common::signer::external::ExternalSigner;
 use radicle_common::signer::ToSigner;
 
 use super::command;
@@ -25,6 +26,14 @@ pub use super::ethereum;
 
 pub const TAB: &str = "   ";
 
+/// Environment variable that disables prompts when set, eg. by `rad --no-prompt`. It is
+/// inherited by sub-processes, so that they don't prompt either.
+pub const RAD_NO_PROMPT: &str = "RAD_NO_PROMPT";
+
+/// Environment variable that accepts confirmations when set, eg. by `rad --yes`. It is
+/// inherited by sub-processes, like [`RAD_NO_PROMPT`].
+pub const RAD_YES: &str = "RAD_YES";
+
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)*) => ({
@@ -162,27 +171,73 @@ pub fn fail(header: &str, error: &anyhow::Error) {
     }
 }
 
-pub fn ask<D: fmt::Display>(prompt: D, default: bool) -> bool {
-    dialoguer::Confirm::new()
+/// Disable prompts for this process and its sub-processes.
+pub fn disable_prompts() {
+    env::set_var(RAD_NO_PROMPT, "1");
+}
+
+/// Accept confirmations for this process and its sub-processes. Other prompts are
+/// disabled.
+pub fn accept_confirmations() {
+    env::set_var(RAD_YES, "1");
+    disable_prompts();
+}
+
+/// Whether the user can be prompted. Prompts are disabled with `--no-prompt`, or when the
+/// standard input isn't a terminal. When disabled, prompts take their default, or fail
+/// if they have none. Confirmations have no default: they are only accepted with `--yes`.
+pub fn interactive() -> bool {
+    env::var_os(RAD_NO_PROMPT).is_none() && atty::is(atty::Stream::Stdin)
+}
+
+/// Error returned by a prompt that has no default, when prompts are disabled. If the value
+/// can be supplied with an option, the option is named.
+pub fn no_prompt(prompt: &str, option: Option<&str>) -> anyhow::Error {
+    match option {
+        Some(option) => anyhow::anyhow!(
+            "missing `{}`, it must be specified with `{}` when prompts are disabled",
+            prompt,
+            option
+        ),
+        None => anyhow::anyhow!("missing `{}`, and prompts are disabled", prompt),
+    }
+}
+
+/// Ask a yes/no question. With `--yes`, the answer is yes. Otherwise, when prompts are
+/// disabled, an error naming `--yes` is returned.
+pub fn ask<D: fmt::Display>(prompt: D, default: bool) -> anyhow::Result<bool> {
+    if env::var_os(RAD_YES).is_some() {
+        return Ok(true);
+    }
+    if !interactive() {
+        return Err(no_prompt("confirmation", Some("--yes")));
+    }
+    Ok(dialoguer::Confirm::new()
         .with_prompt(format!("{} {}", style(" ⤷".to_owned()).cyan(), prompt))
         .wait_for_newline(false)
         .default(true)
         .default(default)
         .interact()
-        .unwrap_or_default()
+        .unwrap_or_default())
 }
 
-pub fn confirm<D: fmt::Display>(prompt: D) -> bool {
+pub fn confirm<D: fmt::Display>(prompt: D) -> anyhow::Result<bool> {
     ask(format::tertiary(prompt), true)
 }
 
-pub fn abort<D: fmt::Display>(prompt: D) -> bool {
+pub fn abort<D: fmt::Display>(prompt: D) -> anyhow::Result<bool> {
     ask(format::tertiary(prompt), false)
 }
 
-/// Get the signer. First we try getting it from ssh-agent, otherwise we prompt the user.
+/// Get the signer. If the profile is configured with an external signer, it is used.
+/// Otherwise, we try getting it from ssh-agent, or else we prompt the user. Fails if the
+/// profile key was revoked.
 pub fn signer(profile: &Profile) -> anyhow::Result<BoxedSigner> {
-    let signer = if let Ok(sock) = keys::ssh_auth_sock() {
+    keys::ensure_not_revoked(profile)?;
+
+    let signer = if let Some(external) = ExternalSigner::load(profile)? {
+        external.to_signer(profile)?
+    } else if let Ok(sock) = keys::ssh_auth_sock() {
         sock.to_signer(profile)?
     } else {
         secret_key(profile)?.to_signer(profile)?
@@ -213,6 +268,9 @@ where
     S: fmt::Display + std::str::FromStr<Err = E> + Clone,
     E: fmt::Debug + fmt::Display,
 {
+    if !interactive() {
+        return default.ok_or_else(|| no_prompt(message, None));
+    }
     let theme = theme();
     let mut input: Input<S> = Input::with_theme(&theme);
 
@@ -226,6 +284,19 @@ where
     Ok(value)
 }
 
+/// Like [`text_input`], for a value that can also be supplied with the given option, which
+/// is named in the error returned when prompts are disabled.
+pub fn text_input_for<S, E>(option: &str, message: &str, default: Option<S>) -> anyhow::Result<S>
+where
+    S: fmt::Display + std::str::FromStr<Err = E> + Clone,
+    E: fmt::Debug + fmt::Display,
+{
+    if !interactive() && default.is_none() {
+        return Err(no_prompt(message, Some(option)));
+    }
+    text_input(message, default)
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Optional<T> {
     option: Option<T>,
@@ -262,6 +333,15 @@ where
     S: fmt::Display + fmt::Debug + FromStr<Err = E> + Clone,
     E: fmt::Debug + fmt::Display,
 {
+    if !interactive() {
+        return match initial {
+            Some(init) => Ok(init
+                .parse::<Optional<S>>()
+                .map_err(|e| anyhow::anyhow!("invalid `{}`: {}", message, e))?
+                .option),
+            None => Ok(None),
+        };
+    }
     let theme = theme();
     let mut input: Input<Optional<S>> = Input::with_theme(&theme);
 
@@ -316,6 +396,11 @@ pub fn read_passphrase(stdin: bool, confirm: bool) -> Result<SecUtf8, anyhow::Er
         _ => {
             if stdin {
                 secret_stdin()?
+            } else if !interactive() {
+                anyhow::bail!(Error::WithHint {
+                    err: no_prompt("passphrase", Some("--stdin")),
+                    hint: "The passphrase may also be given via the `RAD_PASSPHRASE` environment variable.",
+                });
             } else if confirm {
                 secret_input_with_confirmation()
             } else {
@@ -335,7 +420,14 @@ pub fn read_passphrase_from_env_var() -> Result<SecUtf8, anyhow::Error> {
 }
 
 pub fn secret_key(profile: &Profile) -> Result<keys::signer::ZeroizingSecretKey, anyhow::Error> {
-    let passphrase = secret_input();
+    let passphrase = if interactive() {
+        secret_input()
+    } else {
+        read_passphrase_from_env_var().map_err(|_| Error::WithHint {
+            err: no_prompt("passphrase", Some(keys::RAD_PASSPHRASE)),
+            hint: "Alternatively, add your radicle key to ssh-agent with `rad auth`.",
+        })?
+    };
     let spinner = spinner("Unsealing key..."); // Nb. Spinner ends when dropped.
     let key = keys::load_secret_key(profile, passphrase)?;
 
@@ -348,6 +440,9 @@ pub fn select<'a, T>(options: &'a [T], active: &'a T) -> Option<&'a T>
 where
     T: fmt::Display + Eq + PartialEq,
 {
+    if !interactive() {
+        return options.iter().find(|o| *o == active);
+    }
     let theme = theme();
     let active = options.iter().position(|o| o == active);
     let mut selection = dialoguer::Select::with_theme(&theme);
@@ -367,6 +462,9 @@ pub fn select_with_prompt<'a, T>(prompt: &str, options: &'a [T], active: &'a T)
 where
     T: fmt::Display + Eq + PartialEq,
 {
+    if !interactive() {
+        return options.iter().find(|o| *o == active);
+    }
     let theme = theme();
     let active = options.iter().position(|o| o == active);
     let mut selection = dialoguer::Select::with_theme(&theme);
@@ -385,6 +483,9 @@ where
 
 pub fn profile_select<'a>(profiles: &'a [Profile], active: &Profile) -> Option<&'a Profile> {
     let active = profiles.iter().position(|p| p.id() == active.id()).unwrap();
+    if !interactive() {
+        return Some(&profiles[active]);
+    }
     let selection = dialoguer::Select::with_theme(&theme())
         .items(
             &profiles
@@ -400,6 +501,9 @@ pub fn profile_select<'a>(profiles: &'a [Profile], active: &Profile) -> Option<&
 }
 
 pub fn comment_select(issue: &Issue) -> Option<CommentId> {
+    if !interactive() {
+        return Some(CommentId::root());
+    }
     let selection = dialoguer::Select::with_theme(&theme())
         .with_prompt("Which comment do you want to react to?")
         .item(&issue.description().to_string())
@@ -417,6 +521,49 @@ pub fn comment_select(issue: &Issue) -> Option<CommentId> {
     selection.map(CommentId::from)
 }
 
+/// Editor for the user to edit a document with. When prompts are disabled, the editor isn't
+/// opened, and the document is returned as is.
+pub struct Editor {
+    inner: dialoguer::Editor,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Self {
+            inner: dialoguer::Editor::new(),
+        }
+    }
+
+    pub fn extension(&mut self, ext: &str) -> &mut Self {
+        self.inner.extension(ext);
+        self
+    }
+
+    pub fn require_save(&mut self, val: bool) -> &mut Self {
+        self.inner.require_save(val);
+        self
+    }
+
+    pub fn trim_newlines(&mut self, val: bool) -> &mut Self {
+        self.inner.trim_newlines(val);
+        self
+    }
+
+    /// Edit the given document. Returns `None` if the edit was aborted.
+    pub fn edit(&self, doc: &str) -> io::Result<Option<String>> {
+        if !interactive() {
+            return Ok(Some(doc.to_owned()));
+        }
+        self.inner.edit(doc)
+    }
+}
+
 pub fn markdown(content: &str) {
     if !content.is_empty() {
         if command::bat(["-p", "-l", "md"], content).is_err() {
@@ -428,3 +575,48 @@ pub fn markdown(content: &str) {
 fn _info(args: std::fmt::Arguments) {
     println!("{}", args);
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_no_prompt() {
+        assert_eq!(
+            no_prompt("title", Some("--title")).to_string(),
+            "missing `title`, it must be specified with `--title` when prompts are disabled"
+        );
+        assert_eq!(
+            no_prompt("passphrase", None).to_string(),
+            "missing `passphrase`, and prompts are disabled"
+        );
+    }
+
+    #[test]
+    fn test_prompts_disabled() {
+        // Nb. Prompts are configured through the environment, so these cases are tested
+        // in sequence, in a single test.
+        env::remove_var(RAD_YES);
+        env::remove_var(RAD_NO_PROMPT);
+
+        // With `--no-prompt`, confirmations fail, and name the option to use instead.
+        disable_prompts();
+        assert!(!interactive());
+        for result in [ask("Proceed?", true), confirm("Proceed?"), abort("Abort?")] {
+            let err = result.unwrap_err().to_string();
+            assert!(err.contains("--yes"), "{}", err);
+        }
+
+        // With `--yes`, confirmations are accepted, and other prompts stay disabled.
+        env::remove_var(RAD_NO_PROMPT);
+        accept_confirmations();
+        assert!(env::var_os(RAD_NO_PROMPT).is_some());
+        assert!(!interactive());
+        assert!(ask("Proceed?", false).unwrap());
+        assert!(confirm("Proceed?").unwrap());
+        assert!(abort("Abort?").unwrap());
+
+        env::remove_var(RAD_YES);
+        env::remove_var(RAD_NO_PROMPT);
+    }
+}
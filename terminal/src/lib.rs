@@ -23,7 +23,6 @@ use radicle_common::profile;
 use radicle_common::profile::Profile;
 
 pub use console::measure_text_width as text_width;
-pub use dialoguer::Editor;
 pub use io::*;
 pub use spinner::{spinner, Spinner};
 pub use table::Table;
@@ -50,6 +49,16 @@ where
     }
 }
 
+/// Select the profile to run commands against for this invocation, without changing the
+/// active profile. The profile returned by [`Context::profile`] is then the selected
+/// profile, and so it is for sub-processes, eg. git remote helpers.
+pub fn select_profile(id: &str) -> anyhow::Result<Profile> {
+    let profile = profile::find(id)?;
+    std::env::set_var(profile::RAD_PROFILE, id);
+
+    Ok(profile)
+}
+
 /// A command that can be run.
 pub trait Command<A: Args, C: Context> {
     /// Run the command, given arguments and a context.
@@ -119,10 +128,37 @@ where
     };
 
     match cmd.run(options, profile::default) {
-        Ok(()) => process::exit(0),
+        Ok(()) => {
+            pending_hint();
+            process::exit(0)
+        }
         Err(err) => {
             term::fail(&format!("{} failed", action), &err);
             process::exit(1);
         }
     }
 }
+
+/// Let the user know if there are failed syncs waiting to be retried. The hint is
+/// printed to the standard error, so that it doesn't mix with the output of commands
+/// that is read by other programs, eg. JSON or completions.
+fn pending_hint() {
+    use radicle_common::sync::queue::Queue;
+
+    let queue = match profile::default().map(|p| Queue::load(&p)) {
+        Ok(Ok(queue)) => queue,
+        _ => return,
+    };
+    if !queue.is_empty() {
+        eprintln!(
+            "{} {}",
+            style("=>").blue().for_stderr(),
+            style(format!(
+                "{} sync(s) pending, run `rad sync --pending` to retry",
+                queue.len()
+            ))
+            .dim()
+            .for_stderr()
+        );
+    }
+}
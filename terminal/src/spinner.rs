@@ -58,7 +58,17 @@ impl Spinner {
 
 pub fn spinner(message: impl ToString) -> Spinner {
     let message = message.to_string();
-    let style = ProgressStyle::default_spinner()
+    let progress = ProgressBar::new(!0);
+    progress.set_style(progress_style());
+    progress.enable_steady_tick(99);
+    progress.set_message(message.clone());
+
+    Spinner { message, progress }
+}
+
+/// Spinner progress style.
+pub(crate) fn progress_style() -> ProgressStyle {
+    ProgressStyle::default_spinner()
         .tick_strings(&[
             &style("\\ ").yellow().to_string(),
             &style("| ").yellow().to_string(),
@@ -66,12 +76,5 @@ pub fn spinner(message: impl ToString) -> Spinner {
             &style("| ").yellow().to_string(),
         ])
         .template("{spinner} {msg}")
-        .on_finish(ProgressFinish::AndClear);
-
-    let progress = ProgressBar::new(!0);
-    progress.set_style(style);
-    progress.enable_steady_tick(99);
-    progress.set_message(message.clone());
-
-    Spinner { message, progress }
+        .on_finish(ProgressFinish::AndClear)
 }
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
-use std::time;
+use std::thread;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
 use librad::git::Urn;
 
@@ -8,6 +11,7 @@ use radicle_common::nonempty::NonEmpty;
 use radicle_common::profile::Profile;
 use radicle_common::signer::ToSigner;
 use radicle_common::sync;
+use radicle_common::sync::queue::Queue;
 use radicle_common::sync::SyncResult;
 
 use crate as term;
@@ -15,23 +19,110 @@ use crate as term;
 pub fn sync(
     urn: Urn,
     seeds: NonEmpty<sync::Seed<String>>,
-    mode: sync::Mode,
+    options: impl Into<sync::Options>,
     profile: &Profile,
     signer: impl ToSigner,
     rt: &common::tokio::runtime::Runtime,
 ) -> anyhow::Result<NonEmpty<SyncResult>> {
     let signer = signer.to_signer(profile)?;
-    let timeout = time::Duration::from_secs(9);
-    let spinner = term::spinner("Syncing...");
-    let result = rt.block_on(async {
-        let (seeds, _errors) = sync::Seeds::resolve(seeds.iter()).await;
-        let client = sync::client(signer, profile).await?;
-        let result = sync::sync(&client, urn, seeds, mode, timeout).await;
+    let options = options.into();
+    let mode = options.mode;
+    let progress = MultiProgress::new();
+    let bars = seeds
+        .iter()
+        .map(|seed| {
+            let name = seed
+                .label
+                .clone()
+                .unwrap_or_else(|| common::fmt::peer(&seed.peer));
+            let bar = progress.add(ProgressBar::new(!0));
+
+            bar.set_style(term::spinner::progress_style());
+            bar.enable_steady_tick(99);
+            bar.set_message(format!("{} {}", name, term::format::dim("connecting...")));
+
+            (seed.peer, (name, bar))
+        })
+        .collect::<HashMap<_, _>>();
+
+    // Progress bars are drawn from the current thread, while syncing happens in the
+    // background.
+    let result = thread::scope(|scope| {
+        let handle = scope.spawn(|| {
+            let result = rt.block_on(async {
+                let client = sync::client(signer, profile).await?;
+                let result = sync::sync(
+                    &client,
+                    profile,
+                    urn.clone(),
+                    seeds.iter().cloned(),
+                    options,
+                    |peer, event| {
+                        if let Some((name, bar)) = bars.get(peer) {
+                            update(name, bar, event);
+                        }
+                    },
+                )
+                .await;
+
+                Ok::<Vec<SyncResult>, anyhow::Error>(result)
+            });
 
-        Ok::<Vec<SyncResult>, anyhow::Error>(result)
-    })?;
+            // Seeds that couldn't be resolved or weren't attempted.
+            for (name, bar) in bars.values() {
+                if !bar.is_finished() {
+                    finish(
+                        bar,
+                        format!(
+                            "{} {} {}",
+                            term::format::negative("✗"),
+                            name,
+                            term::format::dim("not attempted")
+                        ),
+                    );
+                }
+            }
+            result
+        });
 
-    let results = if let Ok(results) = result.try_into() {
+        match mode {
+            sync::Mode::Push | sync::Mode::All => progress.join(),
+            sync::Mode::Fetch => progress.join_and_clear(),
+        }
+        .ok();
+
+        handle
+            .join()
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("sync thread panicked")))
+    });
+
+    if matches!(mode, sync::Mode::Push | sync::Mode::All) {
+        let synced = result
+            .as_ref()
+            .map(|results| {
+                results
+                    .iter()
+                    .filter(|r| r.is_success())
+                    .map(|r| r.seed.peer)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let failed = seeds
+            .iter()
+            .filter(|s| !synced.contains(&s.peer))
+            .collect::<Vec<_>>();
+
+        if let Err(err) = record(&urn, mode, &failed, profile) {
+            term::warning(&format!("Failed to update sync queue: {}", err));
+        } else if !failed.is_empty() {
+            term::tip!(
+                "Sync with {} seed(s) failed, run `rad sync --pending` to retry",
+                failed.len()
+            );
+        }
+    }
+
+    let results = if let Ok(results) = result?.try_into() {
         results
     } else {
         return Err(anyhow::anyhow!(
@@ -39,10 +130,59 @@ pub fn sync(
         ));
     };
 
-    match mode {
-        sync::Mode::Push | sync::Mode::All => spinner.finish(),
-        sync::Mode::Fetch => spinner.clear(),
+    Ok(results)
+}
+
+/// Record the seeds a sync failed with in the profile's sync queue, or remove the sync
+/// from the queue if it succeeded with all seeds.
+pub fn record(
+    urn: &Urn,
+    mode: sync::Mode,
+    failed: &[&sync::Seed<String>],
+    profile: &Profile,
+) -> Result<(), std::io::Error> {
+    let mut queue = Queue::load(profile)?;
+
+    if failed.is_empty() {
+        if queue.dequeue(urn, mode).is_none() {
+            return Ok(());
+        }
+    } else {
+        queue.enqueue(urn, mode, failed.iter().copied());
     }
+    queue.save()
+}
 
-    Ok(results)
+/// Update a seed's progress bar.
+fn update(name: &str, bar: &ProgressBar, event: sync::Event) {
+    match event {
+        sync::Event::Fetching => {
+            bar.set_message(format!("{} {}", name, term::format::dim("fetching...")));
+        }
+        sync::Event::Pushing => {
+            bar.set_message(format!("{} {}", name, term::format::dim("pushing...")));
+        }
+        sync::Event::Progress(msg) => {
+            bar.set_message(format!("{} {}", name, term::format::dim(msg.trim())));
+        }
+        sync::Event::Done(result) => {
+            let msg = if let Some(err) = result.error() {
+                format!(
+                    "{} {} {}",
+                    term::format::negative("✗"),
+                    name,
+                    term::format::dim(err)
+                )
+            } else {
+                format!("{} {}", term::format::positive("✓"), name)
+            };
+            finish(bar, msg);
+        }
+    }
+}
+
+/// Finish a progress bar, leaving the given message.
+fn finish(bar: &ProgressBar, msg: String) {
+    bar.set_style(ProgressStyle::default_spinner().template("{msg}"));
+    bar.finish_with_message(msg);
 }
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::convert::TryInto;
+use std::convert::{TryFrom, TryInto};
 
 use anyhow::anyhow;
 use anyhow::Context as _;
@@ -349,7 +349,18 @@ pub fn show_remote(
     let remotes = project::list_seed_heads(repo, seed, urn)?;
     let mut commits: HashMap<_, String> = HashMap::new();
 
-    let remote_metadata = if let Ok(meta) = seed::get_remotes(seed.clone(), urn) {
+    let client = match seed::Address::try_from(seed.clone()) {
+        Ok(addr) => seed::SeedClient::new(&addr),
+        Err(_) => {
+            // Not a radicle seed address: assume the API runs on the same host.
+            let mut api = seed.clone();
+            api.set_port(Some(seed::DEFAULT_SEED_API_PORT)).ok();
+
+            seed::SeedClient::from_url(api)
+        }
+    };
+
+    let remote_metadata = if let Ok(meta) = client.remotes(urn) {
         meta.into_iter().map(|r| (r.id, r)).collect()
     } else {
         HashMap::new() // Support old seeds that don't have metadata.
@@ -371,7 +382,7 @@ pub fn show_remote(
         for (branch, oid) in branches {
             let message: String = if let Some(m) = commits.get(&oid) {
                 m.to_owned()
-            } else if let Ok(commit) = seed::get_commit(seed.clone(), urn, &oid) {
+            } else if let Ok(commit) = client.commit(urn, &oid) {
                 commits.insert(oid, commit.header.summary.clone());
                 commit.header.summary
             } else {
@@ -388,3 +399,45 @@ pub fn show_remote(
     }
     Ok(peers)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use radicle_common::test;
+
+    #[test]
+    fn test_show_remote() {
+        let (storage, _, _, project) = test::setup::profile();
+        let urn = project.urn();
+        let peer = *storage.peer_id();
+        let seed = test::seed::Seed::spawn();
+        let metadata = project::get(&storage, &urn).unwrap().unwrap();
+        let monorepo = git::Repository::open_bare(storage.path()).unwrap();
+
+        // A branch published to the seed.
+        seed.request_pull(storage.read_only(), &urn);
+
+        let repo = seed.repository(&urn);
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+        repo.reference(
+            &format!("refs/remotes/{}/heads/master", peer),
+            oid,
+            true,
+            "",
+        )
+        .unwrap();
+
+        let peers = show_remote(&metadata, &monorepo, &seed.git_url()).unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].id, peer);
+        assert_eq!(peers[0].branches.len(), 1);
+        assert_eq!(peers[0].branches[0].name, "master");
+        assert_eq!(peers[0].branches[0].head, oid);
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+use librad::git::refs::Refs;
+use librad::git::storage::ReadOnly;
+use librad::PeerId;
+
+use radicle_common::args::{Args, Error, Help};
+use radicle_common::refs::is_signed;
+use radicle_common::{git, keys, profile, project, Urn};
+use radicle_terminal as term;
+
+pub const HELP: Help = Help {
+    name: "verify",
+    description: env!("CARGO_PKG_DESCRIPTION"),
+    version: env!("CARGO_PKG_VERSION"),
+    usage: r#"
+Usage
+
+    rad verify [<urn>] [--peer <peer-id>] [<option>...]
+
+    Checks that the refs of each tracked peer of a project match the refs signed by
+    that peer, in their `rad/signed_refs`. Refs that differ from their signed value,
+    signed refs that are missing, and branches, tags or notes that aren't signed are
+    reported. Identity refs and collaborative objects are signed by other means, and
+    aren't checked. Peers whose key was revoked by a key rotation in one of the local
+    profiles fail verification.
+
+    If no URN is specified, the project in the current working directory is verified.
+    The command exits with a non-zero status if any peer fails verification.
+
+Options
+
+    --peer <peer-id>    Only verify the given peer
+    --help              Print help
+"#,
+};
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub urn: Option<Urn>,
+    pub peer: Option<PeerId>,
+}
+
+impl Args for Options {
+    fn from_args(args: Vec<OsString>) -> anyhow::Result<(Self, Vec<OsString>)> {
+        use lexopt::prelude::*;
+
+        let mut parser = lexopt::Parser::from_args(args);
+        let mut urn = None;
+        let mut peer = None;
+
+        while let Some(arg) = parser.next()? {
+            match arg {
+                Long("peer") => {
+                    let value = parser.value()?;
+                    let value = value.to_string_lossy();
+
+                    peer = Some(
+                        PeerId::from_str(&value)
+                            .map_err(|_| anyhow!("invalid peer id '{}'", value))?,
+                    );
+                }
+                Long("help") => {
+                    return Err(Error::Help.into());
+                }
+                Value(val) if urn.is_none() => {
+                    let val = val.to_string_lossy();
+                    urn = Some(Urn::from_str(&val).map_err(|_| anyhow!("invalid URN '{}'", val))?);
+                }
+                _ => return Err(anyhow!(arg.unexpected())),
+            }
+        }
+
+        Ok((Options { urn, peer }, vec![]))
+    }
+}
+
+/// Problem found with a ref.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// The ref doesn't point to the signed commit.
+    Mismatch {
+        name: String,
+        signed: String,
+        actual: String,
+    },
+    /// The ref is signed, but not in storage.
+    Missing { name: String },
+    /// The ref is in storage, but not signed.
+    Unsigned { name: String },
+    /// The peer key was revoked in a local profile.
+    Revoked,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mismatch {
+                name,
+                signed,
+                actual,
+            } => write!(
+                f,
+                "{} points to {}, but {} is signed",
+                name,
+                &actual[..7],
+                &signed[..7]
+            ),
+            Self::Missing { name } => write!(f, "{} is signed, but missing", name),
+            Self::Unsigned { name } => write!(f, "{} is not signed", name),
+            Self::Revoked => write!(f, "key was revoked by a key rotation"),
+        }
+    }
+}
+
+/// Verification report of a peer.
+#[derive(Debug)]
+pub struct Report {
+    pub peer: PeerId,
+    /// Number of refs matching their signed value.
+    pub verified: usize,
+    /// Problems found.
+    pub issues: Vec<Issue>,
+    /// Error loading the peer's signed refs, eg. if the signature is invalid.
+    pub error: Option<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty() && self.error.is_none()
+    }
+}
+
+pub fn run(options: Options, ctx: impl term::Context) -> anyhow::Result<()> {
+    let profile = ctx.profile()?;
+    let storage = profile::read_only(&profile)?;
+    let urn = match options.urn {
+        Some(urn) => urn,
+        None => project::cwd().map(|(urn, _)| urn)?,
+    };
+    let proj = project::get(&storage, &urn)?
+        .ok_or_else(|| anyhow!("project {} not found in local storage", urn))?;
+
+    let peers = match options.peer {
+        Some(peer) => vec![peer],
+        None => {
+            let mut peers = project::tracked(&proj, &storage)?
+                .into_keys()
+                .collect::<Vec<_>>();
+            peers.sort_by_key(|p| p.default_encoding());
+            peers
+        }
+    };
+    if peers.is_empty() {
+        term::info!(
+            "No tracked peers to verify for {}",
+            term::format::highlight(&urn)
+        );
+        return Ok(());
+    }
+
+    let revoked = keys::revoked_locally();
+    let mut failed = 0;
+    for peer in peers {
+        let mut report = verify(&storage, &urn, peer)?;
+        if revoked.contains(&peer) {
+            report.issues.push(Issue::Revoked);
+        }
+        let name = project::PeerInfo::get(&peer, &proj, &storage).name();
+
+        if report.is_ok() {
+            term::success!(
+                "{} {} {}",
+                term::format::tertiary(peer),
+                term::format::bold(name),
+                term::format::dim(format!("({} signed ref(s) verified)", report.verified))
+            );
+        } else {
+            failed += 1;
+            term::info!(
+                "{} {} {} {}",
+                term::format::negative("✗"),
+                term::format::tertiary(peer),
+                term::format::bold(name),
+                term::format::negative("verification failed")
+            );
+            if let Some(err) = &report.error {
+                term::indented(&term::format::negative(err));
+            }
+            for issue in &report.issues {
+                term::indented(&term::format::negative(issue));
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} peer(s) failed verification", failed);
+    }
+    Ok(())
+}
+
+/// Verify the refs of a peer against its signed refs.
+pub fn verify(storage: &ReadOnly, urn: &Urn, peer: PeerId) -> anyhow::Result<Report> {
+    let mut report = Report {
+        peer,
+        verified: 0,
+        issues: vec![],
+        error: None,
+    };
+    let peer = if &peer == storage.peer_id() {
+        None
+    } else {
+        Some(peer)
+    };
+    // Nb. Loading the signed refs checks their signature.
+    let signed = match Refs::load(storage, urn, peer) {
+        Ok(Some(refs)) => refs
+            .iter_categorised()
+            .map(|((name, oid), category)| (format!("{}/{}", category, name), oid.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        Ok(None) => BTreeMap::new(),
+        Err(err) => {
+            report.error = Some(format!("invalid signed refs: {}", err));
+            BTreeMap::new()
+        }
+    };
+    let actual = refs(storage, urn, peer)?;
+
+    for (name, oid) in &signed {
+        match actual.get(name) {
+            Some(actual) if actual == oid => report.verified += 1,
+            Some(actual) => report.issues.push(Issue::Mismatch {
+                name: name.clone(),
+                signed: oid.clone(),
+                actual: actual.clone(),
+            }),
+            None => report.issues.push(Issue::Missing { name: name.clone() }),
+        }
+    }
+    for name in actual.keys() {
+        // Only some categories of refs are signed: identity refs, collaborative objects
+        // and the signed refs themselves are not.
+        if !signed.contains_key(name) && is_signed(name) {
+            report.issues.push(Issue::Unsigned { name: name.clone() });
+        }
+    }
+    Ok(report)
+}
+
+/// Get the refs of a peer in storage, relative to the peer, eg. `heads/master`.
+/// If no peer is given, the local refs are returned.
+fn refs(
+    storage: &ReadOnly,
+    urn: &Urn,
+    peer: Option<PeerId>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    let repo = git::Repository::open_bare(storage.path())?;
+    let prefix = match peer {
+        Some(peer) => format!("refs/namespaces/{}/refs/remotes/{}/", urn.encode_id(), peer),
+        None => format!("refs/namespaces/{}/refs/", urn.encode_id()),
+    };
+    let mut refs = BTreeMap::new();
+
+    for r in repo.references_glob(&format!("{}*", prefix))? {
+        let r = r?;
+        if let (Some(name), Some(oid)) = (r.name(), r.target()) {
+            let name = &name[prefix.len()..];
+            if peer.is_none() && name.starts_with("remotes/") {
+                continue;
+            }
+            refs.insert(name.to_owned(), oid.to_string());
+        }
+    }
+    Ok(refs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use librad::git::Storage;
+    use librad::profile::Profile;
+    use radicle_common::test;
+
+    /// Setup a local peer with the refs of a remote peer's project, which has a signed
+    /// branch.
+    fn setup() -> (Storage, Profile, Urn, PeerId, git::Oid) {
+        let (remote, _, _, project) = test::setup::profile();
+        let (local, profile, _, _) = test::setup::profile();
+        let urn = project.urn();
+        let peer = *remote.peer_id();
+        let namespace = format!("refs/namespaces/{}/refs", urn.encode_id());
+
+        let repo = git::Repository::open_bare(remote.path()).unwrap();
+        let sig = git::Signature::now("anonymous", "anonymous@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &sig, &sig, "Initial commit", &tree, &[])
+            .unwrap();
+        repo.reference(&format!("{}/heads/master", namespace), oid, true, "")
+            .unwrap();
+        Refs::update(&remote, &urn).unwrap();
+
+        git::git(
+            local.path(),
+            [
+                "fetch".to_owned(),
+                "--quiet".to_owned(),
+                remote.path().display().to_string(),
+                format!("{}/*:{}/remotes/{}/*", namespace, namespace, peer),
+                format!("{}/rad/id:{}/rad/id", namespace, namespace),
+                format!("{}/rad/ids/*:{}/rad/ids/*", namespace, namespace),
+            ],
+        )
+        .unwrap();
+
+        (local, profile, urn, peer, oid)
+    }
+
+    /// Point a ref of the given peer to the given commit.
+    fn set_ref(storage: &Storage, urn: &Urn, peer: PeerId, name: &str, oid: git::Oid) {
+        let repo = git::Repository::open_bare(storage.path()).unwrap();
+        repo.reference(
+            &format!(
+                "refs/namespaces/{}/refs/remotes/{}/{}",
+                urn.encode_id(),
+                peer,
+                name
+            ),
+            oid,
+            true,
+            "",
+        )
+        .unwrap();
+    }
+
+    /// Create a commit that isn't signed by anyone.
+    fn commit(storage: &Storage) -> git::Oid {
+        let repo = git::Repository::open_bare(storage.path()).unwrap();
+        let sig = git::Signature::now("eve", "eve@radicle.xyz").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+
+        repo.commit(None, &sig, &sig, "Tampered", &tree, &[])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_verify() {
+        let (local, _, urn, peer, oid) = setup();
+
+        // Collaborative objects aren't signed, but aren't reported.
+        set_ref(&local, &urn, peer, "cobs/xyz.radicle.issue/1", oid);
+
+        let report = verify(local.read_only(), &urn, peer).unwrap();
+        assert!(report.is_ok(), "{:?}", report.issues);
+        assert_eq!(report.verified, 1);
+    }
+
+    #[test]
+    fn test_verify_tampered() {
+        let (local, _, urn, peer, oid) = setup();
+        let tampered = commit(&local);
+
+        set_ref(&local, &urn, peer, "heads/master", tampered);
+
+        let report = verify(local.read_only(), &urn, peer).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.issues,
+            vec![Issue::Mismatch {
+                name: "heads/master".to_owned(),
+                signed: oid.to_string(),
+                actual: tampered.to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_verify_unsigned() {
+        let (local, _, urn, peer, _) = setup();
+        let unsigned = commit(&local);
+
+        set_ref(&local, &urn, peer, "heads/unsigned", unsigned);
+
+        let report = verify(local.read_only(), &urn, peer).unwrap();
+        assert_eq!(
+            report.issues,
+            vec![Issue::Unsigned {
+                name: "heads/unsigned".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_run_fails_on_mismatch() {
+        let (local, profile, urn, peer, _) = setup();
+        let options = || Options {
+            urn: Some(urn.clone()),
+            peer: Some(peer),
+        };
+
+        run(options(), profile.clone()).unwrap();
+
+        set_ref(&local, &urn, peer, "heads/master", commit(&local));
+
+        assert!(run(options(), profile).is_err());
+    }
+}